@@ -0,0 +1,137 @@
+use crate::did::error::DidError;
+use crate::did::resolver::DidResolverRegistry;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, Responder};
+use log::error;
+use serde_json::json;
+
+const DID_RESOLUTION_CONTEXT: &str = "https://w3id.org/did-resolution/v1";
+const DID_JSON_CONTENT_TYPE: &str = "application/did+json";
+
+// DID Resolution のエラーコードと HTTP ステータス
+fn map_did_error(error: &DidError) -> (StatusCode, &'static str) {
+    match error {
+        DidError::InvalidDid(_) => (StatusCode::BAD_REQUEST, "invalidDid"),
+        DidError::UnsupportedMethod(_) => (StatusCode::NOT_IMPLEMENTED, "methodNotSupported"),
+        DidError::UnsupportedKeyType(_) => (StatusCode::BAD_REQUEST, "unsupportedPublicKeyType"),
+        DidError::InvalidPublicKey(_) => (StatusCode::BAD_REQUEST, "invalidPublicKey"),
+        DidError::VerificationMethodNotFound(_) => (StatusCode::NOT_FOUND, "notFound"),
+        DidError::ResolutionFailed(_) => (StatusCode::NOT_FOUND, "notFound"),
+        DidError::InvalidDocument(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internalError"),
+    }
+}
+
+// DID を解決し、DID URL（%23 でエンコードしたフラグメント付き）の場合は検証メソッドを返す
+// did:web の取得はブロッキングの HTTP リクエストなので、ワーカーのスレッドではなく web::block で実行する
+pub async fn resolve_did(
+    did_resolver: web::Data<DidResolverRegistry>,
+    did: web::Path<String>,
+) -> impl Responder {
+    let did = did.into_inner();
+    let is_did_url = did.contains('#');
+    let result = web::block({
+        let did = did.clone();
+        move || {
+            if is_did_url {
+                did_resolver.dereference(&did).map(|method| {
+                    json!({
+                        "@context": DID_RESOLUTION_CONTEXT,
+                        "contentStream": method,
+                        "dereferencingMetadata": { "contentType": DID_JSON_CONTENT_TYPE },
+                        "contentMetadata": {},
+                    })
+                })
+            } else {
+                did_resolver.resolve(&did).map(|document| {
+                    json!({
+                        "@context": DID_RESOLUTION_CONTEXT,
+                        "didDocument": document,
+                        "didResolutionMetadata": { "contentType": DID_JSON_CONTENT_TYPE },
+                        "didDocumentMetadata": {},
+                    })
+                })
+            }
+        }
+    })
+    .await;
+
+    let (status, code, message) = match result {
+        Ok(Ok(body)) => return HttpResponse::Ok().json(body),
+        Ok(Err(e)) => {
+            let (status, code) = map_did_error(&e);
+            (status, code, e.to_string())
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internalError",
+            e.to_string(),
+        ),
+    };
+    error!("Failed to resolve {}: {}", did, message);
+    let metadata = if is_did_url {
+        "dereferencingMetadata"
+    } else {
+        "didResolutionMetadata"
+    };
+    HttpResponse::build(status).json(json!({
+        "@context": DID_RESOLUTION_CONTEXT,
+        metadata: { "error": code, "message": message },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::key;
+    use crate::utils::signer::{SignatureAlgorithm, SigningKey};
+    use actix_web::{test, App};
+
+    #[actix_rt::test]
+    async fn test_resolve_did_api() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(DidResolverRegistry::default()))
+                .route("/resolver/{did}", web::get().to(resolve_did)),
+        )
+        .await;
+
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let did = key::did_key(&key.public_key()).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/resolver/{}", did))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["didDocument"]["id"], did);
+        assert_eq!(
+            body["didResolutionMetadata"]["contentType"],
+            "application/did+json"
+        );
+
+        // フラグメントは %23 でエンコードして渡す
+        let key_id = key::key_id(&did).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/resolver/{}", key_id.replace('#', "%23")))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["contentStream"]["id"], key_id);
+
+        for (did, status, error) in [
+            ("did:example:123", 501, "methodNotSupported"),
+            ("example", 400, "invalidDid"),
+            // 公開されていないアドレスの did:web は取得しない
+            ("did:web:127.0.0.1%3A9", 404, "notFound"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/resolver/{}", did))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status().as_u16(), status);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["didResolutionMetadata"]["error"], error);
+        }
+    }
+}
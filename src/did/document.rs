@@ -0,0 +1,74 @@
+use crate::did::error::DidError;
+use crate::did::key;
+use crate::models::did::{DidDocument, VerificationMethod};
+use crate::utils::jwk;
+use crate::utils::signer::VerifyingKey;
+
+pub const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+pub const JWS_2020_CONTEXT: &str = "https://w3id.org/security/suites/jws-2020/v1";
+
+const MULTIKEY_TYPE: &str = "Multikey";
+
+// 検証メソッドの公開鍵（publicKeyJwk、または Multikey の publicKeyMultibase）
+pub fn public_key(method: &VerificationMethod) -> Result<VerifyingKey, DidError> {
+    if let Some(public_key_jwk) = &method.public_key_jwk {
+        return jwk::from_jwk(public_key_jwk)
+            .map_err(|e| DidError::InvalidPublicKey(e.to_string()));
+    }
+    match (&method.public_key_multibase, method.method_type.as_str()) {
+        (Some(multibase), MULTIKEY_TYPE) => key::decode_multikey(multibase),
+        _ => Err(DidError::UnsupportedKeyType(format!(
+            "{} ({})",
+            method.id, method.method_type
+        ))),
+    }
+}
+
+// DID URL、または "#key-1" のような相対参照が指す検証メソッド
+pub fn find_verification_method<'a>(
+    document: &'a DidDocument,
+    id: &str,
+) -> Result<&'a VerificationMethod, DidError> {
+    let id = absolute_id(&document.id, id);
+    document
+        .verification_method
+        .iter()
+        .find(|method| absolute_id(&document.id, &method.id) == id)
+        .ok_or(DidError::VerificationMethodNotFound(id))
+}
+
+// 検証メソッドが assertionMethod（クレデンシャルの署名用）として公開されているか
+pub fn is_assertion_method(document: &DidDocument, id: &str) -> bool {
+    let id = absolute_id(&document.id, id);
+    document
+        .assertion_method
+        .iter()
+        .any(|method| absolute_id(&document.id, method) == id)
+}
+
+// 取得した DID ドキュメントが要求した DID のものであり、検証メソッドがその DID に属することを確認する
+pub fn validate(document: &DidDocument, did: &str) -> Result<(), DidError> {
+    if document.id != did {
+        return Err(DidError::InvalidDocument(format!(
+            "Document id {} does not match {}",
+            document.id, did
+        )));
+    }
+    for method in &document.verification_method {
+        if !absolute_id(did, &method.id).starts_with(&format!("{}#", did)) {
+            return Err(DidError::InvalidDocument(format!(
+                "Verification method {} does not belong to {}",
+                method.id, did
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn absolute_id(did: &str, id: &str) -> String {
+    if id.starts_with('#') {
+        format!("{}{}", did, id)
+    } else {
+        id.to_string()
+    }
+}
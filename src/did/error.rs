@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DidError {
+    #[error("Invalid DID: {0}")]
+    InvalidDid(String),
+    #[error("Unsupported DID method: {0}")]
+    UnsupportedMethod(String),
+    #[error("Unsupported key type: {0}")]
+    UnsupportedKeyType(String),
+    #[error("Invalid public key: {0}")]
+    InvalidPublicKey(String),
+    #[error("Verification method not found: {0}")]
+    VerificationMethodNotFound(String),
+    #[error("Invalid DID document: {0}")]
+    InvalidDocument(String),
+    #[error("DID resolution failed: {0}")]
+    ResolutionFailed(String),
+}
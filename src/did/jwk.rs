@@ -0,0 +1,114 @@
+use crate::did::document::{self, DID_CONTEXT, JWS_2020_CONTEXT};
+use crate::did::error::DidError;
+use crate::models::did::{DidDocument, VerificationMethod};
+use crate::utils::jwk;
+use crate::utils::signer::VerifyingKey;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_json::Value;
+
+const DID_JWK_PREFIX: &str = "did:jwk:";
+const JWK_TYPE: &str = "JsonWebKey2020";
+// did:jwk の検証メソッドは常に一つで、フラグメントは 0
+const KEY_FRAGMENT: &str = "#0";
+
+pub fn is_did_jwk(did: &str) -> bool {
+    did.starts_with(DID_JWK_PREFIX)
+}
+
+// did:jwk は公開鍵の JWK（JSON）を base64url で符号化したもので、
+// did:key と同様に DID 自体に含まれる JWK から DID ドキュメントを導出する
+pub fn resolve(did: &str) -> Result<DidDocument, DidError> {
+    let invalid_did = || DidError::InvalidDid(did.to_string());
+    let encoded = did.strip_prefix(DID_JWK_PREFIX).ok_or_else(invalid_did)?;
+    let public_key_jwk: Value = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .filter(Value::is_object)
+        .ok_or_else(invalid_did)?;
+
+    // 秘密鍵を含む JWK や暗号化用の鍵は署名の検証に使わない
+    if public_key_jwk.get("d").is_some() {
+        return Err(DidError::InvalidPublicKey(format!(
+            "{} contains a private key",
+            did
+        )));
+    }
+    if public_key_jwk["use"] == "enc" {
+        return Err(DidError::UnsupportedKeyType(format!(
+            "{} is an encryption key",
+            did
+        )));
+    }
+    jwk::from_jwk(&public_key_jwk).map_err(|e| DidError::InvalidPublicKey(e.to_string()))?;
+
+    let id = format!("{}{}", did, KEY_FRAGMENT);
+    Ok(DidDocument {
+        context: vec![DID_CONTEXT.to_string(), JWS_2020_CONTEXT.to_string()],
+        id: did.to_string(),
+        verification_method: vec![VerificationMethod {
+            id: id.clone(),
+            method_type: JWK_TYPE.to_string(),
+            controller: did.to_string(),
+            public_key_multibase: None,
+            public_key_jwk: Some(public_key_jwk),
+        }],
+        authentication: vec![id.clone()],
+        assertion_method: vec![id],
+    })
+}
+
+// DID URL（did:jwk:...#0）が指す検証メソッドの公開鍵
+pub fn resolve_public_key(did_url: &str) -> Result<VerifyingKey, DidError> {
+    let (did, _) = did_url
+        .split_once('#')
+        .ok_or_else(|| DidError::InvalidDid(did_url.to_string()))?;
+    let document = resolve(did)?;
+    document::public_key(document::find_verification_method(&document, did_url)?)
+}
+
+#[cfg(test)]
+pub mod test_did_jwk {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use serde_json::Value;
+
+    pub fn did_jwk(jwk: &Value) -> String {
+        format!("did:jwk:{}", URL_SAFE_NO_PAD.encode(jwk.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_did_jwk::did_jwk;
+    use super::*;
+    use crate::utils::signer::{SignatureAlgorithm, SigningKey};
+
+    #[test]
+    fn test_did_jwk_resolution() {
+        for algorithm in SignatureAlgorithm::ALL {
+            let key = SigningKey::generate(algorithm).unwrap();
+            let did = did_jwk(&jwk::to_jwk(&key.public_key()));
+            let document = resolve(&did).unwrap();
+            let key_id = format!("{}#0", did);
+            assert_eq!(document.id, did);
+            assert_eq!(document.verification_method[0].id, key_id);
+            assert_eq!(document.assertion_method, vec![key_id.clone()]);
+            assert_eq!(resolve_public_key(&key_id).unwrap(), key.public_key());
+        }
+
+        let key = p256::ecdsa::SigningKey::random(&mut rand_core::OsRng);
+        let mut private_jwk = jwk::to_jwk(&SigningKey::P256(key.clone()).public_key());
+        private_jwk["d"] = URL_SAFE_NO_PAD.encode(key.to_bytes()).into();
+        let result = resolve(&did_jwk(&private_jwk));
+        assert!(matches!(result, Err(DidError::InvalidPublicKey(_))));
+
+        let mut encryption_jwk = jwk::to_jwk(&SigningKey::P256(key).public_key());
+        encryption_jwk["use"] = "enc".into();
+        let result = resolve(&did_jwk(&encryption_jwk));
+        assert!(matches!(result, Err(DidError::UnsupportedKeyType(_))));
+
+        for did in ["did:jwk:!!!", "did:jwk:WzFd", "did:key:z6Mk"] {
+            assert!(matches!(resolve(did), Err(DidError::InvalidDid(_))));
+        }
+    }
+}
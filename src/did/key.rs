@@ -0,0 +1,157 @@
+use crate::did::document::{self, DID_CONTEXT};
+use crate::did::error::DidError;
+use crate::models::did::{DidDocument, VerificationMethod};
+use crate::utils::signer::VerifyingKey;
+use ed25519_dalek::PublicKey;
+use rsa::pkcs1::{DecodeRsaPublicKey, EncodeRsaPublicKey};
+use rsa::RsaPublicKey;
+
+const DID_KEY_PREFIX: &str = "did:key:";
+const MULTIKEY_CONTEXT: &str = "https://w3id.org/security/multikey/v1";
+const MULTIKEY_TYPE: &str = "Multikey";
+
+// multibase の base58btc（RSA の鍵も扱えるよう長さに制限のない bs58 で符号化する）
+const BASE58_BTC: char = 'z';
+
+// 公開鍵の multicodec（unsigned varint で符号化したもの）
+const ED25519_PUB: [u8; 2] = [0xed, 0x01];
+const P256_PUB: [u8; 2] = [0x80, 0x24];
+const P384_PUB: [u8; 2] = [0x81, 0x24];
+const RSA_PUB: [u8; 2] = [0x85, 0x24];
+
+// multicodec の接頭辞を付けた公開鍵（ECDSA は圧縮した SEC1、RSA は PKCS#1 DER）の base58btc
+pub fn encode_multikey(public_key: &VerifyingKey) -> Result<String, DidError> {
+    let bytes = match public_key {
+        VerifyingKey::Ed25519(key) => [ED25519_PUB.as_slice(), key.as_bytes()].concat(),
+        VerifyingKey::P256(key) => {
+            [P256_PUB.as_slice(), key.to_encoded_point(true).as_bytes()].concat()
+        }
+        VerifyingKey::P384(key) => {
+            [P384_PUB.as_slice(), key.to_encoded_point(true).as_bytes()].concat()
+        }
+        VerifyingKey::Rsa(key) => {
+            let der = key
+                .to_pkcs1_der()
+                .map_err(|e| DidError::InvalidPublicKey(e.to_string()))?;
+            [RSA_PUB.as_slice(), der.as_bytes()].concat()
+        }
+    };
+    Ok(format!(
+        "{}{}",
+        BASE58_BTC,
+        bs58::encode(bytes).into_string()
+    ))
+}
+
+pub fn decode_multikey(multibase: &str) -> Result<VerifyingKey, DidError> {
+    let bytes = multibase
+        .strip_prefix(BASE58_BTC)
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .ok_or_else(|| DidError::InvalidPublicKey(format!("Invalid multibase: {}", multibase)))?;
+    if bytes.len() < 2 {
+        return Err(DidError::InvalidPublicKey(multibase.to_string()));
+    }
+
+    let invalid_key = |e: String| DidError::InvalidPublicKey(e);
+    let (codec, key) = bytes.split_at(2);
+    match [codec[0], codec[1]] {
+        ED25519_PUB => PublicKey::from_bytes(key)
+            .map(VerifyingKey::Ed25519)
+            .map_err(|e| invalid_key(e.to_string())),
+        P256_PUB => p256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+            .map(VerifyingKey::P256)
+            .map_err(|e| invalid_key(e.to_string())),
+        P384_PUB => p384::ecdsa::VerifyingKey::from_sec1_bytes(key)
+            .map(VerifyingKey::P384)
+            .map_err(|e| invalid_key(e.to_string())),
+        RSA_PUB => RsaPublicKey::from_pkcs1_der(key)
+            .map(VerifyingKey::Rsa)
+            .map_err(|e| invalid_key(e.to_string())),
+        _ => Err(DidError::UnsupportedKeyType(format!(
+            "Unknown multicodec: {:02x}{:02x}",
+            codec[0], codec[1]
+        ))),
+    }
+}
+
+pub fn did_key(public_key: &VerifyingKey) -> Result<String, DidError> {
+    Ok(format!(
+        "{}{}",
+        DID_KEY_PREFIX,
+        encode_multikey(public_key)?
+    ))
+}
+
+// did:key の検証メソッドの ID はフラグメントに multibase の値をそのまま使う
+pub fn key_id(did: &str) -> Result<String, DidError> {
+    let multibase = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .ok_or_else(|| DidError::InvalidDid(did.to_string()))?;
+    Ok(format!("{}#{}", did, multibase))
+}
+
+pub fn is_did_key(did: &str) -> bool {
+    did.starts_with(DID_KEY_PREFIX)
+}
+
+// did:key は DID 自体に公開鍵を含むため、外部に問い合わせずに DID ドキュメントを導出できる
+pub fn resolve(did: &str) -> Result<DidDocument, DidError> {
+    let multibase = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .ok_or_else(|| DidError::InvalidDid(did.to_string()))?;
+    decode_multikey(multibase).map_err(|e| DidError::InvalidDid(format!("{}: {}", did, e)))?;
+
+    let id = key_id(did)?;
+    Ok(DidDocument {
+        context: vec![DID_CONTEXT.to_string(), MULTIKEY_CONTEXT.to_string()],
+        id: did.to_string(),
+        verification_method: vec![VerificationMethod {
+            id: id.clone(),
+            method_type: MULTIKEY_TYPE.to_string(),
+            controller: did.to_string(),
+            public_key_multibase: Some(multibase.to_string()),
+            public_key_jwk: None,
+        }],
+        authentication: vec![id.clone()],
+        assertion_method: vec![id],
+    })
+}
+
+// DID URL（did:key:...#...）が指す検証メソッドの公開鍵
+pub fn resolve_public_key(did_url: &str) -> Result<VerifyingKey, DidError> {
+    let (did, _) = did_url
+        .split_once('#')
+        .ok_or_else(|| DidError::InvalidDid(did_url.to_string()))?;
+    let document = resolve(did)?;
+    document::public_key(document::find_verification_method(&document, did_url)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::signer::{SignatureAlgorithm, SigningKey};
+
+    #[test]
+    fn test_did_key_resolution() {
+        let prefixes = ["did:key:z6Mk", "did:key:zDn", "did:key:z82", "did:key:z4MX"];
+        for (algorithm, prefix) in SignatureAlgorithm::ALL.into_iter().zip(prefixes) {
+            let key = SigningKey::generate(algorithm).unwrap();
+            let did = did_key(&key.public_key()).unwrap();
+            assert!(did.starts_with(prefix), "{}", did);
+
+            let document = resolve(&did).unwrap();
+            let key_id = key_id(&did).unwrap();
+            assert_eq!(document.id, did);
+            assert_eq!(document.verification_method[0].id, key_id);
+            assert_eq!(document.verification_method[0].controller, did);
+            assert_eq!(document.assertion_method, vec![key_id.clone()]);
+            assert_eq!(resolve_public_key(&key_id).unwrap(), key.public_key());
+        }
+
+        assert!(resolve("did:example:123").is_err());
+        assert!(resolve("did:key:z6MkInvalid").is_err());
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let did = did_key(&key.public_key()).unwrap();
+        assert!(resolve_public_key(&format!("{}#key-1", did)).is_err());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod api;
+pub mod document;
+pub mod error;
+pub mod jwk;
+pub mod key;
+pub mod resolver;
+pub mod web;
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::did::document;
+use crate::did::error::DidError;
+use crate::did::web::DidWebResolver;
+use crate::did::{jwk, key};
+use crate::models::did::{DidDocument, VerificationMethod};
+use log::debug;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+// DID メソッドごとに DID から DID ドキュメントを解決する
+pub trait DidResolver: Send + Sync + Debug {
+    fn resolve(&self, did: &str) -> Result<DidDocument, DidError>;
+}
+
+#[derive(Debug)]
+pub struct DidKeyResolver;
+
+impl DidResolver for DidKeyResolver {
+    fn resolve(&self, did: &str) -> Result<DidDocument, DidError> {
+        key::resolve(did)
+    }
+}
+
+#[derive(Debug)]
+pub struct DidJwkResolver;
+
+impl DidResolver for DidJwkResolver {
+    fn resolve(&self, did: &str) -> Result<DidDocument, DidError> {
+        jwk::resolve(did)
+    }
+}
+
+// DID メソッド名（did:<method>:...）からリゾルバを選んで解決する
+// 既定では did:key、did:jwk、did:web に対応する
+#[derive(Debug)]
+pub struct DidResolverRegistry {
+    resolvers: HashMap<String, Arc<dyn DidResolver>>,
+}
+
+impl Default for DidResolverRegistry {
+    fn default() -> Self {
+        DidResolverRegistry {
+            resolvers: HashMap::new(),
+        }
+        .with_method("key", Arc::new(DidKeyResolver))
+        .with_method("jwk", Arc::new(DidJwkResolver))
+        .with_method("web", Arc::new(DidWebResolver::default()))
+    }
+}
+
+impl DidResolverRegistry {
+    // 同じメソッドのリゾルバが登録されていれば置き換える
+    pub fn with_method(mut self, method: &str, resolver: Arc<dyn DidResolver>) -> Self {
+        self.resolvers.insert(method.to_string(), resolver);
+        self
+    }
+
+    pub fn resolve(&self, did: &str) -> Result<DidDocument, DidError> {
+        let method = did_method(did)?;
+        let resolver = self
+            .resolvers
+            .get(method)
+            .ok_or_else(|| DidError::UnsupportedMethod(method.to_string()))?;
+
+        debug!("Resolving {} with did:{} resolver", did, method);
+        let document = resolver.resolve(did)?;
+        document::validate(&document, did)?;
+        Ok(document)
+    }
+
+    // DID URL（did:...#fragment）が指す検証メソッド
+    pub fn dereference(&self, did_url: &str) -> Result<VerificationMethod, DidError> {
+        let (did, fragment) = did_url
+            .split_once('#')
+            .ok_or_else(|| DidError::InvalidDid(did_url.to_string()))?;
+        if fragment.is_empty() {
+            return Err(DidError::InvalidDid(did_url.to_string()));
+        }
+
+        let document = self.resolve(did)?;
+        document::find_verification_method(&document, did_url).cloned()
+    }
+}
+
+// did:<method>:<method-specific-id> のメソッド名（DID URL のパス、クエリ、フラグメントは含まない）
+fn did_method(did: &str) -> Result<&str, DidError> {
+    let invalid_did = || DidError::InvalidDid(did.to_string());
+    let (method, id) = did
+        .strip_prefix("did:")
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or_else(invalid_did)?;
+    let is_valid_method = !method.is_empty()
+        && method
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+    if !is_valid_method || id.is_empty() || id.contains(['/', '?', '#']) {
+        return Err(invalid_did());
+    }
+    Ok(method)
+}
+
+#[cfg(test)]
+pub mod test_resolver {
+    use super::*;
+    use std::sync::Mutex;
+
+    // あらかじめ登録した DID ドキュメントを返すだけのリゾルバ
+    #[derive(Debug)]
+    pub struct StaticDidResolver {
+        documents: Mutex<HashMap<String, DidDocument>>,
+    }
+
+    impl StaticDidResolver {
+        pub fn new() -> Self {
+            StaticDidResolver {
+                documents: Mutex::new(HashMap::new()),
+            }
+        }
+
+        pub fn insert(&self, document: DidDocument) {
+            self.documents
+                .lock()
+                .unwrap()
+                .insert(document.id.clone(), document);
+        }
+    }
+
+    impl DidResolver for StaticDidResolver {
+        fn resolve(&self, did: &str) -> Result<DidDocument, DidError> {
+            self.documents
+                .lock()
+                .unwrap()
+                .get(did)
+                .cloned()
+                .ok_or_else(|| DidError::ResolutionFailed(format!("{} not found", did)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_resolver::StaticDidResolver;
+    use super::*;
+    use crate::did::jwk::test_did_jwk::did_jwk;
+    use crate::utils;
+    use crate::utils::signer::{SignatureAlgorithm, SigningKey};
+
+    #[test]
+    fn test_did_resolver_registry() {
+        let registry = DidResolverRegistry::default();
+        for algorithm in [SignatureAlgorithm::EdDSA, SignatureAlgorithm::ES256] {
+            let key = SigningKey::generate(algorithm).unwrap();
+            let did_key = key::did_key(&key.public_key()).unwrap();
+            let did_jwk = did_jwk(&utils::jwk::to_jwk(&key.public_key()));
+            for (did, key_id) in [
+                (did_key.clone(), key::key_id(&did_key).unwrap()),
+                (did_jwk.clone(), format!("{}#0", did_jwk)),
+            ] {
+                assert_eq!(registry.resolve(&did).unwrap().id, did);
+                let method = registry.dereference(&key_id).unwrap();
+                assert_eq!(method.id, key_id);
+                assert_eq!(document::public_key(&method).unwrap(), key.public_key());
+            }
+        }
+
+        let result = registry.resolve("did:example:123");
+        assert!(matches!(result, Err(DidError::UnsupportedMethod(method)) if method == "example"));
+        for did in [
+            "did:key",
+            "did:Key:z6Mk",
+            "example:123",
+            "did:key:z6Mk#key-1",
+        ] {
+            assert!(
+                matches!(registry.resolve(did), Err(DidError::InvalidDid(_))),
+                "{}",
+                did
+            );
+        }
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let did = key::did_key(&key.public_key()).unwrap();
+        let result = registry.dereference(&did);
+        assert!(matches!(result, Err(DidError::InvalidDid(_))));
+        let result = registry.dereference(&format!("{}#key-1", did));
+        assert!(matches!(
+            result,
+            Err(DidError::VerificationMethodNotFound(_))
+        ));
+
+        // 登録したリゾルバの DID ドキュメントも、要求した DID のものでなければ受け入れない
+        let stub = StaticDidResolver::new();
+        let mut document = key::resolve(&did).unwrap();
+        document.id = "did:example:123".to_string();
+        stub.insert(document);
+        let registry = registry.with_method("example", Arc::new(stub));
+        let result = registry.resolve("did:example:123");
+        assert!(matches!(result, Err(DidError::InvalidDocument(_))));
+        let result = registry.resolve("did:example:456");
+        assert!(matches!(result, Err(DidError::ResolutionFailed(_))));
+    }
+}
@@ -0,0 +1,453 @@
+use crate::did::document;
+use crate::did::error::DidError;
+use crate::did::resolver::DidResolver;
+use crate::models::did::DidDocument;
+use log::debug;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DID_WEB_PREFIX: &str = "did:web:";
+const WELL_KNOWN_PATH: &str = ".well-known";
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// DID ドキュメントとして受け付ける大きさの上限
+const MAX_DOCUMENT_SIZE: u64 = 1024 * 1024;
+// キャッシュする DID ドキュメントの数の上限
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+// did:web の DID ドキュメントを HTTPS で取得し、ttl の間はキャッシュする
+// ループバックやプライベートネットワークのアドレスには接続しない
+#[derive(Debug)]
+pub struct DidWebResolver {
+    agent: ureq::Agent,
+    ttl: Duration,
+    scheme: String,
+    max_entries: usize,
+    cache: Mutex<HashMap<String, (Instant, DidDocument)>>,
+}
+
+impl Default for DidWebResolver {
+    fn default() -> Self {
+        DidWebResolver::new(DEFAULT_TTL)
+    }
+}
+
+impl DidWebResolver {
+    pub fn new(ttl: Duration) -> Self {
+        DidWebResolver {
+            agent: agent(false),
+            ttl,
+            scheme: "https".to_string(),
+            max_entries: MAX_CACHE_ENTRIES,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // テストでローカルの HTTP サーバーから取得するためのもの
+    #[cfg(test)]
+    pub fn with_scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    #[cfg(test)]
+    pub fn allow_private_addresses(mut self) -> Self {
+        self.agent = agent(true);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    fn cache_document(&self, did: &str, document: DidDocument) {
+        let mut cache = self.cache.lock().unwrap();
+        if !cache.contains_key(did) && cache.len() >= self.max_entries {
+            // 期限切れのものを削除し、それでも一杯なら最も古いものを削除する
+            cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.ttl);
+            if cache.len() >= self.max_entries {
+                let oldest = cache
+                    .iter()
+                    .min_by_key(|(_, (fetched_at, _))| *fetched_at)
+                    .map(|(did, _)| did.clone());
+                if let Some(oldest) = oldest {
+                    cache.remove(&oldest);
+                }
+            }
+        }
+        cache.insert(did.to_string(), (Instant::now(), document));
+    }
+}
+
+// 名前解決の結果を検査し、公開されていないアドレスには接続しない
+// （リダイレクト先や DNS の応答が変わった場合も同じ検査を通る）
+fn agent(allow_private_addresses: bool) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(REQUEST_TIMEOUT)
+        .resolver(move |netloc: &str| -> io::Result<Vec<SocketAddr>> {
+            let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+            if allow_private_addresses {
+                return Ok(addrs);
+            }
+            match addrs.iter().find(|addr| !is_public_address(&addr.ip())) {
+                Some(addr) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} resolves to a non-public address {}", netloc, addr.ip()),
+                )),
+                None => Ok(addrs),
+            }
+        })
+        .build()
+}
+
+fn is_public_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 100.64.0.0/10（CGNAT）と 0.0.0.0/8
+                || (a == 100 && (b & 0xc0) == 64)
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = embedded_ipv4_address(ip) {
+                return is_public_address(&IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // fc00::/7（ユニークローカル）と fe80::/10（リンクローカル）
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// IPv4 アドレスを埋め込んだ IPv6 アドレス（IPv4 射影、NAT64 の 64:ff9b::/96、6to4 の 2002::/16）
+// の場合は、その IPv4 アドレスで判定する
+fn embedded_ipv4_address(ip: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let octets = ip.octets();
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        Some(ip)
+    } else if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        Some(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ))
+    } else if segments[0] == 0x2002 {
+        Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]))
+    } else {
+        None
+    }
+}
+
+impl DidResolver for DidWebResolver {
+    fn resolve(&self, did: &str) -> Result<DidDocument, DidError> {
+        if let Some((fetched_at, document)) = self.cache.lock().unwrap().get(did) {
+            if fetched_at.elapsed() < self.ttl {
+                debug!("Using cached DID document for {}", did);
+                return Ok(document.clone());
+            }
+        }
+
+        let url = did_web_url(did, &self.scheme)?;
+        debug!("Fetching DID document for {} from {}", did, url);
+        let response = self
+            .agent
+            .get(&url)
+            .set("Accept", "application/did+json, application/json")
+            .call()
+            .map_err(|e| DidError::ResolutionFailed(format!("{}: {}", url, e)))?;
+
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_DOCUMENT_SIZE + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| DidError::ResolutionFailed(format!("{}: {}", url, e)))?;
+        if body.len() as u64 > MAX_DOCUMENT_SIZE {
+            return Err(DidError::InvalidDocument(format!("{} is too large", url)));
+        }
+
+        let document: DidDocument = serde_json::from_slice(&body)
+            .map_err(|e| DidError::InvalidDocument(format!("{}: {}", url, e)))?;
+        document::validate(&document, did)?;
+
+        self.cache_document(did, document.clone());
+        Ok(document)
+    }
+}
+
+pub fn is_did_web(did: &str) -> bool {
+    did.starts_with(DID_WEB_PREFIX)
+}
+
+// did:web:example.com → https://example.com/.well-known/did.json
+// did:web:example.com:user:alice → https://example.com/user/alice/did.json
+// ポート番号のコロンは %3A でエンコードされる（did:web:localhost%3A8080）
+pub fn did_web_url(did: &str, scheme: &str) -> Result<String, DidError> {
+    let invalid_did = || DidError::InvalidDid(did.to_string());
+    let id = did.strip_prefix(DID_WEB_PREFIX).ok_or_else(invalid_did)?;
+    if id.contains(['/', '?', '#']) {
+        return Err(invalid_did());
+    }
+
+    let mut segments = id.split(':');
+    let host = segments
+        .next()
+        .filter(|host| !host.is_empty())
+        .ok_or_else(invalid_did)?
+        .replace("%3A", ":")
+        .replace("%3a", ":");
+    let path: Vec<&str> = segments.collect();
+    if path.iter().any(|segment| segment.is_empty()) {
+        return Err(invalid_did());
+    }
+    let path = if path.is_empty() {
+        WELL_KNOWN_PATH.to_string()
+    } else {
+        path.join("/")
+    };
+
+    let url = format!("{}://{}/{}/did.json", scheme, host, path);
+    url::Url::parse(&url).map_err(|_| invalid_did())?;
+    Ok(url)
+}
+
+#[cfg(test)]
+pub mod test_did_web {
+    use crate::did::document;
+    use crate::utils::jwk;
+    use crate::utils::signer::VerifyingKey;
+
+    // ローカルのモックサーバーの did:web（ポートのコロンは %3A でエンコードする）
+    pub fn mock_did_web(server: &mockito::Server) -> String {
+        format!("did:web:{}", server.host_with_port().replace(':', "%3A"))
+    }
+
+    pub fn did_web_document(did: &str, key: &VerifyingKey) -> serde_json::Value {
+        serde_json::json!({
+            "@context": [document::DID_CONTEXT],
+            "id": did,
+            "verificationMethod": [{
+                "id": format!("{}#key-1", did),
+                "type": "JsonWebKey2020",
+                "controller": did,
+                "publicKeyJwk": jwk::to_jwk(key),
+            }],
+            "assertionMethod": ["#key-1"],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_did_web::{did_web_document, mock_did_web};
+    use super::*;
+    use crate::utils::signer::{SignatureAlgorithm, SigningKey};
+
+    #[test]
+    fn test_did_web_url() {
+        let cases = [
+            (
+                "did:web:example.com",
+                "https://example.com/.well-known/did.json",
+            ),
+            (
+                "did:web:example.com:user:alice",
+                "https://example.com/user/alice/did.json",
+            ),
+            (
+                "did:web:localhost%3A8080",
+                "https://localhost:8080/.well-known/did.json",
+            ),
+        ];
+        for (did, url) in cases {
+            assert_eq!(did_web_url(did, "https").unwrap(), url);
+        }
+
+        for did in [
+            "did:key:z6Mk",
+            "did:web:",
+            "did:web:example.com::alice",
+            "did:web:example.com/alice",
+            "did:web:example.com#key-1",
+        ] {
+            assert!(did_web_url(did, "https").is_err(), "{}", did);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_did_web_resolver() {
+        let mut server = mockito::Server::new_async().await;
+        let did = mock_did_web(&server);
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA)
+            .unwrap()
+            .public_key();
+        let mock = server
+            .mock("GET", "/.well-known/did.json")
+            .with_header("content-type", "application/did+json")
+            .with_body(did_web_document(&did, &key).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        // TTL の間は DID ドキュメントを取得し直さない
+        let resolver = DidWebResolver::new(Duration::from_secs(60))
+            .with_scheme("http")
+            .allow_private_addresses();
+        for _ in 0..2 {
+            let document = resolver.resolve(&did).unwrap();
+            assert_eq!(document.id, did);
+            let method =
+                document::find_verification_method(&document, &format!("{}#key-1", did)).unwrap();
+            assert_eq!(document::public_key(method).unwrap(), key);
+        }
+        mock.assert_async().await;
+
+        // TTL が切れていれば取得し直す
+        let mock = server
+            .mock("GET", "/.well-known/did.json")
+            .with_body(did_web_document(&did, &key).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let resolver = DidWebResolver::new(Duration::ZERO)
+            .with_scheme("http")
+            .allow_private_addresses();
+        for _ in 0..2 {
+            assert!(resolver.resolve(&did).is_ok());
+        }
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_is_public_address() {
+        for ip in [
+            "93.184.215.14",
+            "2606:2800:21f:cb07:6820:80da:af6b:8b2c",
+            "64:ff9b::5db8:d70e",
+            "2002:5db8:d70e::1",
+        ] {
+            assert!(is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a00:1",
+            "2002:7f00:1::",
+            "2002:c0a8:101::1",
+        ] {
+            assert!(!is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_did_web_resolver_rejects_private_addresses() {
+        let mut server = mockito::Server::new_async().await;
+        let did = mock_did_web(&server);
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA)
+            .unwrap()
+            .public_key();
+        let mock = server
+            .mock("GET", "/.well-known/did.json")
+            .with_body(did_web_document(&did, &key).to_string())
+            .expect(0)
+            .create_async()
+            .await;
+
+        // モックサーバーはループバックアドレスなので接続しない
+        let resolver = DidWebResolver::new(Duration::ZERO).with_scheme("http");
+        let result = resolver.resolve(&did);
+        assert!(matches!(result, Err(DidError::ResolutionFailed(_))));
+        mock.assert_async().await;
+    }
+
+    #[actix_rt::test]
+    async fn test_did_web_resolver_bounds_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA)
+            .unwrap()
+            .public_key();
+        let did_a = format!("{}:a", mock_did_web(&server));
+        let did_b = format!("{}:b", mock_did_web(&server));
+        let mock_a = server
+            .mock("GET", "/a/did.json")
+            .with_body(did_web_document(&did_a, &key).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let mock_b = server
+            .mock("GET", "/b/did.json")
+            .with_body(did_web_document(&did_b, &key).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        // 上限を超えると最も古いものから削除される
+        let resolver = DidWebResolver::new(Duration::from_secs(60))
+            .with_scheme("http")
+            .allow_private_addresses()
+            .with_max_entries(1);
+        for did in [&did_a, &did_b, &did_a] {
+            assert!(resolver.resolve(did).is_ok());
+        }
+        assert_eq!(resolver.cache.lock().unwrap().len(), 1);
+        mock_a.assert_async().await;
+        mock_b.assert_async().await;
+    }
+
+    #[actix_rt::test]
+    async fn test_did_web_resolver_rejects_invalid_documents() {
+        let mut server = mockito::Server::new_async().await;
+        let did = mock_did_web(&server);
+        let key = SigningKey::generate(SignatureAlgorithm::EdDSA)
+            .unwrap()
+            .public_key();
+        let resolver = DidWebResolver::new(Duration::ZERO)
+            .with_scheme("http")
+            .allow_private_addresses();
+
+        // 見つからない DID ドキュメント
+        let mock = server
+            .mock("GET", "/.well-known/did.json")
+            .with_status(404)
+            .create_async()
+            .await;
+        let result = resolver.resolve(&did);
+        assert!(matches!(result, Err(DidError::ResolutionFailed(_))));
+        mock.remove_async().await;
+
+        // id が DID と一致しない DID ドキュメント
+        server
+            .mock("GET", "/.well-known/did.json")
+            .with_body(did_web_document("did:web:example.com", &key).to_string())
+            .create_async()
+            .await;
+        let result = resolver.resolve(&did);
+        assert!(matches!(result, Err(DidError::InvalidDocument(_))));
+    }
+}
@@ -23,6 +23,10 @@ pub async fn get_credentials(holder: web::Data<Arc<Holder>>) -> impl Responder {
     }
 }
 
+pub async fn get_public_jwk(holder: web::Data<Arc<Holder>>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "jwk": holder.public_jwk() }))
+}
+
 pub async fn create_presentation(holder: web::Data<Arc<Holder>>, request: web::Json<PresentationRequest>) -> impl Responder {
     match holder.create_presentation(request.into_inner()) {
         Ok(presentation) => HttpResponse::Ok().json(presentation),
@@ -6,6 +6,8 @@ pub enum HolderError {
     SerializationError(String),
     CredentialNotFound(String),
     ProofCreationError(String),
+    KeyBindingError(String),
+    SelectiveDisclosureError(String),
 }
 
 impl fmt::Display for HolderError {
@@ -15,6 +17,10 @@ impl fmt::Display for HolderError {
             HolderError::SerializationError(msg) => write!(f, "Serialization Error: {}", msg),
             HolderError::CredentialNotFound(id) => write!(f, "Credential Not Found: {}", id),
             HolderError::ProofCreationError(msg) => write!(f, "Proof Creation Error: {}", msg),
+            HolderError::KeyBindingError(msg) => write!(f, "Key Binding Error: {}", msg),
+            HolderError::SelectiveDisclosureError(msg) => {
+                write!(f, "Selective Disclosure Error: {}", msg)
+            }
         }
     }
 }
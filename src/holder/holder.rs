@@ -1,7 +1,12 @@
 use super::storage::Storage;
+use crate::did;
 use crate::holder::error::HolderError;
 use crate::models::credential::{CredentialResponse, PresentationRequest, VerifiablePresentation};
-use crate::utils::crypto;
+use crate::utils::data_integrity::{ProofOptions, DATA_INTEGRITY_CONTEXT};
+use crate::utils::signer::SigningKey;
+use crate::utils::{crypto, jwk, sd_jwt};
+use chrono::Utc;
+use serde_json::Value;
 use std::sync::Arc;
 
 use log::{debug, info};
@@ -9,12 +14,28 @@ use log::{debug, info};
 #[derive(Clone)]
 pub struct Holder {
     storage: Arc<dyn Storage>,
+    // SD-JWT の cnf に埋め込まれ、KB-JWT と VP の署名に使う鍵
+    key: Arc<SigningKey>,
 }
 
 impl Holder {
-    pub fn new(storage: Arc<dyn Storage>) -> Self {
+    // key は起動のたびに変わらないよう、呼び出し側で鍵ファイルなどから読み込んだもの
+    pub fn new(storage: Arc<dyn Storage>, key: SigningKey) -> Self {
         info!("Creating new Holder instance");
-        Holder { storage }
+        Holder {
+            storage,
+            key: Arc::new(key),
+        }
+    }
+
+    pub fn public_jwk(&self) -> Value {
+        jwk::to_jwk(&self.key.public_key())
+    }
+
+    // Holder の DID は鍵の did:key
+    pub fn did(&self) -> Result<String, HolderError> {
+        did::key::did_key(&self.key.public_key())
+            .map_err(|e| HolderError::ProofCreationError(e.to_string()))
     }
 
     pub fn store_credential(&self, credential: CredentialResponse) -> Result<String, HolderError> {
@@ -53,6 +74,7 @@ impl Holder {
                 .get(id)
                 .map_err(|e| HolderError::StorageError(e.to_string()))?
             {
+                let credential = self.present_sd_jwt(credential, id, &request)?;
                 selected_credentials.push(credential);
                 debug!("Credential {} added to presentation", id);
             } else {
@@ -61,9 +83,14 @@ impl Holder {
             }
         }
 
+        let holder_did = self.did()?;
         let mut presentation = VerifiablePresentation {
-            context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
+            context: vec![
+                "https://www.w3.org/2018/credentials/v1".to_string(),
+                DATA_INTEGRITY_CONTEXT.to_string(),
+            ],
             types: vec!["VerifiablePresentation".to_string()],
+            holder: Some(holder_did.clone()),
             verifiable_credential: selected_credentials,
             proof: None,
         };
@@ -73,32 +100,108 @@ impl Holder {
             .map_err(|e| HolderError::SerializationError(e.to_string()))?;
 
         debug!("Generating signature for presentation");
-        let mut proof = crypto::sign_json(&presentation_json)
+        let verification_method = did::key::key_id(&holder_did)
             .map_err(|e| HolderError::ProofCreationError(e.to_string()))?;
-
-        debug!("Adding domain and challenge to proof");
-        if let Some(proof_obj) = proof.as_object_mut() {
-            proof_obj.insert(
-                "domain".to_string(),
-                serde_json::Value::String(request.domain.clone()),
-            );
-            proof_obj.insert(
-                "challenge".to_string(),
-                serde_json::Value::String(request.challenge.clone()),
-            );
-            debug!("Domain and challenge added to proof");
-        } else {
-            info!("Failed to create proof: proof is not an object");
-            return Err(HolderError::ProofCreationError(
-                "Failed to create proof".to_string(),
-            ));
-        }
+        // domain と challenge は proof に含めて署名する
+        let options = ProofOptions {
+            domain: Some(request.domain.clone()),
+            challenge: Some(request.challenge.clone()),
+            ..ProofOptions::default()
+        };
+        let proof = crypto::create_proof(
+            &presentation_json,
+            self.key.as_ref(),
+            &verification_method,
+            &options,
+        )
+        .map_err(|e| HolderError::ProofCreationError(e.to_string()))?;
 
         presentation.proof = Some(proof);
         info!("Presentation created successfully");
 
         Ok(presentation)
     }
+
+    // SD-JWT は要求されたクレームの Disclosure だけに絞り込み、
+    // cnf を持つ場合は Verifier の domain と challenge に対する KB-JWT を付与する
+    fn present_sd_jwt(
+        &self,
+        mut credential: CredentialResponse,
+        id: &str,
+        request: &PresentationRequest,
+    ) -> Result<CredentialResponse, HolderError> {
+        let Some(stored_sd_jwt) = credential.sd_jwt.as_deref() else {
+            return Ok(credential);
+        };
+
+        let (jwt, disclosures, _) = sd_jwt::split(stored_sd_jwt);
+        let (_, payload) = crypto::decode_jwt(jwt)
+            .map_err(|e| HolderError::SelectiveDisclosureError(e.to_string()))?;
+
+        let disclosures = match &request.disclose {
+            Some(disclose) => {
+                let claims = disclose.get(id).map(Vec::as_slice).unwrap_or_default();
+                let selected = select_disclosures(&payload, &disclosures, claims)?;
+                debug!(
+                    "Disclosing {} of {} disclosures for credential {}",
+                    selected.len(),
+                    disclosures.len(),
+                    id
+                );
+                // 平文の credentialSubject と VC の proof はすべてのクレームを含むため提示しない
+                credential.credential_subject = serde_json::json!({});
+                credential.proof = None;
+                selected
+            }
+            None => disclosures.iter().map(|d| d.to_string()).collect(),
+        };
+        let presented = sd_jwt::combine(jwt, &disclosures);
+        credential.disclosures = Some(disclosures);
+
+        if payload.get("cnf").is_none() {
+            credential.sd_jwt = Some(presented);
+            return Ok(credential);
+        }
+
+        debug!("Creating KB-JWT for SD-JWT credential");
+        let alg = sd_jwt::SdAlg::from_payload(&payload)
+            .map_err(|e| HolderError::KeyBindingError(e.to_string()))?;
+        let key_binding_jwt = sd_jwt::create_key_binding_jwt(
+            &presented,
+            alg,
+            &request.domain,
+            &request.challenge,
+            Utc::now().timestamp(),
+            self.key.as_ref(),
+        )
+        .map_err(|e| HolderError::KeyBindingError(e.to_string()))?;
+
+        credential.sd_jwt = Some(format!("{}{}", presented, key_binding_jwt));
+        Ok(credential)
+    }
+}
+
+// 要求されたクレームそのもの、その親（入れ子のクレームを辿るために必要）、
+// およびその配下のクレームの Disclosure を選ぶ
+fn select_disclosures(
+    payload: &Value,
+    disclosures: &[&str],
+    claims: &[String],
+) -> Result<Vec<String>, HolderError> {
+    let paths = sd_jwt::disclosure_paths(payload, disclosures)
+        .map_err(|e| HolderError::SelectiveDisclosureError(e.to_string()))?;
+
+    Ok(paths
+        .into_iter()
+        .filter(|(path, _)| {
+            claims.iter().any(|claim| {
+                claim == path
+                    || sd_jwt::is_descendant_path(path, claim)
+                    || sd_jwt::is_descendant_path(claim, path)
+            })
+        })
+        .map(|(_, disclosure)| disclosure)
+        .collect())
 }
 
 #[cfg(test)]
@@ -106,10 +209,20 @@ mod tests {
     use super::*;
     use crate::holder::api;
     use crate::holder::storage::test_storage::TestStorage;
+    use crate::issuer::issuer::create_sd_jwt_credential;
+    use crate::models::credential::Issuer;
+    use crate::models::sd_jwt::SDJWTCredentialRequest;
+    use crate::utils::key_manager::test_key_manager::issuer_key_manager;
+    use crate::utils::key_manager::IssuerConfig;
+    use crate::utils::sd_jwt::SdAlg;
+    use crate::utils::signer::SignatureAlgorithm;
     use actix_web::{test, web, App};
 
     fn setup_test_holder() -> Holder {
-        Holder::new(Arc::new(TestStorage::new()))
+        Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        )
     }
 
     #[actix_web::test]
@@ -120,12 +233,13 @@ mod tests {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             id: Some("test_id".to_string()),
             types: vec!["VerifiableCredential".to_string()],
-            issuer: "did:example:123".to_string(),
-            issuance_date: "2023-01-01T00:00:00Z".to_string(),
+            issuer: Issuer::Id("did:example:123".to_string()),
+            issuance_date: Some("2023-01-01T00:00:00Z".to_string()),
             credential_subject: serde_json::json!({"id": "did:example:456", "name": "Alice"}),
             proof: None,
             sd_jwt: None,
             disclosures: None,
+            ..Default::default()
         };
 
         let result = holder.store_credential(credential.clone());
@@ -144,12 +258,13 @@ mod tests {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             id: Some("test_id".to_string()),
             types: vec!["VerifiableCredential".to_string()],
-            issuer: "did:example:123".to_string(),
-            issuance_date: "2023-01-01T00:00:00Z".to_string(),
+            issuer: Issuer::Id("did:example:123".to_string()),
+            issuance_date: Some("2023-01-01T00:00:00Z".to_string()),
             credential_subject: serde_json::json!({"id": "did:example:456", "name": "Alice"}),
             proof: None,
             sd_jwt: None,
             disclosures: None,
+            ..Default::default()
         };
 
         let credential_id = holder.store_credential(credential).unwrap();
@@ -158,6 +273,7 @@ mod tests {
             verifiable_credential: vec![credential_id],
             challenge: "challenge".to_string(),
             domain: "example.com".to_string(),
+            disclose: None,
         };
 
         let result = holder.create_presentation(request);
@@ -169,6 +285,15 @@ mod tests {
             presentation.verifiable_credential[0].issuer,
             "did:example:123"
         );
+
+        // VP は Holder の did:key の鍵で署名される
+        let holder_did = holder.did().unwrap();
+        assert!(holder_did.starts_with("did:key:z6Mk"));
+        assert_eq!(presentation.holder.as_deref(), Some(holder_did.as_str()));
+        assert_eq!(
+            presentation.proof.unwrap()["verificationMethod"],
+            did::key::key_id(&holder_did).unwrap()
+        );
     }
 
     #[actix_web::test]
@@ -185,12 +310,13 @@ mod tests {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             id: Some("test_id".to_string()),
             types: vec!["VerifiableCredential".to_string()],
-            issuer: "did:example:123".to_string(),
-            issuance_date: "2023-01-01T00:00:00Z".to_string(),
+            issuer: Issuer::Id("did:example:123".to_string()),
+            issuance_date: Some("2023-01-01T00:00:00Z".to_string()),
             credential_subject: serde_json::json!({"id": "did:example:456", "name": "Alice"}),
             proof: None,
             sd_jwt: None,
             disclosures: None,
+            ..Default::default()
         };
 
         let req = test::TestRequest::post()
@@ -222,12 +348,13 @@ mod tests {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             id: Some("test_id".to_string()),
             types: vec!["VerifiableCredential".to_string()],
-            issuer: "did:example:123".to_string(),
-            issuance_date: "2023-01-01T00:00:00Z".to_string(),
+            issuer: Issuer::Id("did:example:123".to_string()),
+            issuance_date: Some("2023-01-01T00:00:00Z".to_string()),
             credential_subject: serde_json::json!({"id": "did:example:456", "name": "Alice"}),
             proof: None,
             sd_jwt: None,
             disclosures: None,
+            ..Default::default()
         };
         holder.store_credential(credential).unwrap();
 
@@ -239,4 +366,155 @@ mod tests {
         assert_eq!(body.len(), 1);
         assert_eq!(body[0].issuer, "did:example:123");
     }
+
+    #[actix_web::test]
+    async fn test_create_presentation_with_key_binding() {
+        let holder = setup_test_holder();
+
+        let credential = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            SDJWTCredentialRequest {
+                credential_subject: serde_json::json!({
+                    "given_name": "Alice",
+                    "family_name": "Smith",
+                    "email": "alice@example.com"
+                }),
+                selective_disclosure: None,
+                holder_jwk: Some(holder.public_jwk()),
+                validity_seconds: None,
+            },
+        )
+        .unwrap();
+        let credential_id = holder.store_credential(credential).unwrap();
+
+        let request = PresentationRequest {
+            verifiable_credential: vec![credential_id],
+            challenge: "challenge".to_string(),
+            domain: "example.com".to_string(),
+            disclose: None,
+        };
+        let presentation = holder.create_presentation(request).unwrap();
+
+        let presented = presentation.verifiable_credential[0]
+            .sd_jwt
+            .clone()
+            .unwrap();
+        let (_, _, key_binding_jwt) = sd_jwt::split(&presented);
+        let key_binding_jwt = key_binding_jwt.expect("KB-JWT should be appended");
+
+        let (header, claims) = crypto::decode_jwt(key_binding_jwt).unwrap();
+        assert_eq!(header["typ"], "kb+jwt");
+        assert_eq!(claims["aud"], "example.com");
+        assert_eq!(claims["nonce"], "challenge");
+        assert_eq!(
+            claims["sd_hash"],
+            sd_jwt::sd_hash(SdAlg::Sha256, &presented)
+        );
+        assert!(claims["iat"].is_i64());
+    }
+
+    fn store_selectively_disclosable_credential(holder: &Holder) -> String {
+        let credential = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            SDJWTCredentialRequest {
+                credential_subject: serde_json::json!({
+                    "given_name": "Alice",
+                    "family_name": "Smith",
+                    "email": "alice@example.com",
+                    "birthdate": "1990-01-01",
+                    "address": {
+                        "street_address": "123 Main St",
+                        "country": "JP"
+                    },
+                    "degree": {
+                        "type": "BachelorDegree",
+                        "name": "Bachelor of Science in Mechanical Engineering"
+                    }
+                }),
+                selective_disclosure: Some(
+                    [
+                        "email",
+                        "birthdate",
+                        "address.street_address",
+                        "degree",
+                        "degree.name",
+                    ]
+                    .iter()
+                    .map(|claim| claim.to_string())
+                    .collect(),
+                ),
+                holder_jwk: Some(holder.public_jwk()),
+                validity_seconds: None,
+            },
+        )
+        .unwrap();
+        holder.store_credential(credential).unwrap()
+    }
+
+    fn present_claims(holder: &Holder, id: &str, claims: &[&str]) -> CredentialResponse {
+        let mut disclose = std::collections::HashMap::new();
+        disclose.insert(
+            id.to_string(),
+            claims.iter().map(|claim| claim.to_string()).collect(),
+        );
+        let presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![id.to_string()],
+                challenge: "challenge".to_string(),
+                domain: "example.com".to_string(),
+                disclose: Some(disclose),
+            })
+            .unwrap();
+        presentation.verifiable_credential[0].clone()
+    }
+
+    fn disclosed_claim_names(credential: &CredentialResponse) -> Vec<String> {
+        let presented = credential.sd_jwt.as_deref().unwrap();
+        let (_, disclosures, _) = sd_jwt::split(presented);
+        let mut names: Vec<String> = disclosures
+            .iter()
+            .map(|d| {
+                sd_jwt::decode_disclosure(d).unwrap()[1]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[actix_web::test]
+    async fn test_create_presentation_with_selective_disclosure() {
+        let holder = setup_test_holder();
+        let id = store_selectively_disclosable_credential(&holder);
+
+        // 入れ子のクレームを開示する場合は親の Disclosure も含める
+        let credential = present_claims(&holder, &id, &["email", "degree.name"]);
+        assert_eq!(
+            disclosed_claim_names(&credential),
+            ["degree", "email", "name"]
+        );
+        assert_eq!(credential.disclosures.as_ref().unwrap().len(), 3);
+        assert_eq!(credential.credential_subject, serde_json::json!({}));
+        assert!(credential.proof.is_none());
+
+        // KB-JWT は絞り込んだ後の SD-JWT に対して作成される
+        let presented = credential.sd_jwt.as_deref().unwrap();
+        let (_, _, key_binding_jwt) = sd_jwt::split(presented);
+        let (_, claims) = crypto::decode_jwt(key_binding_jwt.unwrap()).unwrap();
+        assert_eq!(claims["sd_hash"], sd_jwt::sd_hash(SdAlg::Sha256, presented));
+
+        // 親のクレームを開示する場合は配下の Disclosure も含める
+        let credential = present_claims(&holder, &id, &["degree", "address.street_address"]);
+        assert_eq!(
+            disclosed_claim_names(&credential),
+            ["degree", "name", "street_address"]
+        );
+
+        let credential = present_claims(&holder, &id, &[]);
+        assert!(disclosed_claim_names(&credential).is_empty());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod api;
+#[allow(clippy::module_inception)]
 pub mod holder;
 pub mod storage;
 pub mod error;
\ No newline at end of file
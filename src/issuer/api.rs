@@ -1,11 +1,37 @@
 use super::issuer;
-use crate::models::credential::CredentialRequest;
+use super::type_metadata;
+use crate::models::credential::{CredentialRequest, KeyRotationRequest};
 use crate::models::sd_jwt::SDJWTCredentialRequest;
-use actix_web::{web, HttpResponse, Responder};
-use log::error;
+use crate::utils::json_ld;
+use crate::utils::key_manager::{IssuerConfig, KeyManager};
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use log::{error, warn};
+use sha2::{Digest, Sha256};
 
-pub async fn issue_credential(request: web::Json<CredentialRequest>) -> impl Responder {
-    match issuer::create_credential(request.into_inner()) {
+// 管理用 API の Bearer トークン（未設定の場合は管理用 API を使えない）
+const ADMIN_TOKEN_ENV: &str = "ISSUER_ADMIN_TOKEN";
+
+fn is_admin(request: &HttpRequest) -> bool {
+    let token = match std::env::var(ADMIN_TOKEN_ENV) {
+        Ok(token) if !token.is_empty() => token,
+        _ => return false,
+    };
+    // 長さや一致した位置がわからないよう、ハッシュ値どうしを比較する
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| Sha256::digest(presented) == Sha256::digest(&token))
+}
+
+pub async fn issue_credential(
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+    request: web::Json<CredentialRequest>,
+) -> impl Responder {
+    match issuer::create_credential(key_manager.as_ref(), &issuer_config, request.into_inner()) {
         Ok(credential) => HttpResponse::Ok().json(credential),
         Err(e) => {
             error!("Failed to issue credential: {:?}", e);
@@ -14,16 +40,104 @@ pub async fn issue_credential(request: web::Json<CredentialRequest>) -> impl Res
     }
 }
 
-pub async fn get_issuer_metadata() -> impl Responder {
-    match issuer::get_metadata() {
+pub async fn get_issuer_metadata(
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+) -> impl Responder {
+    match issuer::get_metadata(key_manager.as_ref(), &issuer_config) {
         Ok(metadata) => HttpResponse::Ok().json(metadata),
         Err(_) => HttpResponse::InternalServerError().json("Failed to retrieve issuer metadata"),
     }
 }
 
-pub async fn issue_sd_jwt_credential(request: web::Json<SDJWTCredentialRequest>) -> impl Responder {
-    match issuer::create_sd_jwt_vc(request.into_inner()) {
+pub async fn get_did_document(
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+) -> impl Responder {
+    match issuer::get_did_document(key_manager.as_ref(), &issuer_config) {
+        Ok(document) => HttpResponse::Ok()
+            .content_type("application/did+json")
+            .json(document),
+        Err(e) => {
+            error!("Failed to retrieve DID document: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to retrieve DID document")
+        }
+    }
+}
+
+pub async fn rotate_key(
+    http_request: HttpRequest,
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+    request: web::Json<KeyRotationRequest>,
+) -> impl Responder {
+    if !is_admin(&http_request) {
+        warn!("Unauthorized key rotation request");
+        return HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish();
+    }
+
+    match issuer::rotate_key(key_manager.as_ref(), &issuer_config, &request) {
+        Ok(metadata) => HttpResponse::Ok().json(metadata),
+        Err(e) => {
+            error!("Failed to rotate key: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to rotate key")
+        }
+    }
+}
+
+pub async fn reload_keys(
+    http_request: HttpRequest,
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+) -> impl Responder {
+    if !is_admin(&http_request) {
+        warn!("Unauthorized key reload request");
+        return HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish();
+    }
+
+    match issuer::reload_keys(key_manager.as_ref(), &issuer_config) {
+        Ok(metadata) => HttpResponse::Ok().json(metadata),
+        Err(e) => {
+            error!("Failed to reload keys: {:?}", e);
+            HttpResponse::InternalServerError().json("Failed to reload keys")
+        }
+    }
+}
+
+pub async fn issue_sd_jwt_credential(
+    key_manager: web::Data<dyn KeyManager>,
+    issuer_config: web::Data<IssuerConfig>,
+    request: web::Json<SDJWTCredentialRequest>,
+) -> impl Responder {
+    match issuer::create_sd_jwt_vc(key_manager.as_ref(), &issuer_config, request.into_inner()) {
         Ok(response) => HttpResponse::Ok().json(response),
         Err(e) => HttpResponse::BadRequest().body(e.to_string()),
     }
 }
+
+// Issuer が発行するクレデンシャルの JSON-LD コンテキストを公開する
+pub async fn get_context(name: web::Path<String>) -> impl Responder {
+    match json_ld::context_document(&type_metadata::context_url(&name)) {
+        Some(document) => HttpResponse::Ok()
+            .content_type("application/ld+json")
+            .body(document),
+        None => HttpResponse::NotFound().body(format!("Unknown context: {}", name)),
+    }
+}
+
+// vct が指す Type Metadata を公開する
+// vct#integrity と一致するよう、ドキュメントは常に同じバイト列で返す
+pub async fn get_type_metadata(credential_type: web::Path<String>) -> impl Responder {
+    match type_metadata::type_metadata_document(&credential_type) {
+        Some(document) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(document),
+        None => {
+            HttpResponse::NotFound().body(format!("Unknown credential type: {}", credential_type))
+        }
+    }
+}
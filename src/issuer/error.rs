@@ -9,8 +9,20 @@ pub enum IssuerError {
     SchemaValidationError(String),
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    #[error("Invalid selective disclosure: {0}")]
+    InvalidSelectiveDisclosure(String),
+    #[error("Invalid holder key: {0}")]
+    InvalidHolderKey(String),
+    #[error("Invalid validity period: {0}")]
+    InvalidValidityPeriod(String),
+    #[error("Invalid context: {0}")]
+    InvalidContext(String),
     #[error("Signing error: {0}")]
     SigningError(String),
+    #[error("Key rotation failed: {0}")]
+    KeyRotationFailed(String),
+    #[error("Key reload failed: {0}")]
+    KeyReloadFailed(String),
     //#[error("JWT creation error: {0}")]
     //JwtCreationError(String),
     //#[error("Invalid SD-JWT format")]
@@ -1,15 +1,42 @@
 use crate::issuer::error::IssuerError;
 use crate::issuer::schema;
-use crate::models::credential::{CredentialRequest, CredentialResponse, IssuerMetadata};
+use crate::issuer::sd_jwt::SdJwtBuilder;
+use crate::issuer::type_metadata::{self, vct};
+use crate::models::credential::{
+    CredentialRequest, CredentialResponse, DataModelVersion, Issuer, IssuerMetadata,
+    KeyRotationRequest,
+};
+use crate::models::did::DidDocument;
 use crate::models::schema::CredentialSchema;
 use crate::models::sd_jwt::{SDJWTCredentialRequest, SDJWTCredentialResponse};
-use crate::utils::{crypto, sd_jwt};
-use chrono::Utc;
+use crate::utils::data_integrity::DATA_INTEGRITY_CONTEXT;
+use crate::utils::key_manager::{IssuerConfig, KeyManager};
+use crate::utils::sd_jwt::{
+    child_path, element_path, is_descendant_path, OsRngSaltSource, SaltSource, SdAlg,
+};
+use crate::utils::{crypto, integrity, json_ld, jwk, sd_jwt};
+use chrono::{DateTime, Duration, Utc};
 use log::{debug, error, info};
-use serde_json::json;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
-pub fn create_credential(request: CredentialRequest) -> Result<CredentialResponse, IssuerError> {
+const SD_JWT_CREDENTIAL_TYPE: &str = "SDJWTCredential";
+
+const ISSUER_NAME: &str = "Example University";
+
+// クレデンシャルタイプの用語を定義した、Issuer が公開する JSON-LD コンテキスト
+const ISSUER_CONTEXT: &str = "v1";
+
+// クレデンシャルタイプごとに指定がない場合の _sd_alg
+const DEFAULT_SD_ALG: SdAlg = SdAlg::Sha256;
+
+pub fn create_credential(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+    request: CredentialRequest,
+) -> Result<CredentialResponse, IssuerError> {
     info!("Received credential request: {:?}", request);
 
     let credential_type = get_credential_type(&request.types)?;
@@ -19,30 +46,69 @@ pub fn create_credential(request: CredentialRequest) -> Result<CredentialRespons
         .map_err(|e| IssuerError::SchemaValidationError(e.to_string()))?;
     debug!("Credential subject validated successfully");
 
-    let credential = create_unsigned_credential(request)?;
-    sign_and_finalize_credential(credential)
+    let validity = get_validity(request.validity_seconds, &schema)?;
+    let credential = create_unsigned_credential(
+        key_manager,
+        issuer_config,
+        request,
+        schema.data_model,
+        validity,
+    )?;
+    validate_terms(&credential)?;
+    sign_and_finalize_credential(key_manager, issuer_config, credential)
 }
 
 pub fn create_sd_jwt_credential(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
     request: SDJWTCredentialRequest,
+) -> Result<CredentialResponse, IssuerError> {
+    create_sd_jwt_credential_with_salts(
+        key_manager,
+        issuer_config,
+        request,
+        &OsRngSaltSource,
+        &mut OsRng,
+    )
+}
+
+pub fn create_sd_jwt_credential_with_salts(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+    request: SDJWTCredentialRequest,
+    salts: &dyn SaltSource,
+    rng: &mut dyn RngCore,
 ) -> Result<CredentialResponse, IssuerError> {
     info!("Creating SD-JWT credential");
 
-    let (sd_jwt, disclosures) = create_sd_jwt(&request)?;
+    let schema = get_schema(SD_JWT_CREDENTIAL_TYPE)?;
+    validate_sd_jwt_subject(&request.credential_subject, &schema)?;
+    let selective_disclosure_claims = get_selective_disclosure_claims(&request, &schema)?;
+    let (sd_jwt, disclosures) = create_sd_jwt(
+        key_manager,
+        issuer_config,
+        &request,
+        &schema,
+        &selective_disclosure_claims,
+        salts,
+        rng,
+    )?;
 
     // VCリクエストの構造をクライアントに設定させる項目のみ含める
     let vc_request = CredentialRequest {
-        context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
+        context: vec![schema.data_model.context().to_string()],
         types: vec![
             "VerifiableCredential".to_string(),
-            "SDJWTCredential".to_string(),
+            SD_JWT_CREDENTIAL_TYPE.to_string(),
         ],
         issuer: "".to_string(),        // Issuer側で設定するため空にする
         issuance_date: "".to_string(), // Issuer側で設定するため空にする
         credential_subject: request.credential_subject.clone(),
+        validity_seconds: request.validity_seconds,
+        ..Default::default()
     };
 
-    let mut vc = create_credential(vc_request)?;
+    let mut vc = create_credential(key_manager, issuer_config, vc_request)?;
 
     // SD-JWT と開示情報を追加
     vc.sd_jwt = Some(sd_jwt);
@@ -52,9 +118,11 @@ pub fn create_sd_jwt_credential(
 }
 
 pub fn create_sd_jwt_vc(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
     request: SDJWTCredentialRequest,
 ) -> Result<SDJWTCredentialResponse, IssuerError> {
-    let mut vc = create_sd_jwt_credential(request)?;
+    let mut vc = create_sd_jwt_credential(key_manager, issuer_config, request)?;
 
     // `CredentialResponse` から `sd_jwt` と `disclosures` を取り出し、`CredentialResponse` から削除
     let sd_jwt = vc.sd_jwt.take().unwrap_or_default();
@@ -72,17 +140,54 @@ pub fn create_sd_jwt_vc(
     })
 }
 
-pub fn get_metadata() -> Result<IssuerMetadata, IssuerError> {
+pub fn get_metadata(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<IssuerMetadata, IssuerError> {
     debug!("Fetching issuer metadata");
-    let public_key_info = crypto::get_public_key_info().map_err(IssuerError::from)?;
+    let public_key_info =
+        crypto::issuer_public_key_info(key_manager, issuer_config).map_err(IssuerError::from)?;
 
     Ok(IssuerMetadata {
-        id: "did:example:123".to_string(),
-        name: "Example University".to_string(),
+        id: crypto::issuer_did(key_manager, issuer_config).map_err(IssuerError::from)?,
+        name: ISSUER_NAME.to_string(),
         public_key: public_key_info,
+        public_keys: crypto::issuer_public_key_infos(key_manager, issuer_config)
+            .map_err(IssuerError::from)?,
     })
 }
 
+// 署名鍵をローテーションし、更新した鍵の一覧を含むメタデータを返す
+pub fn rotate_key(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+    request: &KeyRotationRequest,
+) -> Result<IssuerMetadata, IssuerError> {
+    let key = key_manager
+        .rotate_key(request.algorithm, request.revoke_current)
+        .map_err(IssuerError::KeyRotationFailed)?;
+    info!("Issuer signing key rotated to {}", key.kid);
+    get_metadata(key_manager, issuer_config)
+}
+
+// 鍵セットを読み直し、読み直した鍵の一覧を含むメタデータを返す
+pub fn reload_keys(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<IssuerMetadata, IssuerError> {
+    key_manager.reload().map_err(IssuerError::KeyReloadFailed)?;
+    info!("Issuer signing keys reloaded");
+    get_metadata(key_manager, issuer_config)
+}
+
+pub fn get_did_document(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<DidDocument, IssuerError> {
+    debug!("Fetching issuer DID document");
+    crypto::issuer_did_document(key_manager, issuer_config).map_err(IssuerError::from)
+}
+
 fn get_credential_type(types: &[String]) -> Result<&str, IssuerError> {
     types
         .iter()
@@ -101,32 +206,227 @@ fn get_schema(credential_type: &str) -> Result<CredentialSchema, IssuerError> {
     })
 }
 
+// リクエストで指定された有効期間を優先し、なければタイプごとの有効期間を使う（どちらもなければ無期限）
+fn get_validity(
+    validity_seconds: Option<i64>,
+    schema: &CredentialSchema,
+) -> Result<Option<Duration>, IssuerError> {
+    match validity_seconds.or(schema.validity_seconds) {
+        Some(seconds) if seconds <= 0 => {
+            error!("Invalid validity period: {}", seconds);
+            Err(IssuerError::InvalidValidityPeriod(format!(
+                "Validity must be positive: {}",
+                seconds
+            )))
+        }
+        Some(seconds)
+            if schema
+                .max_validity_seconds
+                .is_some_and(|max_seconds| seconds > max_seconds) =>
+        {
+            error!("Invalid validity period: {}", seconds);
+            Err(IssuerError::InvalidValidityPeriod(format!(
+                "Validity exceeds the maximum of {} seconds: {}",
+                schema.max_validity_seconds.unwrap_or_default(),
+                seconds
+            )))
+        }
+        Some(seconds) => Duration::try_seconds(seconds).map(Some).ok_or_else(|| {
+            IssuerError::InvalidValidityPeriod(format!("Validity is out of range: {}", seconds))
+        }),
+        None => Ok(None),
+    }
+}
+
+// 有効期間の終わり（日時の範囲を超える場合はエラー）
+fn expires_at(issued_at: DateTime<Utc>, validity: Duration) -> Result<DateTime<Utc>, IssuerError> {
+    issued_at.checked_add_signed(validity).ok_or_else(|| {
+        IssuerError::InvalidValidityPeriod(format!(
+            "Validity is out of range: {}",
+            validity.num_seconds()
+        ))
+    })
+}
+
+// Issuer が設定する登録済みクレームを credentialSubject で上書きさせない
+fn validate_sd_jwt_subject(
+    subject: &serde_json::Value,
+    schema: &CredentialSchema,
+) -> Result<(), IssuerError> {
+    if let Some(claim) = subject.as_object().and_then(|subject| {
+        subject.keys().find(|key| {
+            sd_jwt::NON_SELECTIVELY_DISCLOSABLE_CLAIMS.contains(&key.as_str())
+                || sd_jwt::ISSUER_SET_CLAIMS.contains(&key.as_str())
+        })
+    }) {
+        error!("Registered claim in credential subject: {}", claim);
+        return Err(IssuerError::SchemaValidationError(format!(
+            "Registered claim cannot be set in credential subject: {}",
+            claim
+        )));
+    }
+    schema::validate_credential_subject(subject, schema)
+}
+
+// リクエストで指定された選択的開示クレームを優先し、なければタイプごとのポリシーを使う
+fn get_selective_disclosure_claims(
+    request: &SDJWTCredentialRequest,
+    schema: &CredentialSchema,
+) -> Result<Vec<String>, IssuerError> {
+    let policy = &schema.selective_disclosure;
+    let claims = request
+        .selective_disclosure
+        .clone()
+        .unwrap_or_else(|| policy.selectively_disclosable.clone());
+
+    for claim in &claims {
+        // 常に開示するクレームの親を隠すと、そのクレームも隠れてしまう
+        let hides_always_disclosed = policy
+            .always_disclosed
+            .iter()
+            .any(|disclosed| disclosed == claim || is_descendant_path(claim, disclosed));
+
+        if sd_jwt::NON_SELECTIVELY_DISCLOSABLE_CLAIMS.contains(&claim.as_str())
+            || hides_always_disclosed
+        {
+            error!("Claim cannot be selectively disclosed: {}", claim);
+            return Err(IssuerError::InvalidSelectiveDisclosure(format!(
+                "Claim cannot be selectively disclosed: {}",
+                claim
+            )));
+        }
+    }
+
+    // リクエストで指定されたクレームは credentialSubject になければならない
+    // （ポリシーの既定のクレームは、credentialSubject にあるものだけを選択的開示にする）
+    if request.selective_disclosure.is_some() {
+        let mut paths = Vec::new();
+        claim_paths(&request.credential_subject, "", &mut paths);
+        if let Some(claim) = claims.iter().find(|claim| !paths.contains(claim)) {
+            error!("Unknown selectively disclosable claim: {}", claim);
+            return Err(IssuerError::InvalidSelectiveDisclosure(format!(
+                "Claim not found in credentialSubject: {}",
+                claim
+            )));
+        }
+    }
+
+    Ok(claims)
+}
+
+// value の中のクレームのパス（selectiveDisclosure と同じ形式）
+fn claim_paths(value: &Value, path: &str, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let claim_path = child_path(path, key);
+                claim_paths(value, &claim_path, paths);
+                paths.push(claim_path);
+            }
+        }
+        Value::Array(elements) => {
+            let path = element_path(path);
+            for element in elements {
+                claim_paths(element, &path, paths);
+            }
+            paths.push(path);
+        }
+        _ => {}
+    }
+}
+
 fn create_unsigned_credential(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
     request: CredentialRequest,
+    data_model: DataModelVersion,
+    validity: Option<Duration>,
 ) -> Result<CredentialResponse, IssuerError> {
     let credential_id = Uuid::new_v4().to_string();
     debug!("Generated credential ID: {}", credential_id);
 
+    // issuer と有効期間は Issuer側で設定する
+    let issuer_did = crypto::issuer_did(key_manager, issuer_config).map_err(IssuerError::from)?;
+    let issued_at = Utc::now();
+    let expires_at = validity
+        .map(|validity| expires_at(issued_at, validity).map(|expires_at| expires_at.to_rfc3339()))
+        .transpose()?;
+    let issued_at = Some(issued_at.to_rfc3339());
+    let (issuer, issuance_date, expiration_date, valid_from, valid_until) = match data_model {
+        DataModelVersion::V1_1 => (Issuer::Id(issuer_did), issued_at, expires_at, None, None),
+        DataModelVersion::V2_0 => (
+            Issuer::Object {
+                id: issuer_did,
+                name: Some(ISSUER_NAME.to_string()),
+            },
+            None,
+            None,
+            issued_at,
+            expires_at,
+        ),
+    };
+
     Ok(CredentialResponse {
-        context: request.context,
+        context: credential_context(data_model, request.context),
         id: Some(format!("http://example.edu/credentials/{}", credential_id)),
         types: request.types,
-        issuer: "did:example:123".to_string(), // Issuer側で設定
-        issuance_date: Utc::now().to_rfc3339(), // Issuer側で設定
+        issuer,
+        issuance_date,
+        expiration_date,
+        valid_from,
+        valid_until,
         credential_subject: request.credential_subject,
+        credential_schema: request.credential_schema,
+        credential_status: request.credential_status,
+        evidence: request.evidence,
+        terms_of_use: request.terms_of_use,
+        refresh_service: request.refresh_service,
         proof: None,
         sd_jwt: None,
         disclosures: None,
     })
 }
 
+// @context の最初の要素は、クレデンシャルタイプのデータモデルの基本コンテキストにする
+// 1.1 の場合は proof の DataIntegrityProof を定義するコンテキストを続ける（2.0 の基本コンテキストは定義済み）
+// 基本のコンテキスト、Data Integrity（1.1 のみ）、Issuer のクレデンシャルタイプの用語、
+// リクエストのコンテキストの順に並べる
+fn credential_context(data_model: DataModelVersion, context: Vec<String>) -> Vec<String> {
+    let issuer_context = type_metadata::context_url(ISSUER_CONTEXT);
+    let mut credential_context = vec![data_model.context().to_string()];
+    if data_model == DataModelVersion::V1_1 {
+        credential_context.push(DATA_INTEGRITY_CONTEXT.to_string());
+    }
+    credential_context.push(issuer_context.clone());
+    credential_context.extend(context.into_iter().filter(|context| {
+        DataModelVersion::from_context(context).is_none()
+            && context != DATA_INTEGRITY_CONTEXT
+            && *context != issuer_context
+    }));
+    credential_context
+}
+
+// type と credentialSubject を JSON-LD として展開し、定義されていない用語があればエラーにする
+// （RDF にしたときに黙って消えるプロパティを含むクレデンシャルを発行しないため）
+fn validate_terms(credential: &CredentialResponse) -> Result<(), IssuerError> {
+    let document = json!({
+        "@context": credential.context,
+        "type": credential.types,
+        "credentialSubject": credential.credential_subject,
+    });
+    json_ld::expand(&document).map_err(|e| IssuerError::InvalidContext(e.to_string()))?;
+    Ok(())
+}
+
 fn sign_and_finalize_credential(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
     mut credential: CredentialResponse,
 ) -> Result<CredentialResponse, IssuerError> {
     let credential_json = serde_json::to_value(&credential)
         .map_err(|e| IssuerError::SerializationError(e.to_string()))?;
 
-    let proof = crypto::sign_json(&credential_json)
+    let proof = crypto::sign_json(&credential_json, key_manager, issuer_config)
         .map_err(|e| IssuerError::SigningError(e.to_string()))?;
 
     credential.proof = Some(proof);
@@ -137,50 +437,93 @@ fn sign_and_finalize_credential(
     Ok(credential)
 }
 
-fn create_sd_jwt(request: &SDJWTCredentialRequest) -> Result<(String, Vec<String>), IssuerError> {
+fn create_sd_jwt(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+    request: &SDJWTCredentialRequest,
+    schema: &CredentialSchema,
+    selective_disclosure_claims: &[String],
+    salts: &dyn SaltSource,
+    rng: &mut dyn RngCore,
+) -> Result<(String, Vec<String>), IssuerError> {
+    let policy = &schema.selective_disclosure;
+    let alg = policy.hash_algorithm.unwrap_or(DEFAULT_SD_ALG);
+    let validity = get_validity(request.validity_seconds, schema)?;
+
+    let issued_at = Utc::now();
     let mut claims = json!({
-        "iss": "did:example:123",  // Issuer側で設定
-        "iat": Utc::now().timestamp(),
-        "vct": "SDJWTCredential",
-        "_sd_alg": "sha-256",
+        "iss": crypto::issuer_did(key_manager, issuer_config).map_err(IssuerError::from)?,  // Issuer側で設定
+        "iat": issued_at.timestamp(),
+        "nbf": issued_at.timestamp(),
+        "vct": vct(SD_JWT_CREDENTIAL_TYPE),
+        "_sd_alg": alg.name(),
     });
+    if let Some(validity) = validity {
+        claims["exp"] = json!(expires_at(issued_at, validity)?.timestamp());
+    }
 
-    let mut disclosures = Vec::new();
-    let mut sd_claims = Vec::new();
-
-    // Issuer側で選択的開示を決定するロジック
-    // そのうち動的に設定できるように改修するかも
-    let selective_disclosure_claims = vec!["email", "birthdate"]; // 例として
-
-    for (key, value) in request.credential_subject.as_object().unwrap() {
-        if selective_disclosure_claims.contains(&key.as_str()) {
-            let salt = sd_jwt::create_salt(&format!("{}:{}", key, value));
-            let disclosure = sd_jwt::create_disclosure(&salt, key, value);
-            let disclosure_hash = sd_jwt::hash_disclosure(&disclosure);
-            sd_claims.push(disclosure_hash);
-            disclosures.push(salt); // ソルトのみを保存
-        } else {
-            claims[key] = value.clone();
-        }
+    let mut subject = request
+        .credential_subject
+        .as_object()
+        .cloned()
+        .ok_or_else(|| IssuerError::SchemaValidationError("Invalid subject format".to_string()))?;
+
+    // 公開している Type Metadata と同じ内容であることを Verifier が確認できるようにする
+    if let Some(document) = type_metadata::type_metadata_document(SD_JWT_CREDENTIAL_TYPE) {
+        claims["vct#integrity"] = json!(integrity::integrity(&document));
+    }
+
+    // credentialSubject.id は SD-JWT VC では sub として表す
+    if let Some(subject_id) = subject.remove("id") {
+        claims["sub"] = subject_id;
+    }
+
+    // Holder の公開鍵を cnf に含め、KB-JWT による所持証明を可能にする
+    if let Some(holder_jwk) = &request.holder_jwk {
+        jwk::from_jwk(holder_jwk).map_err(|e| {
+            error!("Invalid holder key: {}", e);
+            IssuerError::InvalidHolderKey(e.to_string())
+        })?;
+        claims["cnf"] = json!({ "jwk": holder_jwk });
     }
 
-    claims["_sd"] = json!(sd_claims);
+    let mut builder = SdJwtBuilder::new(salts, selective_disclosure_claims)
+        .with_decoys(policy.decoys.clone())
+        .with_hash_algorithm(alg)
+        .with_rng(rng);
+    for (key, value) in builder.conceal_object(&subject, "") {
+        claims[key] = value;
+    }
+    let disclosures = builder.into_disclosures();
 
-    let sd_jwt =
-        crypto::sign_json(&claims).map_err(|e| IssuerError::SigningError(e.to_string()))?;
+    let jwt = crypto::sign_json(&claims, key_manager, issuer_config)
+        .map_err(|e| IssuerError::SigningError(e.to_string()))?;
 
-    Ok((sd_jwt.as_str().unwrap().to_string(), disclosures))
+    // Holder / Verifier がダイジェストを再計算できるよう Combined Format で返す
+    let sd_jwt = sd_jwt::combine(jwt.as_str().unwrap(), &disclosures);
+
+    Ok((sd_jwt, disclosures))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::did;
     use crate::issuer::api;
-    use crate::models::credential::{CredentialRequest, CredentialResponse, IssuerMetadata};
+    use crate::models::credential::{
+        CredentialRequest, CredentialResponse, Issuer, IssuerMetadata,
+    };
+    use crate::models::schema::{DecoyPolicy, DecoyRange};
     use crate::models::sd_jwt::SDJWTCredentialRequest;
+    use crate::utils::key_manager::test_key_manager::issuer_key_manager;
+    use crate::utils::sd_jwt::test_salt_source::SequentialSaltSource;
+    use crate::utils::signer::SigningKey;
     use actix_web::{test, web, App};
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
     use chrono::Utc;
+    use ed25519_dalek::Keypair;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     use serde_json::json;
 
     fn create_test_request() -> CredentialRequest {
@@ -200,13 +543,18 @@ mod tests {
                     "name": "Bachelor of Science in Mechanical Engineering"
                 }
             }),
+            ..Default::default()
         }
     }
 
     #[tokio::test]
     async fn test_create_credential() {
         let request = create_test_request();
-        let result = create_credential(request);
+        let result = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        );
         assert!(
             result.is_ok(),
             "Failed to create credential: {:?}",
@@ -214,28 +562,302 @@ mod tests {
         );
 
         let credential = result.unwrap();
-        assert_eq!(credential.issuer, "did:example:123");
+        assert_eq!(
+            credential.issuer,
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
+        assert!(credential.issuer.id().starts_with("did:key:z6Mk"));
         assert!(credential.proof.is_some());
     }
 
+    #[tokio::test]
+    async fn test_create_credential_data_model_per_type() {
+        // UniversityDegreeCredential は VCDM 1.1、EmploymentCredential は VCDM 2.0 で発行する
+        let credential = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_request(),
+        )
+        .unwrap();
+        assert_eq!(
+            credential.context[0],
+            "https://www.w3.org/2018/credentials/v1"
+        );
+        assert!(matches!(credential.issuer, Issuer::Id(_)));
+        assert!(credential.issuance_date.is_some());
+        assert!(credential.valid_from.is_none());
+
+        let request = CredentialRequest {
+            context: vec![
+                "https://www.w3.org/2018/credentials/v1".to_string(),
+                "https://www.w3.org/ns/credentials/examples/v2".to_string(),
+            ],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "EmploymentCredential".to_string(),
+            ],
+            credential_subject: json!({
+                "id": "did:example:456",
+                "name": "Alice",
+                "employer": "Example Corp",
+                "jobTitle": "Engineer"
+            }),
+            credential_schema: Some(json!({
+                "id": "https://example.com/schemas/employment.json",
+                "type": "JsonSchema"
+            })),
+            terms_of_use: Some(
+                json!([{ "type": "IssuerPolicy", "id": "https://example.com/policies/1" }]),
+            ),
+            ..Default::default()
+        };
+        let credential = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        assert_eq!(
+            credential.context,
+            vec![
+                "https://www.w3.org/ns/credentials/v2",
+                "http://localhost:8080/issuer/contexts/v1",
+                "https://www.w3.org/ns/credentials/examples/v2"
+            ]
+        );
+        assert_eq!(
+            credential.issuer,
+            Issuer::Object {
+                id: crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default())
+                    .unwrap(),
+                name: Some("Example University".to_string()),
+            }
+        );
+        assert!(credential.issuance_date.is_none());
+        assert!(credential.valid_from.is_some());
+        assert_eq!(
+            credential.credential_schema.as_ref().unwrap()["type"],
+            "JsonSchema"
+        );
+        assert!(credential.terms_of_use.is_some());
+
+        let json = serde_json::to_value(&credential).unwrap();
+        assert_eq!(json["issuer"]["name"], "Example University");
+        assert!(json.get("issuanceDate").is_none());
+        assert!(json["validFrom"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_create_credential_rejects_undefined_terms() {
+        let credential = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_request(),
+        )
+        .unwrap();
+        assert_eq!(
+            credential.context,
+            vec![
+                "https://www.w3.org/2018/credentials/v1",
+                "https://w3id.org/security/data-integrity/v2",
+                "http://localhost:8080/issuer/contexts/v1"
+            ]
+        );
+
+        // credentialSubject の中の定義されていない用語
+        let mut request = create_test_request();
+        request.credential_subject["degree"]["major"] = json!("Physics");
+        let result = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        );
+        assert!(
+            matches!(&result, Err(IssuerError::InvalidContext(e)) if e.contains("Undefined term: major")),
+            "{:?}",
+            result.err()
+        );
+
+        // 同梱していないコンテキスト
+        let mut request = create_test_request();
+        request
+            .context
+            .push("https://example.com/contexts/unknown".to_string());
+        let result = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        );
+        assert!(
+            matches!(&result, Err(IssuerError::InvalidContext(e)) if e.contains("Unknown context")),
+            "{:?}",
+            result.err()
+        );
+
+        // どのコンテキストにも定義されていないタイプ
+        let request = CredentialRequest {
+            context: vec!["https://www.w3.org/ns/credentials/v2".to_string()],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "EmploymentCredential".to_string(),
+                "ContractorCredential".to_string(),
+            ],
+            credential_subject: json!({
+                "id": "did:example:456",
+                "name": "Alice",
+                "employer": "Example Corp"
+            }),
+            ..Default::default()
+        };
+        let result = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        );
+        assert!(
+            matches!(&result, Err(IssuerError::InvalidContext(e)) if e.contains("ContractorCredential")),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credential_validity_period() {
+        let key_manager = issuer_key_manager();
+        let parse = |date: &Option<String>| {
+            chrono::DateTime::parse_from_rfc3339(date.as_deref().unwrap()).unwrap()
+        };
+
+        // UniversityDegreeCredential は無期限で、リクエストで有効期間を指定できる
+        let credential = create_credential(
+            key_manager.as_ref(),
+            &IssuerConfig::default(),
+            create_test_request(),
+        )
+        .unwrap();
+        assert!(credential.expiration_date.is_none());
+        let request = CredentialRequest {
+            validity_seconds: Some(3600),
+            ..create_test_request()
+        };
+        let credential =
+            create_credential(key_manager.as_ref(), &IssuerConfig::default(), request).unwrap();
+        assert_eq!(
+            parse(&credential.expiration_date) - parse(&credential.issuance_date),
+            Duration::hours(1)
+        );
+
+        // SD-JWT の exp もリクエストの有効期間に従う
+        let mut request = create_test_sd_jwt_request();
+        request.validity_seconds = Some(600);
+        let sd_jwt =
+            create_sd_jwt_credential(key_manager.as_ref(), &IssuerConfig::default(), request)
+                .unwrap()
+                .sd_jwt
+                .unwrap();
+        let payload = decode_sd_jwt_payload(&sd_jwt);
+        assert_eq!(
+            payload["exp"].as_i64().unwrap() - payload["iat"].as_i64().unwrap(),
+            600
+        );
+
+        // 0 以下、タイプごとの上限を超える値、日時の範囲を超える値は拒否する
+        for validity_seconds in [0, -1, 10 * 365 * 24 * 60 * 60 + 1, i64::MAX] {
+            let request = CredentialRequest {
+                validity_seconds: Some(validity_seconds),
+                ..create_test_request()
+            };
+            let result = create_credential(key_manager.as_ref(), &IssuerConfig::default(), request);
+            assert!(matches!(result, Err(IssuerError::InvalidValidityPeriod(_))));
+        }
+        for validity_seconds in [5 * 365 * 24 * 60 * 60 + 1, i64::MAX] {
+            let mut request = create_test_sd_jwt_request();
+            request.validity_seconds = Some(validity_seconds);
+            let result =
+                create_sd_jwt_credential(key_manager.as_ref(), &IssuerConfig::default(), request);
+            assert!(matches!(result, Err(IssuerError::InvalidValidityPeriod(_))));
+        }
+
+        // 上限のない有効期間でも、日時の範囲を超える場合はパニックせずにエラーにする
+        let schema = CredentialSchema {
+            max_validity_seconds: None,
+            ..schema::get_schema("UniversityDegreeCredential").unwrap()
+        };
+        for validity_seconds in [i64::MAX, i64::MAX / 1000 - 1] {
+            let result = get_validity(Some(validity_seconds), &schema)
+                .and_then(|validity| expires_at(Utc::now(), validity.unwrap()));
+            assert!(matches!(result, Err(IssuerError::InvalidValidityPeriod(_))));
+        }
+    }
+
     #[tokio::test]
     async fn test_get_metadata() {
-        let result = get_metadata();
+        let result = get_metadata(issuer_key_manager().as_ref(), &IssuerConfig::default());
         assert!(result.is_ok(), "Failed to get metadata: {:?}", result.err());
 
         let metadata = result.unwrap();
-        assert_eq!(metadata.id, "did:example:123");
+        assert_eq!(
+            metadata.id,
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
+        assert_eq!(
+            metadata.public_key.id,
+            did::key::key_id(&metadata.id).unwrap()
+        );
         assert_eq!(metadata.name, "Example University");
-        assert!(metadata.public_key.public_key_multibase.starts_with('z'));
+        assert!(metadata
+            .public_key
+            .public_key_multibase
+            .as_ref()
+            .unwrap()
+            .starts_with('z'));
+    }
+
+    #[tokio::test]
+    async fn test_issue_with_did_web_issuer_config() {
+        // 設定した did:web の DID と cryptosuite で発行し、鍵セットの kid を検証メソッドにする
+        let did = "did:web:issuer.example.com";
+        let issuer_config =
+            IssuerConfig::new(Some(did.to_string()), Some("eddsa-rdfc-2022")).unwrap();
+        let key_manager = issuer_key_manager();
+        let key_id = format!("{}#{}", did, key_manager.get_current_key().unwrap().kid);
+
+        let credential =
+            create_credential(key_manager.as_ref(), &issuer_config, create_test_request()).unwrap();
+        assert_eq!(credential.issuer, Issuer::Id(did.to_string()));
+        let proof = credential.proof.unwrap();
+        assert_eq!(proof["cryptosuite"], "eddsa-rdfc-2022");
+        assert_eq!(proof["verificationMethod"], key_id);
+
+        let sd_jwt = create_sd_jwt_credential(
+            key_manager.as_ref(),
+            &issuer_config,
+            create_test_sd_jwt_request(),
+        )
+        .unwrap()
+        .sd_jwt
+        .unwrap();
+        assert_eq!(decode_sd_jwt_payload(&sd_jwt)["iss"], did);
+
+        let metadata = get_metadata(key_manager.as_ref(), &issuer_config).unwrap();
+        assert_eq!(metadata.id, did);
+        assert_eq!(metadata.public_key.id, key_id);
+        let document = get_did_document(key_manager.as_ref(), &issuer_config).unwrap();
+        assert_eq!(document.id, did);
+        assert_eq!(document.assertion_method, vec![key_id]);
     }
 
     #[actix_web::test]
     async fn test_issue_credential_api() {
-        let app =
-            test::init_service(App::new().service(
-                web::resource("/credentials").route(web::post().to(api::issue_credential)),
-            ))
-            .await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(issuer_key_manager()))
+                .app_data(web::Data::new(IssuerConfig::default()))
+                .service(
+                    web::resource("/credentials").route(web::post().to(api::issue_credential)),
+                ),
+        )
+        .await;
 
         let req = test::TestRequest::post()
             .uri("/credentials")
@@ -250,7 +872,10 @@ mod tests {
         );
 
         let body: CredentialResponse = test::read_body_json(resp).await;
-        assert_eq!(body.issuer, "did:example:123");
+        assert_eq!(
+            body.issuer,
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
         assert!(body.proof.is_some());
     }
 
@@ -258,6 +883,8 @@ mod tests {
     async fn test_get_issuer_metadata_api() {
         let app = test::init_service(
             App::new()
+                .app_data(web::Data::from(issuer_key_manager()))
+                .app_data(web::Data::new(IssuerConfig::default()))
                 .service(web::resource("/metadata").route(web::get().to(api::get_issuer_metadata))),
         )
         .await;
@@ -271,23 +898,95 @@ mod tests {
         );
 
         let body: IssuerMetadata = test::read_body_json(resp).await;
-        assert_eq!(body.id, "did:example:123");
+        assert_eq!(
+            body.id,
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
         assert_eq!(body.name, "Example University");
-        assert!(body.public_key.public_key_multibase.starts_with('z'));
+        assert!(body
+            .public_key
+            .public_key_multibase
+            .as_ref()
+            .unwrap()
+            .starts_with('z'));
     }
 
-    #[test]
-    async fn test_create_sd_jwt_credential() {
-        let request = SDJWTCredentialRequest {
+    #[actix_web::test]
+    async fn test_get_did_document_api() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(issuer_key_manager()))
+                .app_data(web::Data::new(IssuerConfig::default()))
+                .route(
+                    "/.well-known/did.json",
+                    web::get().to(api::get_did_document),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/.well-known/did.json")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(
+            resp.status().is_success(),
+            "API call failed: {:?}",
+            resp.status()
+        );
+
+        // Issuer の DID ドキュメントの assertionMethod から署名鍵を解決できる
+        let document: DidDocument = test::read_body_json(resp).await;
+        let key_id = get_metadata(issuer_key_manager().as_ref(), &IssuerConfig::default())
+            .unwrap()
+            .public_key
+            .id;
+        assert_eq!(
+            document.id,
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
+        assert!(did::document::is_assertion_method(&document, &key_id));
+        let method = did::document::find_verification_method(&document, &key_id).unwrap();
+        assert_eq!(
+            did::document::public_key(method).unwrap(),
+            issuer_key_manager().get_current_key().unwrap().public_key
+        );
+    }
+
+    fn create_test_sd_jwt_request() -> SDJWTCredentialRequest {
+        SDJWTCredentialRequest {
             credential_subject: json!({
                 "given_name": "Alice",
                 "family_name": "Smith",
                 "email": "alice@example.com",
                 "birthdate": "1990-01-01"
             }),
-        };
+            selective_disclosure: None,
+            holder_jwk: None,
+            validity_seconds: None,
+        }
+    }
+
+    fn decode_sd_jwt_payload(sd_jwt: &str) -> serde_json::Value {
+        let (jwt, _, _) = sd_jwt::split(sd_jwt);
+        let payload = URL_SAFE_NO_PAD
+            .decode(jwt.split('.').nth(1).unwrap())
+            .unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
 
-        let result = create_sd_jwt_credential(request);
+    fn decode_disclosure(disclosure: &str) -> Vec<serde_json::Value> {
+        let decoded = URL_SAFE_NO_PAD.decode(disclosure).unwrap();
+        let decoded: serde_json::Value = serde_json::from_slice(&decoded).unwrap();
+        decoded.as_array().unwrap().clone()
+    }
+
+    #[test]
+    async fn test_create_sd_jwt_credential() {
+        let result = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+        );
         assert!(result.is_ok(), "SD-JWT creation failed: {:?}", result.err());
 
         let response = result.unwrap();
@@ -299,7 +998,15 @@ mod tests {
         );
 
         let sd_jwt = response.sd_jwt.unwrap();
-        let parts: Vec<&str> = sd_jwt.split('.').collect();
+        assert!(
+            sd_jwt.ends_with('~'),
+            "SD-JWT should use the combined format"
+        );
+        let (jwt, presented_disclosures, key_binding_jwt) = sd_jwt::split(&sd_jwt);
+        assert!(key_binding_jwt.is_none());
+        assert_eq!(presented_disclosures.len(), 2);
+
+        let parts: Vec<&str> = jwt.split('.').collect();
         assert_eq!(parts.len(), 3, "SD-JWT should have three parts");
 
         let payload = URL_SAFE_NO_PAD.decode(parts[1]).unwrap();
@@ -323,7 +1030,10 @@ mod tests {
         );
 
         let sd_claims = payload.get("_sd").unwrap().as_array().unwrap();
-        assert_eq!(sd_claims.len(), 2, "There should be 2 SD claims");
+        assert!(
+            sd_claims.len() >= 3,
+            "There should be 2 SD claims and at least one decoy"
+        );
 
         assert!(
             payload.get("given_name").is_some(),
@@ -346,20 +1056,553 @@ mod tests {
         assert_eq!(disclosures.len(), 2, "There should be 2 disclosures");
 
         for disclosure in disclosures {
-            // ソルトのフォーマットをチェック（Base64 URL-safe エンコーディング）
             assert!(
-                disclosure
-                    .chars()
-                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_'),
-                "Disclosure should only contain Base64 URL-safe characters"
+                presented_disclosures.contains(&disclosure.as_str()),
+                "Disclosure should be part of the combined SD-JWT"
+            );
+
+            // Disclosure は [salt, claim_name, claim_value] の base64url エンコード
+            let decoded = decode_disclosure(&disclosure);
+            assert_eq!(decoded.len(), 3, "Disclosure should have three elements");
+
+            // ソルトは 128 ビット（Base64 URL-safe で 22 文字）
+            let salt = decoded[0].as_str().unwrap();
+            assert_eq!(URL_SAFE_NO_PAD.decode(salt).unwrap().len(), 16);
+
+            let claim_name = decoded[1].as_str().unwrap();
+            assert!(["email", "birthdate"].contains(&claim_name));
+
+            assert!(
+                sd_claims.contains(&json!(sd_jwt::hash_disclosure(SdAlg::Sha256, &disclosure))),
+                "Digest of the disclosure should be listed in _sd"
             );
+        }
+    }
 
-            // ソルトの長さをチェック（16バイトのBase64エンコードは22文字になる）
-            assert_eq!(
-                disclosure.len(),
-                22,
-                "Salt should be 22 characters long (16 bytes in Base64)"
+    #[test]
+    async fn test_sd_jwt_digests_differ_between_issuances() {
+        let first = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+        )
+        .unwrap();
+        let second = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+        )
+        .unwrap();
+
+        let first_digests = decode_sd_jwt_payload(&first.sd_jwt.unwrap())["_sd"].clone();
+        let second_digests = decode_sd_jwt_payload(&second.sd_jwt.unwrap())["_sd"].clone();
+
+        for digest in first_digests.as_array().unwrap() {
+            assert!(
+                !second_digests.as_array().unwrap().contains(digest),
+                "Digests of the same claim must not repeat across issuances"
             );
         }
+
+        let first_salts: Vec<_> = first
+            .disclosures
+            .unwrap()
+            .iter()
+            .map(|d| decode_disclosure(d)[0].clone())
+            .collect();
+        for disclosure in second.disclosures.unwrap() {
+            assert!(!first_salts.contains(&decode_disclosure(&disclosure)[0]));
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_with_injected_salt_source() {
+        let first = create_sd_jwt_credential_with_salts(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+            &SequentialSaltSource::new(),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+        let second = create_sd_jwt_credential_with_salts(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+            &SequentialSaltSource::new(),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+
+        // 同じソルト列と乱数を注入すれば Disclosure とダイジェストは再現できる
+        assert_eq!(first.disclosures, second.disclosures);
+        assert_eq!(
+            decode_sd_jwt_payload(&first.sd_jwt.unwrap())["_sd"],
+            decode_sd_jwt_payload(&second.sd_jwt.unwrap())["_sd"]
+        );
+    }
+
+    #[test]
+    async fn test_sd_jwt_with_requested_selective_disclosure() {
+        let mut request = create_test_sd_jwt_request();
+        request.selective_disclosure = Some(vec!["given_name".to_string()]);
+
+        let response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&response.sd_jwt.unwrap());
+
+        assert!(payload.get("given_name").is_none());
+        assert_eq!(payload["email"], "alice@example.com");
+        assert_eq!(payload["birthdate"], "1990-01-01");
+
+        let disclosures = response.disclosures.unwrap();
+        assert_eq!(disclosures.len(), 1);
+        assert_eq!(decode_disclosure(&disclosures[0])[1], "given_name");
+        assert_eq!(revealed(&disclosures, &payload["_sd"]).len(), 1);
+    }
+
+    #[test]
+    async fn test_sd_jwt_rejects_non_disclosable_claims() {
+        for claim in ["vct", "cnf", "iss", "id"] {
+            let mut request = create_test_sd_jwt_request();
+            request.selective_disclosure = Some(vec![claim.to_string()]);
+
+            let result = create_sd_jwt_credential(
+                issuer_key_manager().as_ref(),
+                &IssuerConfig::default(),
+                request,
+            );
+            assert!(
+                matches!(result, Err(IssuerError::InvalidSelectiveDisclosure(_))),
+                "{} should not be selectively disclosable",
+                claim
+            );
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_rejects_unknown_claims() {
+        for claim in [
+            "phone_number",
+            "email.domain",
+            "degree.major",
+            "degree[]",
+            "email[]",
+        ] {
+            let mut request = create_test_sd_jwt_request();
+            request.selective_disclosure = Some(vec!["email".to_string(), claim.to_string()]);
+
+            let result = create_sd_jwt_credential(
+                issuer_key_manager().as_ref(),
+                &IssuerConfig::default(),
+                request,
+            );
+            assert!(
+                matches!(&result, Err(IssuerError::InvalidSelectiveDisclosure(e)) if e.contains(claim)),
+                "{}: {:?}",
+                claim,
+                result.err()
+            );
+        }
+    }
+
+    fn find_disclosure(
+        disclosures: &[String],
+        digest: &serde_json::Value,
+    ) -> Option<Vec<serde_json::Value>> {
+        disclosures
+            .iter()
+            .find(|d| json!(sd_jwt::hash_disclosure(SdAlg::Sha256, d)) == *digest)
+            .map(|d| decode_disclosure(d))
+    }
+
+    // デコイを除き、Disclosure が存在するダイジェストだけを復元する
+    fn revealed(
+        disclosures: &[String],
+        digests: &serde_json::Value,
+    ) -> Vec<Vec<serde_json::Value>> {
+        digests
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|digest| find_disclosure(disclosures, digest))
+            .collect()
+    }
+
+    #[test]
+    async fn test_sd_jwt_rejects_registered_claims_in_subject() {
+        for claim in [
+            "iss",
+            "vct",
+            "vct#integrity",
+            "exp",
+            "nbf",
+            "cnf",
+            "_sd_alg",
+            "iat",
+            "sub",
+        ] {
+            let mut request = create_test_sd_jwt_request();
+            request.credential_subject[claim] = json!({ "jwk": { "kty": "OKP" } });
+
+            let result = create_sd_jwt_credential(
+                issuer_key_manager().as_ref(),
+                &IssuerConfig::default(),
+                request,
+            );
+            assert!(
+                matches!(&result, Err(IssuerError::SchemaValidationError(e)) if e.contains(claim)),
+                "{}: {:?}",
+                claim,
+                result.err()
+            );
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_nested_and_array_element_disclosure() {
+        let mut request = create_test_sd_jwt_request();
+        request.credential_subject["address"] = json!({
+            "street_address": "123 Main St",
+            "locality": "Anytown",
+            "country": "JP"
+        });
+        request.credential_subject["nationalities"] = json!(["JP", "US"]);
+        request.selective_disclosure = Some(vec![
+            "address.street_address".to_string(),
+            "address.locality".to_string(),
+            "nationalities[]".to_string(),
+        ]);
+
+        let response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&response.sd_jwt.unwrap());
+        let disclosures = response.disclosures.unwrap();
+        assert_eq!(disclosures.len(), 4);
+
+        // 入れ子のオブジェクトはサブプロパティごとに隠される
+        let address = payload["address"].as_object().unwrap();
+        assert_eq!(address["country"], "JP");
+        assert!(address.get("street_address").is_none());
+        assert!(address.get("locality").is_none());
+        let names: Vec<_> = revealed(&disclosures, &address["_sd"])
+            .into_iter()
+            .map(|disclosure| disclosure[1].clone())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&json!("street_address")));
+        assert!(names.contains(&json!("locality")));
+
+        // 配列要素は {"...": digest} に置き換えられ、Disclosure は [salt, value]
+        let nationalities: Vec<_> = payload["nationalities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|element| find_disclosure(&disclosures, &element["..."]))
+            .collect();
+        assert_eq!(nationalities.len(), 2);
+        for (disclosure, expected) in nationalities.iter().zip(["JP", "US"]) {
+            assert_eq!(disclosure.len(), 2);
+            assert_eq!(disclosure[1], expected);
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_recursive_disclosure() {
+        let mut request = create_test_sd_jwt_request();
+        request.credential_subject["degree"] = json!({
+            "type": "BachelorDegree",
+            "name": "Bachelor of Science in Mechanical Engineering"
+        });
+        request.selective_disclosure = Some(vec!["degree".to_string(), "degree.name".to_string()]);
+
+        let response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&response.sd_jwt.unwrap());
+        let disclosures = response.disclosures.unwrap();
+
+        assert!(payload.get("degree").is_none());
+        let top_level = revealed(&disclosures, &payload["_sd"]);
+        assert_eq!(top_level.len(), 1);
+
+        // degree の Disclosure 自体が degree.name のダイジェストを持つ
+        let degree = &top_level[0];
+        assert_eq!(degree[1], "degree");
+        assert_eq!(degree[2]["type"], "BachelorDegree");
+        assert!(degree[2].get("name").is_none());
+
+        let nested = revealed(&disclosures, &degree[2]["_sd"]);
+        assert_eq!(nested.len(), 1);
+        let name = &nested[0];
+        assert_eq!(name[1], "name");
+        assert_eq!(name[2], "Bachelor of Science in Mechanical Engineering");
+    }
+
+    #[test]
+    async fn test_sd_jwt_decoy_digests() {
+        let mut request = create_test_sd_jwt_request();
+        request.credential_subject["nationalities"] = json!(["JP", "US"]);
+        request.selective_disclosure =
+            Some(vec!["email".to_string(), "nationalities[]".to_string()]);
+
+        let response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&response.sd_jwt.unwrap());
+        let disclosures = response.disclosures.unwrap();
+
+        // デコイには対応する Disclosure が存在しない
+        let digests = payload["_sd"].as_array().unwrap();
+        let decoys = digests.len() - revealed(&disclosures, &payload["_sd"]).len();
+        assert!(
+            (1..=3).contains(&decoys),
+            "Unexpected decoy count: {}",
+            decoys
+        );
+        for digest in digests {
+            assert_eq!(digest.as_str().unwrap().len(), 43);
+        }
+
+        let elements = payload["nationalities"].as_array().unwrap();
+        assert!((2..=4).contains(&elements.len()));
+        for element in elements {
+            assert!(element.get("...").is_some());
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_builder_decoy_policy() {
+        let subject = json!({
+            "email": "alice@example.com",
+            "nationalities": ["JP"]
+        });
+        let claims = vec!["email".to_string(), "nationalities[]".to_string()];
+        let salts = SequentialSaltSource::new();
+        let mut builder = SdJwtBuilder::new(&salts, &claims).with_decoys(DecoyPolicy {
+            object: DecoyRange { min: 4, max: 4 },
+            array: DecoyRange { min: 2, max: 2 },
+        });
+
+        let concealed = builder.conceal_object(subject.as_object().unwrap(), "");
+        assert_eq!(concealed["_sd"].as_array().unwrap().len(), 5);
+        assert_eq!(concealed["nationalities"].as_array().unwrap().len(), 3);
+        assert_eq!(builder.into_disclosures().len(), 2);
+
+        // 乱数を注入すればデコイの数と位置、_sd の順序も再現できる
+        let policy = DecoyPolicy {
+            object: DecoyRange { min: 1, max: 8 },
+            array: DecoyRange { min: 1, max: 8 },
+        };
+        let conceal = || {
+            let salts = SequentialSaltSource::new();
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut builder = SdJwtBuilder::new(&salts, &claims)
+                .with_decoys(policy.clone())
+                .with_rng(&mut rng);
+            builder.conceal_object(subject.as_object().unwrap(), "")
+        };
+        assert_eq!(conceal(), conceal());
+    }
+
+    #[test]
+    async fn test_sd_jwt_with_holder_key() {
+        let holder_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let mut request = create_test_sd_jwt_request();
+        request.holder_jwk = Some(jwk::to_jwk(&holder_key.public_key()));
+
+        let response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&response.sd_jwt.unwrap());
+
+        assert_eq!(payload["cnf"]["jwk"], jwk::to_jwk(&holder_key.public_key()));
+    }
+
+    #[test]
+    async fn test_sd_jwt_rejects_invalid_holder_key() {
+        let mut request = create_test_sd_jwt_request();
+        request.holder_jwk = Some(json!({ "kty": "EC", "crv": "P-256" }));
+
+        let result = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        );
+        assert!(matches!(result, Err(IssuerError::InvalidHolderKey(_))));
+    }
+
+    #[test]
+    async fn test_sd_jwt_hash_algorithm_per_credential_type() {
+        let request = create_test_sd_jwt_request();
+        let claims = vec!["email".to_string(), "birthdate".to_string()];
+
+        // タイプごとの指定がなければ Issuer の既定値を使う
+        let mut schema = get_schema(SD_JWT_CREDENTIAL_TYPE).unwrap();
+        let (sd_jwt, _) = create_sd_jwt(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            &request,
+            &schema,
+            &claims,
+            &OsRngSaltSource,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert_eq!(decode_sd_jwt_payload(&sd_jwt)["_sd_alg"], "sha-256");
+
+        for alg in [SdAlg::Sha384, SdAlg::Sha512, SdAlg::Sha3_256] {
+            schema.selective_disclosure.hash_algorithm = Some(alg);
+            let (sd_jwt, disclosures) = create_sd_jwt(
+                issuer_key_manager().as_ref(),
+                &IssuerConfig::default(),
+                &request,
+                &schema,
+                &claims,
+                &OsRngSaltSource,
+                &mut OsRng,
+            )
+            .unwrap();
+            let payload = decode_sd_jwt_payload(&sd_jwt);
+
+            assert_eq!(payload["_sd_alg"], alg.name());
+            for disclosure in &disclosures {
+                let digest = json!(sd_jwt::hash_disclosure(alg, disclosure));
+                assert!(payload["_sd"].as_array().unwrap().contains(&digest));
+            }
+        }
+    }
+
+    #[test]
+    async fn test_sd_jwt_vc_header_and_registered_claims() {
+        let mut request = create_test_sd_jwt_request();
+        request.credential_subject["id"] = json!("did:example:456");
+
+        let sd_jwt = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap()
+        .sd_jwt
+        .unwrap();
+        let (jwt, _, _) = sd_jwt::split(&sd_jwt);
+        let (header, payload) = crypto::decode_jwt(jwt).unwrap();
+
+        assert_eq!(header["typ"], "dc+sd-jwt");
+        assert_eq!(
+            header["kid"],
+            get_metadata(issuer_key_manager().as_ref(), &IssuerConfig::default())
+                .unwrap()
+                .public_key
+                .id
+        );
+        assert_eq!(
+            payload["vct"],
+            "http://localhost:8080/issuer/vct/SDJWTCredential"
+        );
+
+        // credentialSubject.id は sub として平文で含める
+        assert_eq!(payload["sub"], "did:example:456");
+        assert!(payload.get("id").is_none());
+
+        let issued_at = payload["iat"].as_i64().unwrap();
+        assert_eq!(payload["nbf"].as_i64().unwrap(), issued_at);
+        assert_eq!(
+            payload["exp"].as_i64().unwrap(),
+            issued_at + 365 * 24 * 60 * 60
+        );
+    }
+
+    #[test]
+    async fn test_get_type_metadata_api() {
+        let app = test::init_service(App::new().service(
+            web::resource("/vct/{credential_type}").route(web::get().to(api::get_type_metadata)),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/vct/SDJWTCredential")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let document = test::read_body(resp).await;
+
+        // SD-JWT の vct#integrity は公開しているドキュメントのダイジェスト
+        let sd_jwt = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            create_test_sd_jwt_request(),
+        )
+        .unwrap()
+        .sd_jwt
+        .unwrap();
+        let payload = decode_sd_jwt_payload(&sd_jwt);
+        assert_eq!(payload["vct#integrity"], integrity::integrity(&document));
+
+        let metadata: serde_json::Value = serde_json::from_slice(&document).unwrap();
+        assert_eq!(metadata["vct"], payload["vct"]);
+        assert_eq!(
+            metadata["extends"],
+            "http://localhost:8080/issuer/vct/PersonCredential"
+        );
+        let base = type_metadata::type_metadata_document("PersonCredential").unwrap();
+        assert_eq!(metadata["extends#integrity"], integrity::integrity(&base));
+        assert_eq!(metadata["schema"]["properties"]["sub"]["type"], "string");
+        assert!(metadata["claims"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "path": ["sub"], "sd": "never" })));
+
+        let req = test::TestRequest::get()
+            .uri("/vct/UnknownCredential")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    async fn test_get_context_api() {
+        let app = test::init_service(
+            App::new()
+                .service(web::resource("/contexts/{name}").route(web::get().to(api::get_context))),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/contexts/v1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers()
+                .get(actix_web::http::header::CONTENT_TYPE)
+                .unwrap(),
+            "application/ld+json"
+        );
+        let document: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            document["@context"]["UniversityDegreeCredential"],
+            "vcs:UniversityDegreeCredential"
+        );
+
+        let req = test::TestRequest::get().uri("/contexts/v2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
     }
 }
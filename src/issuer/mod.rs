@@ -1,4 +1,7 @@
 pub mod api;
+#[allow(clippy::module_inception)]
 pub mod issuer;
 pub mod error;
-pub mod schema;
\ No newline at end of file
+pub mod schema;
+pub mod sd_jwt;
+pub mod type_metadata;
\ No newline at end of file
@@ -1,5 +1,8 @@
 use crate::issuer::error::IssuerError;
-use crate::models::schema::{CredentialSchema, PropertyType};
+use crate::models::credential::DataModelVersion;
+use crate::models::schema::{
+    CredentialSchema, DecoyPolicy, DecoyRange, PropertyType, SelectiveDisclosurePolicy,
+};
 use serde_json::Value;
 use std::collections::HashMap;
 
@@ -16,6 +19,30 @@ pub fn get_schema(credential_type: &str) -> Option<CredentialSchema> {
                 props
             },
             required: vec!["name".to_string(), "degree".to_string()],
+            selective_disclosure: SelectiveDisclosurePolicy::default(),
+            data_model: DataModelVersion::V1_1,
+            validity_seconds: None,
+            // 10 年
+            max_validity_seconds: Some(10 * 365 * 24 * 60 * 60),
+        }),
+        "EmploymentCredential" => Some(CredentialSchema {
+            id: "EmploymentCredential".to_string(),
+            type_name: "EmploymentCredential".to_string(),
+            properties: {
+                let mut props = HashMap::new();
+                props.insert("id".to_string(), PropertyType::String);
+                props.insert("name".to_string(), PropertyType::String);
+                props.insert("employer".to_string(), PropertyType::String);
+                props.insert("jobTitle".to_string(), PropertyType::String);
+                props
+            },
+            required: vec!["name".to_string(), "employer".to_string()],
+            selective_disclosure: SelectiveDisclosurePolicy::default(),
+            data_model: DataModelVersion::V2_0,
+            // 1 年
+            validity_seconds: Some(365 * 24 * 60 * 60),
+            // 5 年
+            max_validity_seconds: Some(5 * 365 * 24 * 60 * 60),
         }),
         "SDJWTCredential" => Some(CredentialSchema {
             id: "SDJWTCredential".to_string(),
@@ -28,9 +55,25 @@ pub fn get_schema(credential_type: &str) -> Option<CredentialSchema> {
                 props.insert("email".to_string(), PropertyType::String);
                 props.insert("birthdate".to_string(), PropertyType::String);
                 props.insert("degree".to_string(), PropertyType::Object);
+                props.insert("address".to_string(), PropertyType::Object);
+                props.insert("nationalities".to_string(), PropertyType::Array);
                 props
             },
             required: vec!["given_name".to_string(), "family_name".to_string()],
+            selective_disclosure: SelectiveDisclosurePolicy {
+                selectively_disclosable: vec!["email".to_string(), "birthdate".to_string()],
+                always_disclosed: vec!["id".to_string()],
+                decoys: DecoyPolicy {
+                    object: DecoyRange { min: 1, max: 3 },
+                    array: DecoyRange { min: 0, max: 2 },
+                },
+                hash_algorithm: None,
+            },
+            data_model: DataModelVersion::V1_1,
+            // 1 年
+            validity_seconds: Some(365 * 24 * 60 * 60),
+            // 5 年
+            max_validity_seconds: Some(5 * 365 * 24 * 60 * 60),
         }),
         _ => None,
     }
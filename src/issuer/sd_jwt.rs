@@ -0,0 +1,147 @@
+use crate::models::schema::{DecoyPolicy, DecoyRange};
+use crate::utils::sd_jwt::{
+    self, child_path, element_path, SaltSource, SdAlg, ARRAY_ELEMENT_DIGEST_KEY,
+};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use serde_json::{json, Map, Value};
+
+pub struct SdJwtBuilder<'a> {
+    salts: &'a dyn SaltSource,
+    selectively_disclosable: &'a [String],
+    decoys: DecoyPolicy,
+    alg: SdAlg,
+    // _sd の並べ替えとデコイの数・位置に使う乱数（既定は OS の CSPRNG）
+    rng: Box<dyn RngCore + 'a>,
+    disclosures: Vec<String>,
+}
+
+impl<'a> SdJwtBuilder<'a> {
+    pub fn new(salts: &'a dyn SaltSource, selectively_disclosable: &'a [String]) -> Self {
+        SdJwtBuilder {
+            salts,
+            selectively_disclosable,
+            decoys: DecoyPolicy::default(),
+            alg: SdAlg::default(),
+            rng: Box::new(OsRng),
+            disclosures: Vec::new(),
+        }
+    }
+
+    pub fn with_decoys(mut self, decoys: DecoyPolicy) -> Self {
+        self.decoys = decoys;
+        self
+    }
+
+    pub fn with_hash_algorithm(mut self, alg: SdAlg) -> Self {
+        self.alg = alg;
+        self
+    }
+
+    pub fn with_rng(mut self, rng: &'a mut dyn RngCore) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    pub fn conceal_object(
+        &mut self,
+        object: &Map<String, Value>,
+        path: &str,
+    ) -> Map<String, Value> {
+        let mut concealed = Map::new();
+        let mut digests = Vec::new();
+
+        for (key, value) in object {
+            let claim_path = child_path(path, key);
+            // 子要素を先に処理することで、開示される値自体も _sd を持てる
+            let value = self.conceal_value(value, &claim_path);
+
+            if self.is_selectively_disclosable(&claim_path) {
+                let disclosure = sd_jwt::create_disclosure(&self.salts.generate(), key, &value);
+                digests.push(Value::String(self.push_disclosure(disclosure)));
+            } else {
+                concealed.insert(key.clone(), value);
+            }
+        }
+
+        // トップレベルと、選択的開示クレームを持つオブジェクトにデコイを加える
+        if path.is_empty() || !digests.is_empty() {
+            for _ in 0..self.decoy_count(self.decoys.object) {
+                digests.push(Value::String(self.decoy_digest()));
+            }
+        }
+
+        if !digests.is_empty() {
+            // ダイジェストの順序からクレームの順序が分からないようにする
+            digests.shuffle(&mut self.rng);
+            concealed.insert("_sd".to_string(), Value::Array(digests));
+        }
+        concealed
+    }
+
+    pub fn into_disclosures(self) -> Vec<String> {
+        self.disclosures
+    }
+
+    fn conceal_value(&mut self, value: &Value, path: &str) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(self.conceal_object(object, path)),
+            Value::Array(elements) => Value::Array(self.conceal_array(elements, path)),
+            _ => value.clone(),
+        }
+    }
+
+    fn conceal_array(&mut self, elements: &[Value], path: &str) -> Vec<Value> {
+        let path = element_path(path);
+        let selectively_disclosable = self.is_selectively_disclosable(&path);
+
+        let mut concealed: Vec<Value> = elements
+            .iter()
+            .map(|element| {
+                let element = self.conceal_value(element, &path);
+                if selectively_disclosable {
+                    let disclosure =
+                        sd_jwt::create_array_element_disclosure(&self.salts.generate(), &element);
+                    json!({ ARRAY_ELEMENT_DIGEST_KEY: self.push_disclosure(disclosure) })
+                } else {
+                    element
+                }
+            })
+            .collect();
+
+        // 配列要素の順序には意味があるため、デコイはランダムな位置に挿入する
+        if selectively_disclosable {
+            for _ in 0..self.decoy_count(self.decoys.array) {
+                let position = self.rng.gen_range(0, concealed.len() + 1);
+                let decoy = json!({ ARRAY_ELEMENT_DIGEST_KEY: self.decoy_digest() });
+                concealed.insert(position, decoy);
+            }
+        }
+        concealed
+    }
+
+    fn decoy_count(&mut self, range: DecoyRange) -> usize {
+        if range.max <= range.min {
+            range.min
+        } else {
+            self.rng.gen_range(range.min, range.max + 1)
+        }
+    }
+
+    fn decoy_digest(&self) -> String {
+        sd_jwt::create_decoy_digest(self.alg, &self.salts.generate())
+    }
+
+    fn push_disclosure(&mut self, disclosure: String) -> String {
+        let digest = sd_jwt::hash_disclosure(self.alg, &disclosure);
+        self.disclosures.push(disclosure);
+        digest
+    }
+
+    fn is_selectively_disclosable(&self, path: &str) -> bool {
+        self.selectively_disclosable
+            .iter()
+            .any(|claim| claim == path)
+    }
+}
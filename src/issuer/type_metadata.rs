@@ -0,0 +1,164 @@
+use crate::issuer::schema;
+use crate::models::schema::{CredentialSchema, PropertyType};
+use crate::models::type_metadata::{
+    ClaimDisplayMetadata, ClaimMetadata, DisplayMetadata, TypeMetadata,
+};
+use crate::utils::integrity;
+use serde_json::{json, Map, Value};
+
+// vct はこの URL の下でクレデンシャルタイプごとに公開する
+const ISSUER_BASE_URL: &str = "http://localhost:8080";
+
+// SDJWTCredential の継承元となる基本タイプ
+const PERSON_CREDENTIAL_TYPE: &str = "PersonCredential";
+
+pub fn vct(credential_type: &str) -> String {
+    format!("{}/issuer/vct/{}", ISSUER_BASE_URL, credential_type)
+}
+
+// Issuer が公開する JSON-LD コンテキストの URL
+pub fn context_url(name: &str) -> String {
+    format!("{}/issuer/contexts/{}", ISSUER_BASE_URL, name)
+}
+
+pub fn get_type_metadata(credential_type: &str) -> Option<TypeMetadata> {
+    match credential_type {
+        PERSON_CREDENTIAL_TYPE => Some(TypeMetadata {
+            vct: vct(PERSON_CREDENTIAL_TYPE),
+            name: Some("Person".to_string()),
+            description: Some("Basic identity information of a person".to_string()),
+            extends: None,
+            extends_integrity: None,
+            display: vec![display("en-US", "Person"), display("ja-JP", "本人情報")],
+            claims: vec![
+                claim("given_name", "allowed", "Given name", "名"),
+                claim("family_name", "allowed", "Family name", "姓"),
+            ],
+            schema: Some(json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "properties": {
+                    "given_name": { "type": "string" },
+                    "family_name": { "type": "string" }
+                },
+                "required": ["iss", "vct"]
+            })),
+        }),
+        "SDJWTCredential" => {
+            let credential_schema = schema::get_schema(credential_type)?;
+            let base = type_metadata_document(PERSON_CREDENTIAL_TYPE)?;
+            Some(TypeMetadata {
+                vct: vct(credential_type),
+                name: Some("SD-JWT Credential".to_string()),
+                description: Some("Personal information with selective disclosure".to_string()),
+                extends: Some(vct(PERSON_CREDENTIAL_TYPE)),
+                extends_integrity: Some(integrity::integrity(&base)),
+                display: vec![
+                    display("en-US", "SD-JWT Credential"),
+                    display("ja-JP", "SD-JWT クレデンシャル"),
+                ],
+                claims: claims_metadata(&credential_schema),
+                schema: Some(json_schema(&credential_schema)),
+            })
+        }
+        _ => None,
+    }
+}
+
+// 公開するドキュメントのバイト列（vct#integrity / extends#integrity はこれに対して計算する）
+pub fn type_metadata_document(credential_type: &str) -> Option<Vec<u8>> {
+    get_type_metadata(credential_type).and_then(|metadata| serde_json::to_vec(&metadata).ok())
+}
+
+// Verifier が事前に登録しておくための、公開しているすべての Type Metadata
+pub fn published_type_metadata() -> Vec<(String, Vec<u8>)> {
+    [PERSON_CREDENTIAL_TYPE, "SDJWTCredential"]
+        .into_iter()
+        .filter_map(|credential_type| {
+            type_metadata_document(credential_type).map(|document| (vct(credential_type), document))
+        })
+        .collect()
+}
+
+// credentialSubject.id は sub として表すため、Type Metadata でも sub とする
+fn claim_name(property: &str) -> &str {
+    if property == "id" {
+        "sub"
+    } else {
+        property
+    }
+}
+
+fn claims_metadata(credential_schema: &CredentialSchema) -> Vec<ClaimMetadata> {
+    let policy = &credential_schema.selective_disclosure;
+    let mut properties: Vec<&String> = credential_schema.properties.keys().collect();
+    properties.sort();
+
+    properties
+        .into_iter()
+        .map(|property| {
+            let sd = if policy.always_disclosed.contains(property) {
+                "never"
+            } else {
+                "allowed"
+            };
+            ClaimMetadata {
+                path: vec![json!(claim_name(property))],
+                display: Vec::new(),
+                sd: Some(sd.to_string()),
+            }
+        })
+        .collect()
+}
+
+// 選択的開示されたクレームは提示されない場合があるため、required には含めない
+fn json_schema(credential_schema: &CredentialSchema) -> Value {
+    let mut properties = Map::new();
+    for (property, property_type) in &credential_schema.properties {
+        let json_type = match property_type {
+            PropertyType::String => "string",
+            PropertyType::Number => "number",
+            PropertyType::Boolean => "boolean",
+            PropertyType::Object => "object",
+            PropertyType::Array => "array",
+        };
+        properties.insert(
+            claim_name(property).to_string(),
+            json!({ "type": json_type }),
+        );
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": properties,
+        "required": ["iss", "vct"]
+    })
+}
+
+fn display(lang: &str, name: &str) -> DisplayMetadata {
+    DisplayMetadata {
+        lang: lang.to_string(),
+        name: name.to_string(),
+        description: None,
+    }
+}
+
+fn claim(name: &str, sd: &str, label_en: &str, label_ja: &str) -> ClaimMetadata {
+    ClaimMetadata {
+        path: vec![json!(name)],
+        display: vec![
+            ClaimDisplayMetadata {
+                lang: "en-US".to_string(),
+                label: label_en.to_string(),
+                description: None,
+            },
+            ClaimDisplayMetadata {
+                lang: "ja-JP".to_string(),
+                label: label_ja.to_string(),
+                description: None,
+            },
+        ],
+        sd: Some(sd.to_string()),
+    }
+}
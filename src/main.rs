@@ -2,29 +2,122 @@ use actix_web::{web, App, HttpServer};
 use env_logger::Env;
 use std::sync::Arc;
 
+mod did;
 mod holder;
 mod issuer;
 mod models;
 mod utils;
 mod verifier;
 
+use did::resolver::DidResolverRegistry;
 use holder::holder::Holder;
 use holder::storage::MemoryStorage;
+use std::path::Path;
+use utils::key_manager::{KeyManager, KeyManagerConfig};
+use verifier::key_resolver::{FileIssuerKeyResolver, IssuerKeyResolver, MetadataIssuerKeyResolver};
+use verifier::type_metadata::{
+    FileTypeMetadataResolver, InMemoryTypeMetadataResolver, TypeMetadataResolver,
+};
+
+// Verifier が信頼する Type Metadata を置くディレクトリ
+const TYPE_METADATA_DIR: &str = "type_metadata";
+// Verifier が信頼する Issuer のメタデータを置くディレクトリ
+const TRUSTED_ISSUERS_DIR: &str = "trusted_issuers";
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("debug")).init();
+
+    // vc-system migrate-keys [平文の鍵ファイル] [暗号化した鍵ファイル]
+    // ISSUER_KEY_PASSPHRASE のパスフレーズで鍵ファイルを暗号化し、平文の鍵ファイルを削除する
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("migrate-keys") {
+        let plaintext_path = args
+            .get(2)
+            .map(String::as_str)
+            .unwrap_or(utils::key_manager::KEY_FILE);
+        let encrypted_path = args
+            .get(3)
+            .map(String::as_str)
+            .unwrap_or(utils::key_manager::ENCRYPTED_KEY_FILE);
+        return utils::key_manager::migrate_key_file(
+            plaintext_path,
+            encrypted_path,
+            utils::key_manager::key_passphrase(),
+            utils::key_store::KdfParams::generate(),
+        )
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+    }
+
+    // Issuer の署名鍵と DID・cryptosuite（起動時に一度だけ読み込み、管理用 API から鍵をローテーションする）
+    let config = KeyManagerConfig::from_env()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    let issuer_config = Arc::new(config.issuer);
+    let key_manager: Arc<dyn KeyManager> = config
+        .keys
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+
     // ストレージの初期化
     let storage = Arc::new(MemoryStorage::new());
 
-    // Holder インスタンスの作成
-    let holder = Arc::new(Holder::new(storage));
+    // Holder インスタンスの作成（鍵は鍵ファイルから読み込み、なければ生成する）
+    let holder_key = utils::key_manager::load_or_create_signing_key(
+        &utils::key_manager::holder_key_file(),
+    )
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let holder = Arc::new(Holder::new(storage, holder_key));
+
+    // Type Metadata のリゾルバ（ディレクトリがなければ同じプロセスの Issuer が公開するものを使う）
+    let type_metadata: Arc<dyn TypeMetadataResolver> = if Path::new(TYPE_METADATA_DIR).is_dir() {
+        Arc::new(
+            FileTypeMetadataResolver::new(TYPE_METADATA_DIR)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+        )
+    } else {
+        let mut resolver = InMemoryTypeMetadataResolver::new();
+        for (vct, document) in issuer::type_metadata::published_type_metadata() {
+            resolver.insert(&vct, document);
+        }
+        Arc::new(resolver)
+    };
+
+    // DID リゾルバ（did:key、did:jwk、did:web）
+    let did_resolver = Arc::new(DidResolverRegistry::default());
+
+    // Issuer の鍵のリゾルバ（ディレクトリがなければ同じプロセスの Issuer だけを信頼し、
+    // 鍵のローテーションに追従するよう、解決のたびにメタデータを読み込む）
+    let issuer_keys: Arc<dyn IssuerKeyResolver> = if Path::new(TRUSTED_ISSUERS_DIR).is_dir() {
+        Arc::new(
+            FileIssuerKeyResolver::new(TRUSTED_ISSUERS_DIR, did_resolver.clone())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+        )
+    } else {
+        issuer::issuer::get_metadata(key_manager.as_ref(), &issuer_config)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        let key_manager = key_manager.clone();
+        let issuer_config = issuer_config.clone();
+        Arc::new(MetadataIssuerKeyResolver::new(move || {
+            issuer::issuer::get_metadata(key_manager.as_ref(), &issuer_config)
+                .map_err(|e| e.to_string())
+        }))
+    };
 
     // サーバーの設定と起動
     HttpServer::new(move || {
         App::new()
             // Holder のデータを共有データとして追加
             .app_data(web::Data::new(holder.clone()))
+            .app_data(web::Data::from(type_metadata.clone()))
+            .app_data(web::Data::from(issuer_keys.clone()))
+            .app_data(web::Data::from(did_resolver.clone()))
+            .app_data(web::Data::from(key_manager.clone()))
+            .app_data(web::Data::from(issuer_config.clone()))
+            // did:web で解決される Issuer の DID ドキュメント
+            .route(
+                "/.well-known/did.json",
+                web::get().to(issuer::api::get_did_document),
+            )
             // Holder のルートを設定
             .service(
                 web::scope("/holder")
@@ -33,6 +126,7 @@ async fn main() -> std::io::Result<()> {
                         web::post().to(holder::api::store_credential),
                     )
                     .route("/credentials", web::get().to(holder::api::get_credentials))
+                    .route("/jwk", web::get().to(holder::api::get_public_jwk))
                     .route(
                         "/presentations",
                         web::post().to(holder::api::create_presentation),
@@ -46,11 +140,26 @@ async fn main() -> std::io::Result<()> {
                         web::post().to(issuer::api::issue_credential),
                     )
                     .route("/metadata", web::get().to(issuer::api::get_issuer_metadata))
+                    .route(
+                        "/vct/{credential_type}",
+                        web::get().to(issuer::api::get_type_metadata),
+                    )
+                    .route("/contexts/{name}", web::get().to(issuer::api::get_context))
                     .route(
                         "/sd-jwt-credentials",
                         web::post().to(issuer::api::issue_sd_jwt_credential),
+                    )
+                    .route(
+                        "/admin/keys/rotate",
+                        web::post().to(issuer::api::rotate_key),
+                    )
+                    .route(
+                        "/admin/keys/reload",
+                        web::post().to(issuer::api::reload_keys),
                     ),
             )
+            // DID リゾルバのルートを設定
+            .service(web::scope("/resolver").route("/{did}", web::get().to(did::api::resolve_did)))
             // Verifier のルートを設定
             .service(
                 web::scope("/verifier")
@@ -69,12 +178,8 @@ async fn main() -> std::io::Result<()> {
     .await
 }
 
-use env_logger;
-
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn init_logger() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1,19 +1,148 @@
+use crate::utils::key_manager::KeyStatus;
+use crate::utils::signer::SignatureAlgorithm;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+// VC Data Model の各バージョンの基本コンテキスト（@context の最初の要素）
+pub const CREDENTIALS_V1_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+pub const CREDENTIALS_V2_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DataModelVersion {
+    #[default]
+    #[serde(rename = "1.1")]
+    V1_1,
+    #[serde(rename = "2.0")]
+    V2_0,
+}
+
+impl DataModelVersion {
+    pub fn context(self) -> &'static str {
+        match self {
+            DataModelVersion::V1_1 => CREDENTIALS_V1_CONTEXT,
+            DataModelVersion::V2_0 => CREDENTIALS_V2_CONTEXT,
+        }
+    }
+
+    pub fn from_context(context: &str) -> Option<Self> {
+        match context {
+            CREDENTIALS_V1_CONTEXT => Some(DataModelVersion::V1_1),
+            CREDENTIALS_V2_CONTEXT => Some(DataModelVersion::V2_0),
+            _ => None,
+        }
+    }
+}
+
+// VCDM 2.0 では issuer を id と name を持つオブジェクトとしても表せる
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Issuer {
+    Id(String),
+    Object {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+impl Default for Issuer {
+    fn default() -> Self {
+        Issuer::Id(String::new())
+    }
+}
+
+impl Issuer {
+    pub fn id(&self) -> &str {
+        match self {
+            Issuer::Id(id) | Issuer::Object { id, .. } => id,
+        }
+    }
+}
+
+impl From<String> for Issuer {
+    fn from(id: String) -> Self {
+        Issuer::Id(id)
+    }
+}
+
+impl fmt::Display for Issuer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+// Issuer の DID と比較する
+impl PartialEq<str> for Issuer {
+    fn eq(&self, other: &str) -> bool {
+        self.id() == other
+    }
+}
+
+impl PartialEq<&str> for Issuer {
+    fn eq(&self, other: &&str) -> bool {
+        self.id() == *other
+    }
+}
+
+impl PartialEq<String> for Issuer {
+    fn eq(&self, other: &String) -> bool {
+        self.id() == other
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct CredentialRequest {
     #[serde(rename = "@context")]
     pub context: Vec<String>,
     #[serde(rename = "type")]
     pub types: Vec<String>,
+    #[serde(default)]
     pub issuer: String,
-    #[serde(rename = "issuanceDate")]
+    // VCDM 2.0 のクレデンシャルタイプでは使わない（Issuer 側で validFrom を設定する）
+    #[serde(rename = "issuanceDate", default)]
     pub issuance_date: String,
     #[serde(rename = "credentialSubject")]
     pub credential_subject: serde_json::Value,
+    // 有効期間（秒）。省略時はクレデンシャルタイプの有効期間に従う
+    #[serde(
+        rename = "validitySeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub validity_seconds: Option<i64>,
+    // 指定された場合はそのままクレデンシャルに含める
+    #[serde(
+        rename = "credentialSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credential_schema: Option<serde_json::Value>,
+    #[serde(
+        rename = "credentialStatus",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credential_status: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<serde_json::Value>,
+    #[serde(
+        rename = "termsOfUse",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub terms_of_use: Option<serde_json::Value>,
+    #[serde(
+        rename = "refreshService",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub refresh_service: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// @context の最初の要素で VC Data Model 1.1 と 2.0 を区別する
+// 1.1 は issuanceDate / expirationDate、2.0 は validFrom / validUntil で有効期間を表す
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CredentialResponse {
     #[serde(rename = "@context")]
     pub context: Vec<String>,
@@ -21,11 +150,55 @@ pub struct CredentialResponse {
     pub id: Option<String>,
     #[serde(rename = "type")]
     pub types: Vec<String>,
-    pub issuer: String,
-    #[serde(rename = "issuanceDate")]
-    pub issuance_date: String,
+    pub issuer: Issuer,
+    #[serde(
+        rename = "issuanceDate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub issuance_date: Option<String>,
+    #[serde(
+        rename = "expirationDate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiration_date: Option<String>,
+    #[serde(rename = "validFrom", default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<String>,
+    #[serde(
+        rename = "validUntil",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_until: Option<String>,
     #[serde(rename = "credentialSubject")]
     pub credential_subject: serde_json::Value,
+    #[serde(
+        rename = "credentialSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credential_schema: Option<serde_json::Value>,
+    #[serde(
+        rename = "credentialStatus",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub credential_status: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<serde_json::Value>,
+    #[serde(
+        rename = "termsOfUse",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub terms_of_use: Option<serde_json::Value>,
+    #[serde(
+        rename = "refreshService",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub refresh_service: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proof: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -38,8 +211,22 @@ pub struct CredentialResponse {
 pub struct IssuerMetadata {
     pub id: String,
     pub name: String,
+    // 現在の署名鍵
     #[serde(rename = "publicKey")]
     pub public_key: PublicKeyInfo,
+    // 検証に使える鍵（現在の署名鍵と、ローテーションで退役した鍵）
+    #[serde(rename = "publicKeys", default, skip_serializing_if = "Vec::is_empty")]
+    pub public_keys: Vec<PublicKeyInfo>,
+}
+
+// 署名鍵のローテーション（algorithm を省略した場合は現在の鍵と同じアルゴリズム）
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct KeyRotationRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<SignatureAlgorithm>,
+    // 鍵が漏洩した場合などに、現在の鍵を退役ではなく失効させる
+    #[serde(rename = "revokeCurrent", default)]
+    pub revoke_current: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -47,8 +234,29 @@ pub struct PublicKeyInfo {
     pub id: String,
     #[serde(rename = "type")]
     pub key_type: String,
-    #[serde(rename = "publicKeyMultibase")]
-    pub public_key_multibase: String,
+    #[serde(
+        rename = "publicKeyMultibase",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub public_key_multibase: Option<String>,
+    #[serde(
+        rename = "publicKeyJwk",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub public_key_jwk: Option<serde_json::Value>,
+    // 鍵セットの状態と署名に使える期間
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<KeyStatus>,
+    #[serde(rename = "validFrom", default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<String>,
+    #[serde(
+        rename = "validUntil",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_until: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -57,6 +265,9 @@ pub struct PresentationRequest {
     pub verifiable_credential: Vec<String>,
     pub domain: String,
     pub challenge: String,
+    // クレデンシャル ID ごとに開示するクレームのパス（省略時はすべて開示）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disclose: Option<HashMap<String, Vec<String>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,15 +276,11 @@ pub struct VerifiablePresentation {
     pub context: Vec<String>,
     #[serde(rename = "type")]
     pub types: Vec<String>,
+    // VP に署名した Holder の DID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
     #[serde(rename = "verifiableCredential")]
     pub verifiable_credential: Vec<CredentialResponse>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proof: Option<serde_json::Value>,
 }
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SDJWTCredentialResponse {
-    pub verifiable_credential: CredentialResponse,
-    pub sd_jwt: String,
-    pub disclosures: Vec<String>,
-}
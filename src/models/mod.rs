@@ -1,3 +1,5 @@
 pub mod credential;
+pub mod did;
 pub mod schema;
-pub mod sd_jwt;
\ No newline at end of file
+pub mod sd_jwt;
+pub mod type_metadata;
\ No newline at end of file
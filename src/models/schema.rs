@@ -1,3 +1,5 @@
+use crate::models::credential::DataModelVersion;
+use crate::utils::sd_jwt::SdAlg;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -7,6 +9,43 @@ pub struct CredentialSchema {
     pub type_name: String,
     pub properties: HashMap<String, PropertyType>,
     pub required: Vec<String>,
+    #[serde(default)]
+    pub selective_disclosure: SelectiveDisclosurePolicy,
+    // このタイプのクレデンシャルを発行する VC Data Model のバージョン
+    #[serde(default)]
+    pub data_model: DataModelVersion,
+    // 有効期間（秒）。リクエストで指定がない場合に使い、None の場合は無期限とする
+    #[serde(default)]
+    pub validity_seconds: Option<i64>,
+    // リクエストで指定できる有効期間の上限（秒）
+    #[serde(default)]
+    pub max_validity_seconds: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SelectiveDisclosurePolicy {
+    // リクエストで指定がない場合に選択的開示とするクレーム
+    pub selectively_disclosable: Vec<String>,
+    // 常に平文で含め、選択的開示を許可しないクレーム
+    pub always_disclosed: Vec<String>,
+    #[serde(default)]
+    pub decoys: DecoyPolicy,
+    // _sd_alg（指定がない場合は Issuer の既定のアルゴリズムを使う）
+    #[serde(default)]
+    pub hash_algorithm: Option<SdAlg>,
+}
+
+// 隠されたクレーム数を推測されないよう追加するデコイダイジェストの数
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DecoyPolicy {
+    pub object: DecoyRange,
+    pub array: DecoyRange,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct DecoyRange {
+    pub min: usize,
+    pub max: usize,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -7,7 +7,23 @@ use serde_json::Value;
 pub struct SDJWTCredentialRequest {
     #[serde(rename = "credentialSubject")]
     pub credential_subject: Value,
-    //pub selective_disclosure: Vec<String>,
+    // 省略時はクレデンシャルタイプのポリシーに従う
+    #[serde(
+        rename = "selectiveDisclosure",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub selective_disclosure: Option<Vec<String>>,
+    // 指定された場合は cnf.jwk として SD-JWT に埋め込む
+    #[serde(rename = "holderJwk", default, skip_serializing_if = "Option::is_none")]
+    pub holder_jwk: Option<Value>,
+    // 有効期間（秒）。省略時はクレデンシャルタイプの有効期間に従う
+    #[serde(
+        rename = "validitySeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub validity_seconds: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// SD-JWT VC Type Metadata（vct が指すクレデンシャルタイプの定義）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeMetadata {
+    pub vct: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // 継承元のタイプの vct と、そのドキュメントの Subresource Integrity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(
+        rename = "extends#integrity",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub extends_integrity: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub display: Vec<DisplayMetadata>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub claims: Vec<ClaimMetadata>,
+    // 処理済みのペイロードを検証する JSON Schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayMetadata {
+    pub lang: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimMetadata {
+    // クレームのパス（文字列はプロパティ名、null は配列の全要素）
+    pub path: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub display: Vec<ClaimDisplayMetadata>,
+    // "always" / "allowed" / "never"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sd: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimDisplayMetadata {
+    pub lang: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
@@ -1,99 +1,245 @@
 // src/utils/crypto.rs
+use crate::did;
 use crate::models::credential::PublicKeyInfo;
+use crate::models::did::{DidDocument, VerificationMethod};
+use crate::utils::data_integrity::{self, ProofOptions, DATA_INTEGRITY_PROOF_TYPE};
 use crate::utils::error::UtilsError;
-use crate::utils::key_manager::{FileKeyManager, KeyManager};
-use base58::{FromBase58, ToBase58};
+use crate::utils::key_manager::{IssuerConfig, KeyManager, KeyStatus, ManagedKey};
+use crate::utils::sd_jwt::SD_JWT_VC_TYPE;
+use crate::utils::signer::{SignatureAlgorithm, SignatureVerifier, Signer, VerifyingKey};
+use crate::utils::{jcs, jwk};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use chrono::Utc;
-use ed25519_dalek::{PublicKey, Signature, Signer, Verifier};
 use log::debug;
 use serde_json::Value;
 
-fn get_key_manager() -> impl KeyManager {
-    FileKeyManager::new("keys/keys.json".to_string())
+// Ed25519 以外の鍵で署名した VC の proof は、RFC 7797 の detached JWS で表す
+const JWS_PROOF_TYPE: &str = "JsonWebSignature2020";
+
+fn current_key(key_manager: &dyn KeyManager) -> Result<ManagedKey, UtilsError> {
+    key_manager
+        .get_current_key()
+        .map_err(|e| UtilsError::SignatureError(e.to_string()))
 }
 
-pub fn get_public_key() -> Result<PublicKey, UtilsError> {
-    get_key_manager()
-        .get_public_key()
-        .map_err(|_| UtilsError::SignatureError("Failed to get public key".to_string()))
+// 現在の署名鍵とその検証メソッドの ID（VC の verificationMethod と SD-JWT の kid）
+fn current_signer(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<(Box<dyn Signer>, String), UtilsError> {
+    let key = current_key(key_manager)?;
+    let signer = key_manager
+        .get_signer(&key.kid)
+        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
+    let key_id = key_id(&issuer_did_for(issuer_config, &key)?, &key)?;
+    Ok((signer, key_id))
 }
 
-pub fn get_public_key_info() -> Result<PublicKeyInfo, UtilsError> {
-    let key_manager = get_key_manager();
-    let public_key = key_manager
-        .get_public_key()
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?; // ここでエラーを `UtilsError` に変換
-    Ok(PublicKeyInfo {
-        id: "did:example:123#key-1".to_string(),
-        key_type: "Ed25519VerificationKey2020".to_string(),
-        public_key_multibase: format!("z{}", public_key.to_bytes().to_base58()),
-    })
+// Issuer の DID（did:web が設定されていなければ現在の署名鍵の did:key）
+pub fn issuer_did(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<String, UtilsError> {
+    issuer_did_for(issuer_config, &current_key(key_manager)?)
 }
 
-fn sort_json(value: &Value) -> Value {
-    match value {
-        Value::Object(map) => {
-            let mut sorted_map = serde_json::Map::new();
-            let mut keys: Vec<_> = map.keys().collect();
-            keys.sort();
-            for key in keys {
-                sorted_map.insert(key.clone(), sort_json(&map[key]));
-            }
-            Value::Object(sorted_map)
-        }
-        Value::Array(arr) => Value::Array(arr.iter().map(sort_json).collect()),
-        _ => value.clone(),
+fn issuer_did_for(
+    issuer_config: &IssuerConfig,
+    current_key: &ManagedKey,
+) -> Result<String, UtilsError> {
+    match &issuer_config.did {
+        Some(did) => Ok(did.clone()),
+        None => did::key::did_key(&current_key.public_key)
+            .map_err(|e| UtilsError::InvalidKey(e.to_string())),
     }
 }
 
-pub fn sign_vc(json: &Value) -> Result<Value, UtilsError> {
-    let key_manager = get_key_manager();
-    let keypair = key_manager
-        .get_keypair()
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
-    let sorted_json = sort_json(json);
-    let message = serde_json::to_string(&sorted_json)
-        .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?;
-    debug!("Signing VC message: {}", message);
+// did:key は鍵ごとに DID が異なるため鍵から導出し、did:web は鍵セットの kid をフラグメントにする
+fn key_id(did: &str, key: &ManagedKey) -> Result<String, UtilsError> {
+    if did::key::is_did_key(did) {
+        did::key::did_key(&key.public_key)
+            .and_then(|did| did::key::key_id(&did))
+            .map_err(|e| UtilsError::InvalidKey(e.to_string()))
+    } else {
+        Ok(format!("{}#{}", did, key.kid))
+    }
+}
 
-    let signature = keypair.sign(message.as_bytes());
-    debug!("VC Signature created: {}", signature.to_bytes().to_base58());
+// 失効していない鍵（退役した鍵も、それで署名されたクレデンシャルを検証できるよう公開し続ける）
+fn published_keys(key_manager: &dyn KeyManager) -> Result<Vec<ManagedKey>, UtilsError> {
+    Ok(key_manager
+        .get_keys()
+        .map_err(|e| UtilsError::SignatureError(e.to_string()))?
+        .into_iter()
+        .filter(|key| key.status != KeyStatus::Revoked)
+        .collect())
+}
+
+// did:web の場合に /.well-known/did.json として公開する Issuer の DID ドキュメント
+pub fn issuer_did_document(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<DidDocument, UtilsError> {
+    did_document(&issuer_did(key_manager, issuer_config)?, key_manager)
+}
+
+// did:web の DID ドキュメントには公開しているすべての鍵を assertionMethod として含める
+pub fn did_document(did: &str, key_manager: &dyn KeyManager) -> Result<DidDocument, UtilsError> {
+    if did::key::is_did_key(did) {
+        return did::key::resolve(did).map_err(|e| UtilsError::InvalidKey(e.to_string()));
+    }
+
+    let mut verification_method = Vec::new();
+    for key in published_keys(key_manager)? {
+        verification_method.push(VerificationMethod {
+            id: key_id(did, &key)?,
+            method_type: "JsonWebKey2020".to_string(),
+            controller: did.to_string(),
+            public_key_multibase: None,
+            public_key_jwk: Some(jwk::to_jwk(&key.public_key)),
+        });
+    }
+    Ok(DidDocument {
+        context: vec![
+            did::document::DID_CONTEXT.to_string(),
+            did::document::JWS_2020_CONTEXT.to_string(),
+        ],
+        id: did.to_string(),
+        assertion_method: verification_method
+            .iter()
+            .map(|method| method.id.clone())
+            .collect(),
+        verification_method,
+        authentication: vec![],
+    })
+}
+
+// 現在の署名鍵
+pub fn issuer_public_key_info(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<PublicKeyInfo, UtilsError> {
+    let key = current_key(key_manager)?;
+    public_key_info(&issuer_did_for(issuer_config, &key)?, &key)
+}
 
-    Ok(serde_json::json!({
-        "type": "Ed25519Signature2020",
+// 公開しているすべての鍵（現在の署名鍵と退役した鍵）
+pub fn issuer_public_key_infos(
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<Vec<PublicKeyInfo>, UtilsError> {
+    let did = issuer_did(key_manager, issuer_config)?;
+    published_keys(key_manager)?
+        .iter()
+        .map(|key| public_key_info(&did, key))
+        .collect()
+}
+
+fn public_key_info(did: &str, key: &ManagedKey) -> Result<PublicKeyInfo, UtilsError> {
+    let (key_type, public_key_multibase, public_key_jwk) = match &key.public_key {
+        VerifyingKey::Ed25519(public_key) => (
+            "Ed25519VerificationKey2020",
+            Some(format!(
+                "z{}",
+                bs58::encode(public_key.to_bytes()).into_string()
+            )),
+            None,
+        ),
+        public_key => ("JsonWebKey2020", None, Some(jwk::to_jwk(public_key))),
+    };
+    Ok(PublicKeyInfo {
+        id: key_id(did, key)?,
+        key_type: key_type.to_string(),
+        public_key_multibase,
+        public_key_jwk,
+        status: Some(key.status),
+        valid_from: key.not_before.map(|time| time.to_rfc3339()),
+        valid_until: key.not_after.map(|time| time.to_rfc3339()),
+    })
+}
+
+pub fn sign_vc(
+    json: &Value,
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<Value, UtilsError> {
+    let (signer, key_id) = current_signer(key_manager, issuer_config)?;
+    let options = ProofOptions {
+        cryptosuite: issuer_config.cryptosuite,
+        ..ProofOptions::default()
+    };
+    create_proof(json, signer.as_ref(), &key_id, &options)
+}
+
+// 任意の鍵で proof を作成する（VP の場合は Holder の鍵）
+// Ed25519 の鍵は Data Integrity の proof、それ以外の鍵は detached JWS にする
+pub fn create_proof(
+    json: &Value,
+    signer: &dyn Signer,
+    verification_method: &str,
+    options: &ProofOptions,
+) -> Result<Value, UtilsError> {
+    if signer.algorithm() == SignatureAlgorithm::EdDSA {
+        let proof = data_integrity::create_proof(json, signer, verification_method, options)?;
+        debug!("VC proof created with {}", options.cryptosuite.name());
+        return Ok(proof);
+    }
+
+    let mut proof = serde_json::json!({
+        "type": JWS_PROOF_TYPE,
         "created": Utc::now().to_rfc3339(),
-        "verificationMethod": "did:example:123#key-1",
+        "verificationMethod": verification_method,
         "proofPurpose": "assertionMethod",
-        "proofValue": signature.to_bytes().to_base58(),
-    }))
+    });
+    for (name, value) in [
+        ("expires", &options.expires),
+        ("domain", &options.domain),
+        ("challenge", &options.challenge),
+    ] {
+        if let Some(value) = value {
+            proof[name] = value.clone().into();
+        }
+    }
+    // domain、challenge、expires を書き換えられないよう、jws を除いた proof も署名の対象にする
+    let message = data_integrity::jcs_hash_data(json, &proof)?;
+    debug!("Signing VC proof with {}", signer.algorithm().name());
+    proof["jws"] = sign_detached_jws(&message, signer)?.into();
+    Ok(proof)
 }
 
-pub fn sign_sd_jwt(json: &Value) -> Result<String, UtilsError> {
-    let key_manager = get_key_manager();
-    let keypair = key_manager
-        .get_keypair()
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
-    let sorted_json = sort_json(json);
-    let message = serde_json::to_string(&sorted_json)
-        .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?;
+pub fn sign_sd_jwt(
+    json: &Value,
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<String, UtilsError> {
+    let (signer, key_id) = current_signer(key_manager, issuer_config)?;
 
     let header = serde_json::json!({
-        "alg": "EdDSA",
-        "typ": "JWT"
+        "typ": SD_JWT_VC_TYPE,
+        "kid": key_id
     });
 
-    let header_encoded = URL_SAFE_NO_PAD.encode(
-        serde_json::to_string(&header)
-            .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?,
-    );
-    let payload_encoded = URL_SAFE_NO_PAD.encode(&message);
+    let jwt = sign_jwt(&header, json, signer.as_ref())?;
+    debug!("SD-JWT created: {}", jwt);
+    Ok(jwt)
+}
+
+// 任意の鍵で JWS Compact Serialization を作成する
+// alg ヘッダーは署名鍵のアルゴリズムに合わせて設定する
+pub fn sign_jwt(
+    header: &Value,
+    payload: &Value,
+    signer: &dyn Signer,
+) -> Result<String, UtilsError> {
+    let mut header = header.clone();
+    header["alg"] = signer.algorithm().name().into();
 
-    let signature_input = format!("{}.{}", header_encoded, payload_encoded);
-    let signature = keypair.sign(signature_input.as_bytes());
-    let signature_encoded = URL_SAFE_NO_PAD.encode(signature.to_bytes());
+    let header_encoded = URL_SAFE_NO_PAD.encode(jcs::canonicalize(&header)?);
+    let payload_encoded = URL_SAFE_NO_PAD.encode(jcs::canonicalize(payload)?);
 
-    debug!("SD-JWT Signature created: {}", signature_encoded);
+    let signature_input = format!("{}.{}", header_encoded, payload_encoded);
+    let signature = signer.sign(signature_input.as_bytes())?;
+    let signature_encoded = URL_SAFE_NO_PAD.encode(signature);
 
     Ok(format!(
         "{}.{}.{}",
@@ -101,58 +247,181 @@ pub fn sign_sd_jwt(json: &Value) -> Result<String, UtilsError> {
     ))
 }
 
-pub fn sign_json(json: &Value) -> Result<Value, UtilsError> {
+// ペイロードを含めない JWS（<header>..<signature>）で任意のバイト列に署名する
+fn sign_detached_jws(message: &[u8], signer: &dyn Signer) -> Result<String, UtilsError> {
+    let header = serde_json::json!({
+        "alg": signer.algorithm().name(),
+        "b64": false,
+        "crit": ["b64"],
+    });
+    let header_encoded = URL_SAFE_NO_PAD.encode(jcs::canonicalize(&header)?);
+
+    let mut signature_input = format!("{}.", header_encoded).into_bytes();
+    signature_input.extend_from_slice(message);
+    let signature = signer.sign(&signature_input)?;
+
+    Ok(format!(
+        "{}..{}",
+        header_encoded,
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+// JWT のヘッダーとペイロードを署名検証せずに取り出す
+pub fn decode_jwt(jwt: &str) -> Result<(Value, Value), UtilsError> {
+    let parts: Vec<&str> = jwt.split('.').collect();
+    if parts.len() != 3 {
+        return Err(UtilsError::InvalidJwt("Invalid JWT format".to_string()));
+    }
+
+    let decode_part = |part: &str| -> Result<Value, UtilsError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| UtilsError::InvalidJwt("Invalid base64 encoding".to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|e| UtilsError::InvalidJwt(e.to_string()))
+    };
+
+    Ok((decode_part(parts[0])?, decode_part(parts[1])?))
+}
+
+pub fn sign_json(
+    json: &Value,
+    key_manager: &dyn KeyManager,
+    issuer_config: &IssuerConfig,
+) -> Result<Value, UtilsError> {
     if json.get("_sd_alg").is_some() {
-        sign_sd_jwt(json).map(Value::String)
+        sign_sd_jwt(json, key_manager, issuer_config).map(Value::String)
     } else {
-        sign_vc(json)
+        sign_vc(json, key_manager, issuer_config)
     }
 }
 
-pub fn verify_vc<T: serde::Serialize>(data: &T, proof: &Value) -> Result<bool, UtilsError> {
-    let key_manager = get_key_manager();
-    let public_key = key_manager
-        .get_public_key()
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?; // ここでも同様にエラーを変換
-
-    let message = serde_json::to_string(&sort_json(
-        &serde_json::to_value(data)
-            .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?,
-    ))
-    .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?;
-    let signature_base58 = proof["proofValue"]
-        .as_str()
-        .ok_or(UtilsError::SignatureError(
-            "Invalid proof value".to_string(),
-        ))?;
-    let signature_bytes = signature_base58
-        .from_base58()
-        .map_err(|_| UtilsError::SignatureError("Invalid base58 encoding".to_string()))?;
-    let signature = Signature::from_bytes(&signature_bytes)
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
+// 検証鍵は proof.verificationMethod から呼び出し側で解決したものを渡す
+pub fn verify_vc<T: serde::Serialize>(
+    data: &T,
+    proof: &Value,
+    public_key: &VerifyingKey,
+    allowed_algorithms: &[SignatureAlgorithm],
+) -> Result<bool, UtilsError> {
+    let document = serde_json::to_value(data)
+        .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))?;
+    match proof["type"].as_str() {
+        Some(DATA_INTEGRITY_PROOF_TYPE) => {
+            SignatureAlgorithm::EdDSA.ensure_allowed(allowed_algorithms)?;
+            data_integrity::verify_proof(&document, proof, public_key)?;
+        }
+        Some(JWS_PROOF_TYPE) => {
+            let jws = proof["jws"]
+                .as_str()
+                .ok_or(UtilsError::SignatureError("Missing jws".to_string()))?;
+            let mut proof_options = proof.clone();
+            if let Some(proof_options) = proof_options.as_object_mut() {
+                proof_options.remove("jws");
+            }
+            let message = data_integrity::jcs_hash_data(&document, &proof_options)?;
+            verify_detached_jws(jws, &message, public_key, allowed_algorithms)?;
+        }
+        _ => {
+            return Err(UtilsError::SignatureError(format!(
+                "Unsupported proof type: {}",
+                proof["type"]
+            )))
+        }
+    }
+    Ok(true)
+}
 
-    public_key
-        .verify(message.as_bytes(), &signature)
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
+pub fn verify_sd_jwt(
+    jwt: &str,
+    public_key: &VerifyingKey,
+    allowed_algorithms: &[SignatureAlgorithm],
+) -> Result<bool, UtilsError> {
+    verify_jwt(jwt, public_key, allowed_algorithms)?;
     Ok(true)
 }
 
-pub fn verify_sd_jwt(jwt: &str) -> Result<bool, UtilsError> {
-    let public_key = get_public_key()?;
+// ヘッダーの alg が許可リストにあり、検証鍵の種類と一致する場合のみ署名を検証する
+pub fn verify_jwt(
+    jwt: &str,
+    public_key: &VerifyingKey,
+    allowed_algorithms: &[SignatureAlgorithm],
+) -> Result<(), UtilsError> {
     let parts: Vec<&str> = jwt.split('.').collect();
     if parts.len() != 3 {
         return Err(UtilsError::SignatureError("Invalid JWT format".to_string()));
     }
 
+    let (header, _) = decode_jwt(jwt)?;
+    let algorithm = jws_algorithm(&header, allowed_algorithms)?;
+
     let signature_input = format!("{}.{}", parts[0], parts[1]);
-    let signature_bytes = URL_SAFE_NO_PAD
+    let signature = URL_SAFE_NO_PAD
         .decode(parts[2])
         .map_err(|_| UtilsError::SignatureError("Invalid base64 encoding".to_string()))?;
-    let signature = Signature::from_bytes(&signature_bytes)
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
 
-    public_key
-        .verify(signature_input.as_bytes(), &signature)
-        .map_err(|e| UtilsError::SignatureError(e.to_string()))?;
-    Ok(true)
-}
\ No newline at end of file
+    verify_signature(
+        public_key,
+        algorithm,
+        signature_input.as_bytes(),
+        &signature,
+    )
+}
+
+fn verify_detached_jws(
+    jws: &str,
+    message: &[u8],
+    public_key: &VerifyingKey,
+    allowed_algorithms: &[SignatureAlgorithm],
+) -> Result<(), UtilsError> {
+    let (header_encoded, signature_encoded) = jws
+        .split_once("..")
+        .ok_or_else(|| UtilsError::SignatureError("Invalid detached JWS".to_string()))?;
+    let header: Value = URL_SAFE_NO_PAD
+        .decode(header_encoded)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| UtilsError::SignatureError("Invalid JWS header".to_string()))?;
+    if header["b64"] != false {
+        return Err(UtilsError::SignatureError(
+            "Detached JWS must use an unencoded payload".to_string(),
+        ));
+    }
+    let algorithm = jws_algorithm(&header, allowed_algorithms)?;
+
+    let mut signature_input = format!("{}.", header_encoded).into_bytes();
+    signature_input.extend_from_slice(message);
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature_encoded)
+        .map_err(|_| UtilsError::SignatureError("Invalid base64 encoding".to_string()))?;
+
+    verify_signature(public_key, algorithm, &signature_input, &signature)
+}
+
+fn jws_algorithm(
+    header: &Value,
+    allowed_algorithms: &[SignatureAlgorithm],
+) -> Result<SignatureAlgorithm, UtilsError> {
+    let name = header["alg"]
+        .as_str()
+        .ok_or_else(|| UtilsError::SignatureError("Missing alg".to_string()))?;
+    let algorithm = SignatureAlgorithm::from_name(name)?;
+    algorithm.ensure_allowed(allowed_algorithms)?;
+    Ok(algorithm)
+}
+
+// alg と鍵の種類が異なる場合は、別のアルゴリズムとして検証させない
+fn verify_signature(
+    public_key: &VerifyingKey,
+    algorithm: SignatureAlgorithm,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), UtilsError> {
+    if public_key.algorithm() != algorithm {
+        return Err(UtilsError::SignatureError(format!(
+            "Algorithm {} does not match the {} key",
+            algorithm.name(),
+            public_key.algorithm().name()
+        )));
+    }
+    public_key.verify(message, signature)
+}
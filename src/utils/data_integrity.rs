@@ -0,0 +1,261 @@
+use crate::utils::error::UtilsError;
+use crate::utils::signer::{SignatureAlgorithm, SignatureVerifier, Signer, VerifyingKey};
+use crate::utils::{jcs, json_ld};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+// W3C Data Integrity の EdDSA Cryptosuites v1.0（eddsa-jcs-2022 / eddsa-rdfc-2022）
+pub const DATA_INTEGRITY_PROOF_TYPE: &str = "DataIntegrityProof";
+// VC Data Model 1.1 は DataIntegrityProof の語彙を定義していないため、このコンテキストを追加する
+pub const DATA_INTEGRITY_CONTEXT: &str = "https://w3id.org/security/data-integrity/v2";
+
+// proofValue は multibase の base58btc で表す
+const BASE58_BTC: char = 'z';
+const ED25519_SIGNATURE_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cryptosuite {
+    // RFC 8785 の JCS で正規化する
+    #[default]
+    EddsaJcs2022,
+    // JSON-LD を RDF に変換し、RDFC-1.0 で正規化する
+    EddsaRdfc2022,
+}
+
+impl Cryptosuite {
+    pub const ALL: [Cryptosuite; 2] = [Cryptosuite::EddsaJcs2022, Cryptosuite::EddsaRdfc2022];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cryptosuite::EddsaJcs2022 => "eddsa-jcs-2022",
+            Cryptosuite::EddsaRdfc2022 => "eddsa-rdfc-2022",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, UtilsError> {
+        Cryptosuite::ALL
+            .into_iter()
+            .find(|cryptosuite| cryptosuite.name() == name)
+            .ok_or_else(|| UtilsError::SignatureError(format!("Unsupported cryptosuite: {}", name)))
+    }
+}
+
+// proof に含めて署名する値（VP では Verifier の domain と challenge）
+#[derive(Clone, Debug, Default)]
+pub struct ProofOptions {
+    pub cryptosuite: Cryptosuite,
+    pub domain: Option<String>,
+    pub challenge: Option<String>,
+    pub expires: Option<String>,
+}
+
+pub fn create_proof(
+    document: &Value,
+    signer: &dyn Signer,
+    verification_method: &str,
+    options: &ProofOptions,
+) -> Result<Value, UtilsError> {
+    if signer.algorithm() != SignatureAlgorithm::EdDSA {
+        return Err(UtilsError::SignatureError(format!(
+            "{} requires an Ed25519 key",
+            options.cryptosuite.name()
+        )));
+    }
+
+    let mut proof = serde_json::json!({
+        "type": DATA_INTEGRITY_PROOF_TYPE,
+        "cryptosuite": options.cryptosuite.name(),
+        "created": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        "verificationMethod": verification_method,
+        "proofPurpose": "assertionMethod",
+    });
+    for (name, value) in [
+        ("expires", &options.expires),
+        ("domain", &options.domain),
+        ("challenge", &options.challenge),
+    ] {
+        if let Some(value) = value {
+            proof[name] = value.clone().into();
+        }
+    }
+
+    let hash_data = hash_data(document, &proof, options.cryptosuite)?;
+    let signature = signer.sign(&hash_data)?;
+    proof["proofValue"] = format!("{}{}", BASE58_BTC, bs58::encode(signature).into_string()).into();
+    Ok(proof)
+}
+
+// document は proof を取り除いた文書
+pub fn verify_proof(
+    document: &Value,
+    proof: &Value,
+    public_key: &VerifyingKey,
+) -> Result<(), UtilsError> {
+    if proof["type"] != DATA_INTEGRITY_PROOF_TYPE {
+        return Err(UtilsError::SignatureError(format!(
+            "Unsupported proof type: {}",
+            proof["type"]
+        )));
+    }
+    let cryptosuite = Cryptosuite::from_name(proof["cryptosuite"].as_str().unwrap_or_default())?;
+    let signature = proof["proofValue"]
+        .as_str()
+        .and_then(|value| value.strip_prefix(BASE58_BTC))
+        .and_then(|value| bs58::decode(value).into_vec().ok())
+        .filter(|signature| signature.len() == ED25519_SIGNATURE_LEN)
+        .ok_or_else(|| UtilsError::SignatureError("Invalid proofValue".to_string()))?;
+
+    let mut proof_options = proof.clone();
+    if let Some(proof_options) = proof_options.as_object_mut() {
+        proof_options.remove("proofValue");
+    }
+    let mut document = document.clone();
+    if let Some(document) = document.as_object_mut() {
+        document.remove("proof");
+    }
+    // proof に @context がある場合、文書の @context はそれで始まっていなければならない
+    if cryptosuite == Cryptosuite::EddsaJcs2022 {
+        if let Some(context) = proof_options.get("@context") {
+            let expected = as_array(context);
+            let actual = as_array(&document["@context"]);
+            if !actual.starts_with(&expected) {
+                return Err(UtilsError::SignatureError(
+                    "Document @context does not start with the proof @context".to_string(),
+                ));
+            }
+            document["@context"] = context.clone();
+        }
+    }
+
+    if public_key.algorithm() != SignatureAlgorithm::EdDSA {
+        return Err(UtilsError::SignatureError(format!(
+            "{} requires an Ed25519 key",
+            cryptosuite.name()
+        )));
+    }
+    let hash_data = hash_data(&document, &proof_options, cryptosuite)?;
+    public_key.verify(&hash_data, &signature)
+}
+
+// JsonWebSignature2020 の proof も、同じように JCS で正規化した proof の設定と文書に署名する
+pub fn jcs_hash_data(document: &Value, proof_options: &Value) -> Result<Vec<u8>, UtilsError> {
+    hash_data(document, proof_options, Cryptosuite::EddsaJcs2022)
+}
+
+// 正規化した proof の設定と文書それぞれの SHA-256 を連結したものに署名する
+fn hash_data(
+    document: &Value,
+    proof_options: &Value,
+    cryptosuite: Cryptosuite,
+) -> Result<Vec<u8>, UtilsError> {
+    for name in ["created", "expires"] {
+        if let Some(value) = proof_options.get(name) {
+            if value
+                .as_str()
+                .is_none_or(|value| DateTime::parse_from_rfc3339(value).is_err())
+            {
+                return Err(UtilsError::SignatureError(format!(
+                    "Invalid proof {}: {}",
+                    name, value
+                )));
+            }
+        }
+    }
+
+    let mut proof_config = proof_options.clone();
+    if let Some(context) = document.get("@context") {
+        proof_config["@context"] = context.clone();
+    }
+    let (canonical_config, canonical_document) = match cryptosuite {
+        Cryptosuite::EddsaJcs2022 => (
+            jcs::canonicalize(&proof_config)?,
+            jcs::canonicalize(document)?,
+        ),
+        Cryptosuite::EddsaRdfc2022 => (
+            json_ld::canonicalize(&proof_config)?,
+            json_ld::canonicalize(document)?,
+        ),
+    };
+
+    let mut hash_data = Sha256::digest(canonical_config.as_bytes()).to_vec();
+    hash_data.extend_from_slice(&Sha256::digest(canonical_document.as_bytes()));
+    Ok(hash_data)
+}
+
+fn as_array(value: &Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values.clone(),
+        Value::Null => Vec::new(),
+        value => vec![value.clone()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did;
+
+    #[test]
+    fn test_data_integrity_test_vectors() {
+        // Data Integrity EdDSA Cryptosuites v1.0 の例（鍵と proofValue は仕様のもの）
+        let document = serde_json::json!({
+            "@context": [
+                "https://www.w3.org/ns/credentials/v2",
+                "https://www.w3.org/ns/credentials/examples/v2"
+            ],
+            "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+            "type": ["VerifiableCredential", "AlumniCredential"],
+            "name": "Alumni Credential",
+            "description": "A minimum viable example of an Alumni Credential.",
+            "issuer": "https://vc.example/issuers/5678",
+            "validFrom": "2023-01-01T00:00:00Z",
+            "credentialSubject": {
+                "id": "did:example:abcdefgh",
+                "alumniOf": "The School of Examples"
+            }
+        });
+        let subject = "<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33>";
+        let expected: String = [
+            "<did:example:abcdefgh> <https://www.w3.org/ns/credentials/examples#alumniOf> \"The School of Examples\" .".to_string(),
+            format!("{} <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/2018/credentials#VerifiableCredential> .", subject),
+            format!("{} <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/ns/credentials/examples#AlumniCredential> .", subject),
+            format!("{} <https://schema.org/description> \"A minimum viable example of an Alumni Credential.\" .", subject),
+            format!("{} <https://schema.org/name> \"Alumni Credential\" .", subject),
+            format!("{} <https://www.w3.org/2018/credentials#credentialSubject> <did:example:abcdefgh> .", subject),
+            format!("{} <https://www.w3.org/2018/credentials#issuer> <https://vc.example/issuers/5678> .", subject),
+            format!("{} <https://www.w3.org/2018/credentials#validFrom> \"2023-01-01T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime> .", subject),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+        assert_eq!(json_ld::canonicalize(&document).unwrap(), expected);
+
+        let public_key =
+            did::key::decode_multikey("z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2").unwrap();
+        for (cryptosuite, proof_value) in [
+            (
+                Cryptosuite::EddsaRdfc2022,
+                "z2YwC8z3ap7yx1nZYCg4L3j3ApHsF8kgPdSb5xoS1VR7vPG3F561B52hYnQF9iseabecm3ijx4K1FBTQsCZahKZme",
+            ),
+            (
+                Cryptosuite::EddsaJcs2022,
+                "z2HnFSSPPBzR36zdDgK8PbEHeXbR56YF24jwMpt3R1eHXQzJDMWS93FCzpvJpwTWd3GAVFuUfjoJdcnTMuVor51aX",
+            ),
+        ] {
+            let mut proof = serde_json::json!({
+                "type": "DataIntegrityProof",
+                "cryptosuite": cryptosuite.name(),
+                "created": "2023-02-24T23:36:38Z",
+                "verificationMethod": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2#z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+                "proofPurpose": "assertionMethod",
+                "proofValue": proof_value
+            });
+            let result = verify_proof(&document, &proof, &public_key);
+            assert!(result.is_ok(), "{}: {:?}", cryptosuite.name(), result);
+
+            proof["created"] = "2023-02-24T23:36:39Z".into();
+            assert!(verify_proof(&document, &proof, &public_key).is_err());
+        }
+    }
+}
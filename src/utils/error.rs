@@ -1,15 +1,27 @@
-use std::fmt;
+use thiserror::Error;
 
-#[derive(Debug)]
+#[derive(Error, Debug)]
 pub enum UtilsError {
+    #[error("JSON serialization error: {0}")]
     JsonSerializationError(String),
+    #[error("Signature error: {0}")]
     SignatureError(String),
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    #[error("Invalid JWT: {0}")]
+    InvalidJwt(String),
+    #[error("Invalid disclosure: {0}")]
+    InvalidDisclosure(String),
+    #[error("Unsupported _sd_alg: {0}")]
+    UnsupportedSdAlg(String),
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Algorithm not allowed: {0}")]
+    AlgorithmNotAllowed(String),
+    #[error("Invalid integrity metadata: {0}")]
+    InvalidIntegrity(String),
+    #[error("Key store error: {0}")]
+    KeyStoreError(String),
+    #[error("JSON-LD error: {0}")]
+    JsonLdError(String),
 }
-
-impl fmt::Display for UtilsError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-impl std::error::Error for UtilsError {}
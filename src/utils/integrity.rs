@@ -0,0 +1,26 @@
+use crate::utils::error::UtilsError;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+// Subresource Integrity（"sha256-<base64>"）形式でドキュメントのダイジェストを表す
+pub fn integrity(document: &[u8]) -> String {
+    format!("sha256-{}", STANDARD.encode(Sha256::digest(document)))
+}
+
+// 空白区切りで複数のダイジェストが指定された場合は、いずれかと一致すればよい
+pub fn verify_integrity(document: &[u8], expected: &str) -> Result<bool, UtilsError> {
+    let mut matched = false;
+    for metadata in expected.split_whitespace() {
+        let (alg, digest) = metadata
+            .split_once('-')
+            .ok_or_else(|| UtilsError::InvalidIntegrity(metadata.to_string()))?;
+        let actual = match alg {
+            "sha256" => Sha256::digest(document).to_vec(),
+            "sha384" => Sha384::digest(document).to_vec(),
+            "sha512" => Sha512::digest(document).to_vec(),
+            _ => return Err(UtilsError::InvalidIntegrity(metadata.to_string())),
+        };
+        matched |= STANDARD.encode(actual) == digest;
+    }
+    Ok(matched)
+}
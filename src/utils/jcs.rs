@@ -0,0 +1,260 @@
+use crate::utils::error::UtilsError;
+use serde_json::{Number, Value};
+use std::fmt::Write;
+
+// RFC 8785 の JSON Canonicalization Scheme（JCS）で JSON を正規化する
+// 署名する JSON はすべてこの形式のバイト列にする
+pub fn canonicalize(value: &Value) -> Result<String, UtilsError> {
+    let mut output = String::new();
+    write_value(value, &mut output)?;
+    Ok(output)
+}
+
+fn write_value(value: &Value, output: &mut String) -> Result<(), UtilsError> {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => output.push_str(&format_number(number)?),
+        Value::String(string) => write_string(string, output),
+        Value::Array(values) => {
+            output.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_value(value, output)?;
+            }
+            output.push(']');
+        }
+        Value::Object(map) => {
+            // メンバーはキーの UTF-16 のコード単位の順に並べる
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            output.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_string(key, output);
+                output.push(':');
+                write_value(value, output)?;
+            }
+            output.push('}');
+        }
+    }
+    Ok(())
+}
+
+// 短い形式のある制御文字と、それ以外の制御文字（\u00XX）、" と \ だけをエスケープする
+fn write_string(string: &str, output: &mut String) {
+    output.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{08}' => output.push_str("\\b"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\u{0c}' => output.push_str("\\f"),
+            '\r' => output.push_str("\\r"),
+            c if c < ' ' => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+// 数値は IEEE 754 の倍精度として、ECMAScript の Number.prototype.toString と同じ形式にする
+fn format_number(number: &Number) -> Result<String, UtilsError> {
+    let value = number
+        .as_f64()
+        .ok_or_else(|| UtilsError::JsonSerializationError(format!("Invalid number: {}", number)))?;
+    format_f64(value)
+}
+
+fn format_f64(value: f64) -> Result<String, UtilsError> {
+    if !value.is_finite() {
+        return Err(UtilsError::JsonSerializationError(format!(
+            "Number is not finite: {}",
+            value
+        )));
+    }
+    if value == 0.0 {
+        // -0 も 0 にする
+        return Ok("0".to_string());
+    }
+
+    // {:e} は値を一意に表す最短の桁（d.ddde±x）を出力する
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .ok_or_else(|| UtilsError::JsonSerializationError(scientific.clone()))?;
+    let exponent: i32 = exponent
+        .parse()
+        .map_err(|_| UtilsError::JsonSerializationError(scientific.clone()))?;
+    let mut digits = mantissa.replace('.', "");
+    // 整数の値は二つの最短の桁のちょうど中間にならないので、正確な表現は整数でない値だけで調べる
+    if value.fract() != 0.0 {
+        digits = round_half_even(value.abs(), digits, exponent);
+    }
+    // ECMAScript の仕様の k（桁数）と n（小数点の位置）
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let mut output = String::new();
+    if value < 0.0 {
+        output.push('-');
+    }
+    if k <= n && n <= 21 {
+        output.push_str(&digits);
+        output.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        output.push_str(&digits[..n as usize]);
+        output.push('.');
+        output.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        output.push_str("0.");
+        output.push_str(&"0".repeat(-n as usize));
+        output.push_str(&digits);
+    } else {
+        output.push_str(&digits[..1]);
+        if k > 1 {
+            output.push('.');
+            output.push_str(&digits[1..]);
+        }
+        let _ = write!(
+            output,
+            "e{}{}",
+            if n - 1 < 0 { '-' } else { '+' },
+            (n - 1).abs()
+        );
+    }
+    Ok(output)
+}
+
+// 値がちょうど二つの最短の桁の中間にある場合、ECMAScript は最後の桁が偶数のほうを選ぶが、
+// {:e} は切り上げたほうを出力するので、切り捨てたほうが偶数ならそれに置き換える
+fn round_half_even(value: f64, digits: String, exponent: i32) -> String {
+    // 倍精度の値の 10 進での正確な表現は 767 桁以内に収まる
+    let exact = format!("{:.800e}", value);
+    let Some((mantissa, exact_exponent)) = exact.split_once('e') else {
+        return digits;
+    };
+    if exact_exponent.parse() != Ok(exponent) {
+        return digits;
+    }
+    let exact_digits = mantissa.replace('.', "");
+    let (truncated, rest) = exact_digits.split_at(digits.len());
+    let is_tie = rest.starts_with('5') && rest[1..].bytes().all(|b| b == b'0');
+    let is_even = truncated.bytes().last().is_some_and(|b| b % 2 == 0);
+    let round_trips = format!("{}e{}", truncated, exponent + 1 - digits.len() as i32)
+        .parse::<f64>()
+        .is_ok_and(|parsed| parsed == value);
+    if is_tie && is_even && round_trips {
+        truncated.to_string()
+    } else {
+        digits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jcs_test_vectors() {
+        // RFC 8785 3.2.2 と 3.2.3 の例
+        let input: Value = serde_json::from_str(
+            r#"{
+                "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+                "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+                "literals": [null, true, false]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize(&input).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        let input: Value = serde_json::from_str(
+            r#"{
+                "\u20ac": "Euro Sign",
+                "\r": "Carriage Return",
+                "\ufb33": "Hebrew Letter Dalet With Dagesh",
+                "1": "One",
+                "\ud83d\ude00": "Emoji: Grinning Face",
+                "\u0080": "Control",
+                "\u00f6": "Latin Small Letter O With Diaeresis"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize(&input).unwrap(),
+            "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+             \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+             \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+        );
+
+        // RFC 8785 Appendix B の数値（IEEE 754 のビット列と期待する文字列）
+        for (bits, expected) in [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ] {
+            assert_eq!(format_f64(f64::from_bits(bits)).unwrap(), expected);
+        }
+        for bits in [0x7fffffffffffffff, 0x7ff0000000000000] {
+            assert!(format_f64(f64::from_bits(bits)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_jcs_round_half_even() {
+        // RFC 8785 Appendix B の 1424953923781206.25 は 1424953923781206.2 と ...206.3 の中間にある
+        let value = f64::from_bits(0x43143ff3c1cb0959);
+        assert_eq!(value.fract(), 0.25);
+        assert_eq!(format!("{:e}", value), "1.4249539237812063e15");
+        assert_eq!(
+            round_half_even(value, "14249539237812063".to_string(), 15),
+            "14249539237812062"
+        );
+        assert_eq!(format_f64(value).unwrap(), "1424953923781206.2");
+        assert_eq!(format_f64(-value).unwrap(), "-1424953923781206.2");
+
+        // 中間にない値と整数の値はそのまま
+        let value = f64::from_bits(0x41b3de4355555554);
+        assert_eq!(
+            round_half_even(value, "33333333333333325".to_string(), 8),
+            "33333333333333325"
+        );
+        assert_eq!(format_f64(value).unwrap(), "333333333.33333325");
+        assert_eq!(
+            format_f64(f64::from_bits(0x4430000000000000)).unwrap(),
+            "295147905179352830000"
+        );
+    }
+}
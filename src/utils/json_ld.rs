@@ -0,0 +1,2648 @@
+use crate::utils::error::UtilsError;
+use crate::utils::jcs;
+use oxrdf::{BlankNode, Dataset, GraphName, Literal, NamedNode, Quad, Subject, Term};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+// リモートのコンテキストはネットワークから取得せず、同梱したものだけを読み込む
+const CONTEXTS: [(&str, &str); 7] = [
+    (
+        "https://www.w3.org/2018/credentials/v1",
+        include_str!("../../contexts/credentials-v1.jsonld"),
+    ),
+    (
+        "https://www.w3.org/2018/credentials/examples/v1",
+        include_str!("../../contexts/credentials-examples-v1.jsonld"),
+    ),
+    (
+        "https://www.w3.org/ns/odrl.jsonld",
+        include_str!("../../contexts/odrl.jsonld"),
+    ),
+    (
+        "https://www.w3.org/ns/credentials/v2",
+        include_str!("../../contexts/credentials-v2.jsonld"),
+    ),
+    (
+        "https://www.w3.org/ns/credentials/examples/v2",
+        include_str!("../../contexts/credentials-examples-v2.jsonld"),
+    ),
+    (
+        "https://w3id.org/security/data-integrity/v2",
+        include_str!("../../contexts/data-integrity-v2.jsonld"),
+    ),
+    // Issuer が発行するクレデンシャルタイプの用語
+    (
+        "http://localhost:8080/issuer/contexts/v1",
+        include_str!("../../contexts/vc-system-v1.jsonld"),
+    ),
+];
+
+const MAX_REMOTE_CONTEXTS: usize = 32;
+
+const KEYWORDS: [&str; 23] = [
+    "@base",
+    "@container",
+    "@context",
+    "@direction",
+    "@graph",
+    "@id",
+    "@import",
+    "@included",
+    "@index",
+    "@json",
+    "@language",
+    "@list",
+    "@nest",
+    "@none",
+    "@prefix",
+    "@propagate",
+    "@protected",
+    "@reverse",
+    "@set",
+    "@type",
+    "@value",
+    "@version",
+    "@vocab",
+];
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+const RDF_JSON: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON";
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+
+// RDFC-1.0 で正規化した N-Quads（eddsa-rdfc-2022 の署名対象）
+pub fn canonicalize(document: &Value) -> Result<String, UtilsError> {
+    rdf_canon::canonicalize(&to_rdf(document)?).map_err(|e| error(e.to_string()))
+}
+
+// JSON-LD 1.1 の Deserialize JSON-LD to RDF
+// 署名されない値が残らないよう、展開で落ちる項目や相対 IRI は無視せずにエラーにする
+pub fn to_rdf(document: &Value) -> Result<Dataset, UtilsError> {
+    let mut writer = RdfWriter::default();
+    for node in expand(document)? {
+        if let Value::Object(node) = node {
+            writer.node(&node, &GraphName::DefaultGraph)?;
+        }
+    }
+    Ok(writer.dataset)
+}
+
+// JSON-LD 1.1 の Expansion（結果は常に配列）
+pub fn expand(document: &Value) -> Result<Vec<Value>, UtilsError> {
+    let expanded = expand_element(&Context::default(), None, document, false)?;
+    let expanded = match expanded {
+        Value::Object(mut map) if map.len() == 1 && map.contains_key("@graph") => {
+            map.remove("@graph").unwrap_or(Value::Null)
+        }
+        expanded => expanded,
+    };
+    Ok(match expanded {
+        Value::Null => Vec::new(),
+        Value::Array(values) => values,
+        value => vec![value],
+    })
+}
+
+// JSON-LD 1.1 の Compaction（context は @context の値か、@context を持つ文書）
+#[allow(dead_code)]
+pub fn compact(document: &Value, context: &Value) -> Result<Value, UtilsError> {
+    let local = match context {
+        Value::Object(map) if map.contains_key("@context") => map["@context"].clone(),
+        context => context.clone(),
+    };
+    let active = process_context(&Context::default(), &local, &[], false, true, true)?;
+    let compacted = compact_element(&active, None, &Value::Array(expand(document)?))?;
+    let mut result = match compacted {
+        Value::Object(map) => map,
+        Value::Array(items) if items.is_empty() => Map::new(),
+        items => {
+            let mut map = Map::new();
+            map.insert(compact_iri(&active, "@graph", None, true, false)?, items);
+            map
+        }
+    };
+    let has_context = match &local {
+        Value::Null => false,
+        Value::Array(contexts) => !contexts.is_empty(),
+        Value::Object(context) => !context.is_empty(),
+        _ => true,
+    };
+    if has_context {
+        result.insert("@context".to_string(), local);
+    }
+    Ok(Value::Object(result))
+}
+
+// 同梱したコンテキストの文書（静的なドキュメントローダー）
+pub fn context_document(url: &str) -> Option<&'static str> {
+    CONTEXTS
+        .iter()
+        .find(|(context_url, _)| *context_url == url)
+        .map(|(_, document)| *document)
+}
+
+fn load_context(url: &str) -> Result<Value, UtilsError> {
+    let document =
+        context_document(url).ok_or_else(|| error(format!("Unknown context: {}", url)))?;
+    serde_json::from_str(document).map_err(|e| error(format!("Invalid context {}: {}", url, e)))
+}
+
+#[derive(Clone, Debug, Default)]
+struct Context {
+    terms: HashMap<String, TermDefinition>,
+    vocab: Option<String>,
+    language: Option<String>,
+    direction: Option<String>,
+    // 型スコープのコンテキストを適用する前のコンテキスト（@propagate: false）
+    previous: Option<Box<Context>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct TermDefinition {
+    iri: Option<String>,
+    prefix: bool,
+    protected: bool,
+    reverse: bool,
+    type_mapping: Option<String>,
+    container: Vec<String>,
+    // Some(None) は null が明示されたもの
+    language: Option<Option<String>>,
+    direction: Option<Option<String>>,
+    index: Option<String>,
+    nest: Option<String>,
+    context: Option<Value>,
+}
+
+impl TermDefinition {
+    fn has_container(&self, container: &str) -> bool {
+        self.container.iter().any(|c| c == container)
+    }
+}
+
+impl Context {
+    fn term(&self, term: &str) -> Option<&TermDefinition> {
+        self.terms.get(term)
+    }
+
+    fn has_container(&self, term: Option<&str>, container: &str) -> bool {
+        term.and_then(|term| self.term(term))
+            .is_some_and(|definition| definition.has_container(container))
+    }
+
+    fn has_protected_terms(&self) -> bool {
+        self.terms.values().any(|definition| definition.protected)
+    }
+}
+
+// Context Processing
+fn process_context(
+    active: &Context,
+    local: &Value,
+    remote_contexts: &[String],
+    override_protected: bool,
+    propagate: bool,
+    validate_scoped: bool,
+) -> Result<Context, UtilsError> {
+    let mut result = active.clone();
+    let propagate = match local.get("@propagate") {
+        None => propagate,
+        Some(Value::Bool(propagate)) => *propagate,
+        Some(value) => return Err(error(format!("Invalid @propagate value: {}", value))),
+    };
+    if !propagate && result.previous.is_none() {
+        result.previous = Some(Box::new(active.clone()));
+    }
+
+    let contexts = match local {
+        Value::Array(contexts) => contexts.iter().collect(),
+        context => vec![context],
+    };
+    for context in contexts {
+        match context {
+            Value::Null => {
+                if !override_protected && result.has_protected_terms() {
+                    return Err(error("Invalid context nullification".to_string()));
+                }
+                let previous = result;
+                result = Context::default();
+                if !propagate {
+                    result.previous = Some(Box::new(previous));
+                }
+            }
+            Value::String(url) => {
+                if !validate_scoped && remote_contexts.contains(url) {
+                    continue;
+                }
+                if remote_contexts.len() >= MAX_REMOTE_CONTEXTS {
+                    return Err(error("Context overflow".to_string()));
+                }
+                let mut remote_contexts = remote_contexts.to_vec();
+                remote_contexts.push(url.clone());
+                let document = load_context(url)?;
+                let loaded = document
+                    .get("@context")
+                    .ok_or_else(|| error(format!("Invalid remote context: {}", url)))?;
+                result = process_context(
+                    &result,
+                    loaded,
+                    &remote_contexts,
+                    override_protected,
+                    true,
+                    validate_scoped,
+                )?;
+            }
+            Value::Object(definition) => {
+                process_context_definition(
+                    &mut result,
+                    definition,
+                    remote_contexts,
+                    override_protected,
+                )?;
+            }
+            context => return Err(error(format!("Invalid local context: {}", context))),
+        }
+    }
+    Ok(result)
+}
+
+fn process_context_definition(
+    result: &mut Context,
+    definition: &Map<String, Value>,
+    remote_contexts: &[String],
+    override_protected: bool,
+) -> Result<(), UtilsError> {
+    if let Some(version) = definition.get("@version") {
+        if version.as_f64() != Some(1.1) {
+            return Err(error(format!("Invalid @version value: {}", version)));
+        }
+    }
+    if definition.contains_key("@import") {
+        return Err(error("@import is not supported".to_string()));
+    }
+    if let Some(base) = definition.get("@base") {
+        // 相対 IRI の解決には対応しないため、@base は絶対 IRI か null だけを受け付ける
+        if !base.is_null() && !base.as_str().is_some_and(is_absolute_iri) {
+            return Err(error(format!("Invalid base IRI: {}", base)));
+        }
+    }
+    match definition.get("@vocab") {
+        None => {}
+        Some(Value::Null) => result.vocab = None,
+        Some(Value::String(vocab)) => {
+            let vocab = expand_iri(result, vocab, true, true)
+                .filter(|vocab| is_absolute_iri(vocab) || is_blank_node(vocab))
+                .ok_or_else(|| error(format!("Invalid vocab mapping: {}", vocab)))?;
+            result.vocab = Some(vocab);
+        }
+        Some(vocab) => return Err(error(format!("Invalid vocab mapping: {}", vocab))),
+    }
+    match definition.get("@language") {
+        None => {}
+        Some(Value::Null) => result.language = None,
+        Some(Value::String(language)) => result.language = Some(language.to_lowercase()),
+        Some(language) => return Err(error(format!("Invalid default language: {}", language))),
+    }
+    if let Some(direction) = definition.get("@direction") {
+        result.direction = base_direction(direction)?;
+    }
+
+    let mut creation = TermCreation {
+        local: definition,
+        defined: HashMap::new(),
+        override_protected,
+        remote_contexts,
+    };
+    for term in definition.keys() {
+        if matches!(
+            term.as_str(),
+            "@base"
+                | "@direction"
+                | "@import"
+                | "@language"
+                | "@propagate"
+                | "@protected"
+                | "@version"
+                | "@vocab"
+        ) {
+            continue;
+        }
+        create_term_definition(result, &mut creation, term)?;
+    }
+    Ok(())
+}
+
+struct TermCreation<'a> {
+    local: &'a Map<String, Value>,
+    defined: HashMap<String, bool>,
+    override_protected: bool,
+    remote_contexts: &'a [String],
+}
+
+impl TermCreation<'_> {
+    fn needs_definition(&self, term: &str) -> bool {
+        self.local.contains_key(term) && self.defined.get(term) != Some(&true)
+    }
+}
+
+// Create Term Definition
+fn create_term_definition(
+    active: &mut Context,
+    creation: &mut TermCreation,
+    term: &str,
+) -> Result<(), UtilsError> {
+    match creation.defined.get(term) {
+        Some(true) => return Ok(()),
+        Some(false) => return Err(error(format!("Cyclic IRI mapping: {}", term))),
+        None => {}
+    }
+    if term.is_empty() {
+        return Err(error("Invalid term definition: empty term".to_string()));
+    }
+    creation.defined.insert(term.to_string(), false);
+
+    let value = creation.local.get(term).cloned().unwrap_or(Value::Null);
+    if term == "@type" {
+        let valid = value.as_object().is_some_and(|value| {
+            !value.is_empty()
+                && value.iter().all(|(key, entry)| match key.as_str() {
+                    "@container" => entry == "@set",
+                    "@protected" => entry.is_boolean(),
+                    _ => false,
+                })
+        });
+        if !valid {
+            return Err(error(format!("Keyword redefinition: {}", term)));
+        }
+    } else if is_keyword(term) {
+        return Err(error(format!("Keyword redefinition: {}", term)));
+    } else if has_keyword_form(term) {
+        creation.defined.insert(term.to_string(), true);
+        return Ok(());
+    }
+
+    let previous = active.terms.remove(term);
+    let (value, simple_term) = match value {
+        Value::Null => (
+            [("@id".to_string(), Value::Null)].into_iter().collect(),
+            false,
+        ),
+        Value::String(id) => (
+            [("@id".to_string(), Value::String(id))]
+                .into_iter()
+                .collect(),
+            true,
+        ),
+        Value::Object(value) => (value, false),
+        value => return Err(error(format!("Invalid term definition: {}", value))),
+    };
+
+    let protected = match value.get("@protected") {
+        None => creation.local.get("@protected") == Some(&Value::Bool(true)),
+        Some(Value::Bool(protected)) => *protected,
+        Some(protected) => return Err(error(format!("Invalid @protected value: {}", protected))),
+    };
+    let mut definition = TermDefinition {
+        protected,
+        ..Default::default()
+    };
+
+    if let Some(type_mapping) = value.get("@type") {
+        let type_mapping = type_mapping
+            .as_str()
+            .ok_or_else(|| error(format!("Invalid type mapping: {}", type_mapping)))?;
+        let type_mapping = expand_iri_defining(active, creation, type_mapping, true)?
+            .filter(|type_mapping| {
+                matches!(type_mapping.as_str(), "@id" | "@json" | "@none" | "@vocab")
+                    || is_absolute_iri(type_mapping)
+            })
+            .ok_or_else(|| error(format!("Invalid type mapping: {}", type_mapping)))?;
+        definition.type_mapping = Some(type_mapping);
+    }
+
+    if let Some(reverse) = value.get("@reverse") {
+        if value.contains_key("@id") || value.contains_key("@nest") {
+            return Err(error(format!("Invalid reverse property: {}", term)));
+        }
+        let reverse = reverse
+            .as_str()
+            .ok_or_else(|| error(format!("Invalid IRI mapping: {}", reverse)))?;
+        if has_keyword_form(reverse) {
+            creation.defined.insert(term.to_string(), true);
+            return Ok(());
+        }
+        let iri = expand_iri_defining(active, creation, reverse, true)?
+            .filter(|iri| is_absolute_iri(iri) || is_blank_node(iri))
+            .ok_or_else(|| error(format!("Invalid IRI mapping: {}", reverse)))?;
+        definition.iri = Some(iri);
+        definition.reverse = true;
+        definition.container = container_mapping(value.get("@container"))?;
+        if !definition
+            .container
+            .iter()
+            .all(|container| container == "@set" || container == "@index")
+        {
+            return Err(error(format!("Invalid reverse property: {}", term)));
+        }
+        active.terms.insert(term.to_string(), definition);
+        creation.defined.insert(term.to_string(), true);
+        return Ok(());
+    }
+
+    let compact_prefix = split_prefix(term);
+    match value.get("@id") {
+        Some(id) if id != term => match id {
+            Value::Null => definition.iri = None,
+            Value::String(id) => {
+                if !is_keyword(id) && has_keyword_form(id) {
+                    creation.defined.insert(term.to_string(), true);
+                    return Ok(());
+                }
+                let iri = expand_iri_defining(active, creation, id, true)?
+                    .filter(|iri| is_keyword(iri) || is_absolute_iri(iri) || is_blank_node(iri))
+                    .ok_or_else(|| error(format!("Invalid IRI mapping: {}", id)))?;
+                if iri == "@context" {
+                    return Err(error(format!("Invalid keyword alias: {}", term)));
+                }
+                let inner_colon = term
+                    .get(1..term.len().saturating_sub(1))
+                    .is_some_and(|inner| inner.contains(':'));
+                if inner_colon || term.contains('/') {
+                    creation.defined.insert(term.to_string(), true);
+                    if expand_iri_defining(active, creation, term, true)?.as_deref()
+                        != Some(iri.as_str())
+                    {
+                        return Err(error(format!("Invalid IRI mapping: {}", term)));
+                    }
+                }
+                if !term.contains(':')
+                    && !term.contains('/')
+                    && simple_term
+                    && (iri.ends_with([':', '/', '?', '#', '[', ']', '@']) || is_blank_node(&iri))
+                {
+                    definition.prefix = true;
+                }
+                definition.iri = Some(iri);
+            }
+            id => return Err(error(format!("Invalid IRI mapping: {}", id))),
+        },
+        _ => {
+            definition.iri = Some(if let Some((prefix, suffix)) = compact_prefix {
+                let is_compact_iri = prefix != "_" && !suffix.starts_with("//");
+                if is_compact_iri && creation.needs_definition(prefix) {
+                    create_term_definition(active, creation, prefix)?;
+                }
+                match active.term(prefix).and_then(|prefix| prefix.iri.as_ref()) {
+                    Some(prefix_iri) => format!("{}{}", prefix_iri, suffix),
+                    None => term.to_string(),
+                }
+            } else if term.contains('/') {
+                expand_iri(active, term, false, true)
+                    .filter(|iri| is_absolute_iri(iri))
+                    .ok_or_else(|| error(format!("Invalid IRI mapping: {}", term)))?
+            } else if term == "@type" {
+                "@type".to_string()
+            } else {
+                let vocab = active
+                    .vocab
+                    .as_ref()
+                    .ok_or_else(|| error(format!("Invalid IRI mapping: {}", term)))?;
+                format!("{}{}", vocab, term)
+            });
+        }
+    }
+
+    if value.contains_key("@container") {
+        definition.container = container_mapping(value.get("@container"))?;
+        if definition.has_container("@type") {
+            let type_mapping = definition
+                .type_mapping
+                .get_or_insert_with(|| "@id".to_string());
+            if type_mapping != "@id" && type_mapping != "@vocab" {
+                return Err(error(format!("Invalid type mapping: {}", type_mapping)));
+            }
+        }
+    }
+
+    if let Some(index) = value.get("@index") {
+        let index = index
+            .as_str()
+            .filter(|_| definition.has_container("@index"))
+            .ok_or_else(|| error(format!("Invalid term definition: {}", term)))?;
+        if !expand_iri(active, index, false, true).is_some_and(|index| is_absolute_iri(&index)) {
+            return Err(error(format!("Invalid term definition: {}", term)));
+        }
+        definition.index = Some(index.to_string());
+    }
+
+    if let Some(context) = value.get("@context") {
+        // 適用するのは展開時だが、ここで不正なコンテキストでないことを確認しておく
+        process_context(active, context, creation.remote_contexts, true, true, false)
+            .map_err(|e| error(format!("Invalid scoped context for {}: {}", term, e)))?;
+        definition.context = Some(context.clone());
+    }
+
+    if !value.contains_key("@type") {
+        if let Some(language) = value.get("@language") {
+            definition.language = Some(match language {
+                Value::Null => None,
+                Value::String(language) => Some(language.to_lowercase()),
+                language => return Err(error(format!("Invalid language mapping: {}", language))),
+            });
+        }
+        if let Some(direction) = value.get("@direction") {
+            definition.direction = Some(base_direction(direction)?);
+        }
+    }
+
+    if let Some(nest) = value.get("@nest") {
+        let nest = nest
+            .as_str()
+            .filter(|nest| !is_keyword(nest) || *nest == "@nest")
+            .ok_or_else(|| error(format!("Invalid @nest value: {}", nest)))?;
+        definition.nest = Some(nest.to_string());
+    }
+
+    if let Some(prefix) = value.get("@prefix") {
+        if term.contains(':') || term.contains('/') {
+            return Err(error(format!("Invalid term definition: {}", term)));
+        }
+        definition.prefix = prefix
+            .as_bool()
+            .ok_or_else(|| error(format!("Invalid @prefix value: {}", prefix)))?;
+    }
+
+    if let Some(key) = value.keys().find(|key| {
+        !matches!(
+            key.as_str(),
+            "@id"
+                | "@reverse"
+                | "@container"
+                | "@context"
+                | "@direction"
+                | "@index"
+                | "@language"
+                | "@nest"
+                | "@prefix"
+                | "@protected"
+                | "@type"
+        )
+    }) {
+        return Err(error(format!(
+            "Invalid term definition: {} has {}",
+            term, key
+        )));
+    }
+
+    // 保護された用語は、同じ定義でなければ上書きできない
+    if let Some(previous) = previous.filter(|previous| previous.protected) {
+        if !creation.override_protected {
+            let redefined = TermDefinition {
+                protected: true,
+                ..definition.clone()
+            };
+            if redefined != previous {
+                return Err(error(format!("Protected term redefinition: {}", term)));
+            }
+            definition = previous;
+        }
+    }
+
+    active.terms.insert(term.to_string(), definition);
+    creation.defined.insert(term.to_string(), true);
+    Ok(())
+}
+
+fn container_mapping(container: Option<&Value>) -> Result<Vec<String>, UtilsError> {
+    let containers = match container {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::String(container)) => vec![container.clone()],
+        Some(Value::Array(containers)) => containers
+            .iter()
+            .map(|container| container.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| error(format!("Invalid container mapping: {:?}", containers)))?,
+        Some(container) => return Err(error(format!("Invalid container mapping: {}", container))),
+    };
+    if !containers.iter().all(|container| {
+        matches!(
+            container.as_str(),
+            "@graph" | "@id" | "@index" | "@language" | "@list" | "@set" | "@type"
+        )
+    }) {
+        return Err(error(format!(
+            "Invalid container mapping: {:?}",
+            containers
+        )));
+    }
+    Ok(containers)
+}
+
+fn base_direction(direction: &Value) -> Result<Option<String>, UtilsError> {
+    match direction {
+        Value::Null => Ok(None),
+        Value::String(direction) if direction == "ltr" || direction == "rtl" => {
+            Ok(Some(direction.clone()))
+        }
+        direction => Err(error(format!("Invalid base direction: {}", direction))),
+    }
+}
+
+// コンテキストの定義中の IRI Expansion（参照する用語を先に定義する）
+fn expand_iri_defining(
+    active: &mut Context,
+    creation: &mut TermCreation,
+    value: &str,
+    vocab: bool,
+) -> Result<Option<String>, UtilsError> {
+    if is_keyword(value) {
+        return Ok(Some(value.to_string()));
+    }
+    if has_keyword_form(value) {
+        return Ok(None);
+    }
+    if creation.needs_definition(value) {
+        create_term_definition(active, creation, value)?;
+    }
+    if let Some(definition) = active.term(value) {
+        if definition.iri.as_deref().is_some_and(is_keyword) || vocab {
+            return Ok(definition.iri.clone());
+        }
+    }
+    if let Some((prefix, _)) = split_prefix(value) {
+        if creation.needs_definition(prefix) {
+            create_term_definition(active, creation, prefix)?;
+        }
+    }
+    Ok(expand_iri(active, value, false, vocab))
+}
+
+// IRI Expansion（文書の基底 IRI は扱わないため、相対 IRI はそのまま返す）
+fn expand_iri(
+    active: &Context,
+    value: &str,
+    _document_relative: bool,
+    vocab: bool,
+) -> Option<String> {
+    if is_keyword(value) {
+        return Some(value.to_string());
+    }
+    if has_keyword_form(value) {
+        return None;
+    }
+    if let Some(definition) = active.term(value) {
+        if definition.iri.as_deref().is_some_and(is_keyword) || vocab {
+            return definition.iri.clone();
+        }
+    }
+    if let Some((prefix, suffix)) = split_prefix(value) {
+        if prefix == "_" || suffix.starts_with("//") {
+            return Some(value.to_string());
+        }
+        if let Some(prefix_iri) = active
+            .term(prefix)
+            .filter(|definition| definition.prefix)
+            .and_then(|definition| definition.iri.as_ref())
+        {
+            return Some(format!("{}{}", prefix_iri, suffix));
+        }
+        if is_absolute_iri(value) {
+            return Some(value.to_string());
+        }
+    }
+    if vocab {
+        if let Some(vocab) = &active.vocab {
+            return Some(format!("{}{}", vocab, value));
+        }
+    }
+    Some(value.to_string())
+}
+
+// Expansion Algorithm
+fn expand_element(
+    active: &Context,
+    active_property: Option<&str>,
+    element: &Value,
+    from_map: bool,
+) -> Result<Value, UtilsError> {
+    let property_scoped = active_property
+        .and_then(|property| active.term(property))
+        .and_then(|definition| definition.context.clone());
+
+    match element {
+        Value::Null => Ok(Value::Null),
+        Value::Array(items) => {
+            let mut result = Vec::new();
+            for item in items {
+                let mut expanded = expand_element(active, active_property, item, from_map)?;
+                if active.has_container(active_property, "@list") && expanded.is_array() {
+                    expanded = single("@list", expanded);
+                }
+                match expanded {
+                    Value::Array(expanded) => result.extend(expanded),
+                    Value::Null => {}
+                    expanded => result.push(expanded),
+                }
+            }
+            Ok(Value::Array(result))
+        }
+        Value::Object(element) => {
+            expand_object(active, active_property, element, property_scoped, from_map)
+        }
+        scalar => {
+            if active_property.is_none_or(|property| property == "@graph") {
+                return Ok(Value::Null);
+            }
+            let active = match &property_scoped {
+                Some(context) => &process_context(active, context, &[], true, true, true)?,
+                None => active,
+            };
+            expand_value(active, active_property, scalar)
+        }
+    }
+}
+
+fn expand_object(
+    active: &Context,
+    active_property: Option<&str>,
+    element: &Map<String, Value>,
+    property_scoped: Option<Value>,
+    from_map: bool,
+) -> Result<Value, UtilsError> {
+    let mut context = active.clone();
+
+    // 型スコープのコンテキストは、ノードの中に入ったところで元に戻す
+    if let Some(previous) = &context.previous {
+        let expands_to = |key: &str, keyword: &str| {
+            expand_iri(active, key, false, true).as_deref() == Some(keyword)
+        };
+        let has_value = element.keys().any(|key| expands_to(key, "@value"));
+        let only_id = element.len() == 1 && element.keys().all(|key| expands_to(key, "@id"));
+        if !from_map && !has_value && !only_id {
+            context = (**previous).clone();
+        }
+    }
+    if let Some(property_scoped) = &property_scoped {
+        context = process_context(&context, property_scoped, &[], true, true, true)?;
+    }
+    if let Some(local) = element.get("@context") {
+        context = process_context(&context, local, &[], false, true, true)?;
+    }
+
+    let type_scoped = context.clone();
+    let mut keys: Vec<&String> = element.keys().collect();
+    keys.sort();
+    let type_keys: Vec<&String> = keys
+        .iter()
+        .copied()
+        .filter(|key| expand_iri(&type_scoped, key, false, true).as_deref() == Some("@type"))
+        .collect();
+    for key in &type_keys {
+        let mut terms: Vec<&str> = match &element[key.as_str()] {
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            value => value.as_str().into_iter().collect(),
+        };
+        terms.sort();
+        for term in terms {
+            if let Some(local) = type_scoped
+                .term(term)
+                .and_then(|definition| definition.context.as_ref())
+            {
+                context = process_context(&context, local, &[], false, false, true)?;
+            }
+        }
+    }
+    let input_type = type_keys
+        .first()
+        .and_then(|key| match &element[key.as_str()] {
+            Value::Array(values) => values.last().and_then(Value::as_str),
+            value => value.as_str(),
+        })
+        .and_then(|value| expand_iri(&type_scoped, value, false, true));
+
+    let mut result = Map::new();
+    let state = ExpansionState {
+        context: &context,
+        type_scoped: &type_scoped,
+        input_type: input_type.as_deref(),
+        active_property,
+    };
+    expand_entries(&state, element, &mut result)?;
+
+    finish_object(result, active_property)
+}
+
+struct ExpansionState<'a> {
+    context: &'a Context,
+    type_scoped: &'a Context,
+    input_type: Option<&'a str>,
+    active_property: Option<&'a str>,
+}
+
+fn expand_entries(
+    state: &ExpansionState,
+    element: &Map<String, Value>,
+    result: &mut Map<String, Value>,
+) -> Result<(), UtilsError> {
+    let context = state.context;
+    let mut keys: Vec<&String> = element.keys().collect();
+    keys.sort();
+    let mut nests = Vec::new();
+
+    for key in keys {
+        let value = &element[key.as_str()];
+        if key == "@context" {
+            continue;
+        }
+        let expanded_property = expand_iri(context, key, false, true)
+            .filter(|property| is_keyword(property) || property.contains(':'))
+            .ok_or_else(|| error(format!("Undefined term: {}", key)))?;
+
+        if is_keyword(&expanded_property) {
+            if state.active_property == Some("@reverse") {
+                return Err(error(format!("Invalid reverse property map: {}", key)));
+            }
+            if result.contains_key(&expanded_property)
+                && expanded_property != "@included"
+                && expanded_property != "@type"
+            {
+                return Err(error(format!("Colliding keywords: {}", expanded_property)));
+            }
+            let expanded_value = match expanded_property.as_str() {
+                "@id" => {
+                    let id = value
+                        .as_str()
+                        .ok_or_else(|| error(format!("Invalid @id value: {}", value)))?;
+                    Value::String(node_iri(context, id, false)?)
+                }
+                "@type" => {
+                    let expand_type = |value: &Value| {
+                        value
+                            .as_str()
+                            .ok_or_else(|| error(format!("Invalid type value: {}", value)))
+                            .and_then(|value| {
+                                // 値オブジェクトの @type は @json も取れる
+                                match expand_iri(state.type_scoped, value, false, true) {
+                                    Some(json) if json == "@json" => Ok(json),
+                                    _ => node_iri(state.type_scoped, value, true),
+                                }
+                            })
+                            .map(Value::String)
+                    };
+                    let expanded = match value {
+                        Value::Array(values) => {
+                            Value::Array(values.iter().map(expand_type).collect::<Result<_, _>>()?)
+                        }
+                        value => expand_type(value)?,
+                    };
+                    match result.remove("@type") {
+                        Some(existing) => {
+                            let mut types = as_array(existing);
+                            types.extend(as_array(expanded));
+                            Value::Array(types)
+                        }
+                        None => expanded,
+                    }
+                }
+                "@graph" => Value::Array(as_array(expand_element(
+                    context,
+                    Some("@graph"),
+                    value,
+                    false,
+                )?)),
+                "@included" => {
+                    let included = as_array(expand_element(context, None, value, false)?);
+                    if !included.iter().all(is_node_object) {
+                        return Err(error("Invalid @included value".to_string()));
+                    }
+                    let mut existing = result.remove("@included").map(as_array).unwrap_or_default();
+                    existing.extend(included);
+                    Value::Array(existing)
+                }
+                "@value" => {
+                    if state.input_type == Some("@json") {
+                        value.clone()
+                    } else if value.is_null() {
+                        result.insert("@value".to_string(), Value::Null);
+                        continue;
+                    } else if value.is_object() || value.is_array() {
+                        return Err(error(format!("Invalid value object value: {}", value)));
+                    } else {
+                        value.clone()
+                    }
+                }
+                "@language" => Value::String(
+                    value
+                        .as_str()
+                        .ok_or_else(|| error(format!("Invalid language-tagged string: {}", value)))?
+                        .to_lowercase(),
+                ),
+                "@direction" => match base_direction(value)? {
+                    Some(direction) => Value::String(direction),
+                    None => continue,
+                },
+                "@index" => {
+                    if !value.is_string() {
+                        return Err(error(format!("Invalid @index value: {}", value)));
+                    }
+                    value.clone()
+                }
+                "@list" => {
+                    if state
+                        .active_property
+                        .is_none_or(|property| property == "@graph")
+                    {
+                        continue;
+                    }
+                    Value::Array(as_array(expand_element(
+                        context,
+                        state.active_property,
+                        value,
+                        false,
+                    )?))
+                }
+                "@set" => expand_element(context, state.active_property, value, false)?,
+                "@reverse" => {
+                    if !value.is_object() {
+                        return Err(error(format!("Invalid @reverse value: {}", value)));
+                    }
+                    let expanded = expand_element(context, Some("@reverse"), value, false)?;
+                    if let Value::Object(mut expanded) = expanded {
+                        if let Some(Value::Object(reversed)) = expanded.remove("@reverse") {
+                            for (property, items) in reversed {
+                                add_value(result, &property, items);
+                            }
+                        }
+                        if !expanded.is_empty() {
+                            let reverse_map = reverse_map(result);
+                            for (property, items) in expanded {
+                                for item in as_array(items) {
+                                    if item.get("@value").is_some() || item.get("@list").is_some() {
+                                        return Err(error(format!(
+                                            "Invalid reverse property value: {}",
+                                            property
+                                        )));
+                                    }
+                                    add_value(reverse_map, &property, item);
+                                }
+                            }
+                        }
+                    }
+                    continue;
+                }
+                "@nest" => {
+                    nests.push(key);
+                    continue;
+                }
+                _ => continue,
+            };
+            if !expanded_value.is_null() {
+                result.insert(expanded_property, expanded_value);
+            }
+            continue;
+        }
+
+        let definition = context.term(key);
+        let has_container = |container: &str| {
+            definition.is_some_and(|definition| definition.has_container(container))
+        };
+        let mut expanded_value = if definition
+            .is_some_and(|definition| definition.type_mapping.as_deref() == Some("@json"))
+        {
+            let mut json = Map::new();
+            json.insert("@value".to_string(), value.clone());
+            json.insert("@type".to_string(), Value::String("@json".to_string()));
+            Value::Object(json)
+        } else if has_container("@language") && value.is_object() {
+            expand_language_map(context, definition, value)?
+        } else if (has_container("@index") || has_container("@type") || has_container("@id"))
+            && value.is_object()
+        {
+            expand_index_map(context, key, definition, value)?
+        } else {
+            expand_element(context, Some(key), value, false)?
+        };
+        if expanded_value.is_null() {
+            continue;
+        }
+        if has_container("@list") && expanded_value.get("@list").is_none() {
+            expanded_value = single("@list", Value::Array(as_array(expanded_value)));
+        }
+        if has_container("@graph") && !has_container("@id") && !has_container("@index") {
+            expanded_value = Value::Array(
+                as_array(expanded_value)
+                    .into_iter()
+                    .map(|value| single("@graph", Value::Array(as_array(value))))
+                    .collect(),
+            );
+        }
+        if definition.is_some_and(|definition| definition.reverse) {
+            let reverse_map = reverse_map(result);
+            for item in as_array(expanded_value) {
+                if item.get("@value").is_some() || item.get("@list").is_some() {
+                    return Err(error(format!("Invalid reverse property value: {}", key)));
+                }
+                add_value(reverse_map, &expanded_property, item);
+            }
+        } else {
+            add_value(result, &expanded_property, expanded_value);
+        }
+    }
+
+    for key in nests {
+        for nested in as_array(element[key.as_str()].clone()) {
+            let nested = match nested {
+                Value::Object(nested)
+                    if !nested.keys().any(|key| {
+                        expand_iri(context, key, false, true).as_deref() == Some("@value")
+                    }) =>
+                {
+                    nested
+                }
+                nested => return Err(error(format!("Invalid @nest value: {}", nested))),
+            };
+            let nested_state = ExpansionState {
+                active_property: Some(key.as_str()),
+                ..*state
+            };
+            expand_entries(&nested_state, &nested, result)?;
+        }
+    }
+    Ok(())
+}
+
+fn expand_language_map(
+    context: &Context,
+    definition: Option<&TermDefinition>,
+    value: &Value,
+) -> Result<Value, UtilsError> {
+    let direction = match definition.and_then(|definition| definition.direction.clone()) {
+        Some(direction) => direction,
+        None => context.direction.clone(),
+    };
+    let mut expanded = Vec::new();
+    let mut entries: Vec<_> = value.as_object().into_iter().flatten().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (language, language_value) in entries {
+        for item in as_array(language_value.clone()) {
+            let item = match item {
+                Value::Null => continue,
+                Value::String(item) => item,
+                item => return Err(error(format!("Invalid language map value: {}", item))),
+            };
+            let mut object = Map::new();
+            object.insert("@value".to_string(), Value::String(item));
+            if expand_iri(context, language, false, true).as_deref() != Some("@none") {
+                object.insert(
+                    "@language".to_string(),
+                    Value::String(language.to_lowercase()),
+                );
+            }
+            if let Some(direction) = &direction {
+                object.insert("@direction".to_string(), Value::String(direction.clone()));
+            }
+            expanded.push(Value::Object(object));
+        }
+    }
+    Ok(Value::Array(expanded))
+}
+
+fn expand_index_map(
+    context: &Context,
+    key: &str,
+    definition: Option<&TermDefinition>,
+    value: &Value,
+) -> Result<Value, UtilsError> {
+    let has_container =
+        |container: &str| definition.is_some_and(|definition| definition.has_container(container));
+    let index_key = definition
+        .and_then(|definition| definition.index.clone())
+        .unwrap_or_else(|| "@index".to_string());
+    let mut expanded = Vec::new();
+    let mut entries: Vec<_> = value.as_object().into_iter().flatten().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (index, index_value) in entries {
+        let map_context = if has_container("@id") || has_container("@type") {
+            match &context.previous {
+                Some(previous) => (**previous).clone(),
+                None => context.clone(),
+            }
+        } else {
+            context.clone()
+        };
+        let map_context = match map_context
+            .term(index)
+            .and_then(|definition| definition.context.clone())
+        {
+            Some(local) if has_container("@type") => {
+                process_context(&map_context, &local, &[], false, true, true)?
+            }
+            _ => map_context,
+        };
+        let expanded_index = expand_iri(context, index, false, true);
+        let is_none = expanded_index.as_deref() == Some("@none");
+        let items = as_array(expand_element(
+            &map_context,
+            Some(key),
+            &Value::Array(as_array(index_value.clone())),
+            true,
+        )?);
+        for mut item in items {
+            if has_container("@graph") && item.get("@graph").is_none() {
+                item = single("@graph", Value::Array(as_array(item)));
+            }
+            let Value::Object(object) = &mut item else {
+                continue;
+            };
+            if has_container("@index") && index_key != "@index" && !is_none {
+                let reexpanded =
+                    expand_value(context, Some(&index_key), &Value::String(index.clone()))?;
+                let property = expand_iri(context, &index_key, false, true)
+                    .ok_or_else(|| error(format!("Invalid index key: {}", index_key)))?;
+                let mut values = vec![reexpanded];
+                values.extend(object.remove(&property).map(as_array).unwrap_or_default());
+                object.insert(property, Value::Array(values));
+                if object.contains_key("@value") {
+                    return Err(error(format!("Invalid value object: {}", key)));
+                }
+            } else if has_container("@index") && !object.contains_key("@index") && !is_none {
+                object.insert("@index".to_string(), Value::String(index.clone()));
+            } else if has_container("@id") && !object.contains_key("@id") && !is_none {
+                object.insert(
+                    "@id".to_string(),
+                    Value::String(node_iri(context, index, false)?),
+                );
+            } else if has_container("@type") && !is_none {
+                let mut types = vec![Value::String(
+                    expanded_index.clone().unwrap_or_else(|| index.clone()),
+                )];
+                types.extend(object.remove("@type").map(as_array).unwrap_or_default());
+                object.insert("@type".to_string(), Value::Array(types));
+            }
+            expanded.push(item);
+        }
+    }
+    Ok(Value::Array(expanded))
+}
+
+// Value Expansion
+fn expand_value(
+    active: &Context,
+    active_property: Option<&str>,
+    value: &Value,
+) -> Result<Value, UtilsError> {
+    let definition = active_property.and_then(|property| active.term(property));
+    let type_mapping = definition.and_then(|definition| definition.type_mapping.as_deref());
+    if let Value::String(string) = value {
+        match type_mapping {
+            Some("@id") => {
+                return Ok(single(
+                    "@id",
+                    Value::String(node_iri(active, string, false)?),
+                ))
+            }
+            Some("@vocab") => {
+                return Ok(single(
+                    "@id",
+                    Value::String(node_iri(active, string, true)?),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = Map::new();
+    result.insert("@value".to_string(), value.clone());
+    match type_mapping {
+        Some(type_mapping) if !matches!(type_mapping, "@id" | "@vocab" | "@none") => {
+            result.insert("@type".to_string(), Value::String(type_mapping.to_string()));
+        }
+        _ if value.is_string() => {
+            let language = match definition.and_then(|definition| definition.language.clone()) {
+                Some(language) => language,
+                None => active.language.clone(),
+            };
+            let direction = match definition.and_then(|definition| definition.direction.clone()) {
+                Some(direction) => direction,
+                None => active.direction.clone(),
+            };
+            if let Some(language) = language {
+                result.insert("@language".to_string(), Value::String(language));
+            }
+            if let Some(direction) = direction {
+                result.insert("@direction".to_string(), Value::String(direction));
+            }
+        }
+        _ => {}
+    }
+    Ok(Value::Object(result))
+}
+
+// ノードの @id と @type は絶対 IRI か空白ノードでなければ RDF に変換されない
+fn node_iri(active: &Context, value: &str, vocab: bool) -> Result<String, UtilsError> {
+    expand_iri(active, value, true, vocab)
+        .filter(|iri| is_absolute_iri(iri) || is_blank_node(iri))
+        .ok_or_else(|| error(format!("Relative IRI: {}", value)))
+}
+
+fn finish_object(
+    mut result: Map<String, Value>,
+    active_property: Option<&str>,
+) -> Result<Value, UtilsError> {
+    if result.contains_key("@value") {
+        if let Some(key) = result.keys().find(|key| {
+            !matches!(
+                key.as_str(),
+                "@direction" | "@index" | "@language" | "@type" | "@value"
+            )
+        }) {
+            return Err(error(format!("Invalid value object: {}", key)));
+        }
+        let has_language = result.contains_key("@language") || result.contains_key("@direction");
+        if result.contains_key("@type") && has_language {
+            return Err(error(
+                "Invalid value object: @type with @language".to_string(),
+            ));
+        }
+        if result.get("@type").and_then(Value::as_str) == Some("@json") {
+            return Ok(Value::Object(result));
+        }
+        match &result["@value"] {
+            Value::Null => return Ok(Value::Null),
+            Value::String(_) => {}
+            value if has_language => {
+                return Err(error(format!("Invalid language-tagged value: {}", value)))
+            }
+            _ => {}
+        }
+        if let Some(datatype) = result.get("@type") {
+            if !datatype.as_str().is_some_and(is_absolute_iri) {
+                return Err(error(format!("Invalid typed value: {}", datatype)));
+            }
+        }
+    } else if let Some(types) = result.get_mut("@type") {
+        if !types.is_array() {
+            *types = Value::Array(vec![types.take()]);
+        }
+    } else if result.contains_key("@set") || result.contains_key("@list") {
+        if result.len() > 2 || (result.len() == 2 && !result.contains_key("@index")) {
+            return Err(error("Invalid set or list object".to_string()));
+        }
+        if let Some(set) = result.remove("@set") {
+            return Ok(set);
+        }
+    }
+
+    if result.len() == 1 && result.contains_key("@language") {
+        return Ok(Value::Null);
+    }
+    if active_property.is_none_or(|property| property == "@graph") {
+        let free_floating = result.is_empty()
+            || result.contains_key("@value")
+            || result.contains_key("@list")
+            || (result.len() == 1 && result.contains_key("@id"));
+        if free_floating {
+            return Ok(Value::Null);
+        }
+    }
+    Ok(Value::Object(result))
+}
+
+fn reverse_map(result: &mut Map<String, Value>) -> &mut Map<String, Value> {
+    map_entry(result, "@reverse")
+}
+
+fn map_entry<'a>(map: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let entry = map.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    if !entry.is_object() {
+        *entry = Value::Object(Map::new());
+    }
+    entry.as_object_mut().expect("map entry is an object")
+}
+
+fn add_value(map: &mut Map<String, Value>, key: &str, value: Value) {
+    let values = map.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+    if !values.is_array() {
+        *values = Value::Array(vec![values.take()]);
+    }
+    if let Value::Array(values) = values {
+        values.extend(as_array(value));
+    }
+}
+
+fn single(key: &str, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(key.to_string(), value);
+    Value::Object(map)
+}
+
+fn as_array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        Value::Null => Vec::new(),
+        value => vec![value],
+    }
+}
+
+fn is_node_object(value: &Value) -> bool {
+    value.as_object().is_some_and(|object| {
+        !object.contains_key("@value")
+            && !object.contains_key("@list")
+            && !object.contains_key("@set")
+    })
+}
+
+// 最初の文字より後にある最初の : で、接頭辞と接尾辞に分ける
+fn split_prefix(value: &str) -> Option<(&str, &str)> {
+    let index = value.get(1..)?.find(':')? + 1;
+    Some((&value[..index], &value[index + 1..]))
+}
+
+fn is_keyword(value: &str) -> bool {
+    KEYWORDS.contains(&value)
+}
+
+// @ の後に英字だけが続く語は、将来のキーワードとして無視する
+fn has_keyword_form(value: &str) -> bool {
+    value
+        .strip_prefix('@')
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+fn is_blank_node(value: &str) -> bool {
+    value.starts_with("_:")
+}
+
+// スキームを持つ IRI
+fn is_absolute_iri(value: &str) -> bool {
+    let Some((scheme, _)) = value.split_once(':') else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+// Compaction Algorithm
+fn compact_element(
+    active: &Context,
+    active_property: Option<&str>,
+    element: &Value,
+) -> Result<Value, UtilsError> {
+    match element {
+        Value::Array(items) => {
+            let mut result = Vec::new();
+            for item in items {
+                let compacted = compact_element(active, active_property, item)?;
+                if !compacted.is_null() {
+                    result.push(compacted);
+                }
+            }
+            let keep_array = result.len() != 1
+                || active_property
+                    .is_some_and(|property| property == "@graph" || property == "@set")
+                || active.has_container(active_property, "@list")
+                || active.has_container(active_property, "@set");
+            Ok(if keep_array {
+                Value::Array(result)
+            } else {
+                result.remove(0)
+            })
+        }
+        Value::Object(element) => compact_object(active, active_property, element),
+        scalar => Ok(scalar.clone()),
+    }
+}
+
+fn compact_object(
+    active: &Context,
+    active_property: Option<&str>,
+    element: &Map<String, Value>,
+) -> Result<Value, UtilsError> {
+    let property_scoped = active_property
+        .and_then(|property| active.term(property))
+        .and_then(|definition| definition.context.clone());
+    let mut context = active.clone();
+    if let Some(previous) = &active.previous {
+        let only_id = element.len() == 1 && element.contains_key("@id");
+        if !element.contains_key("@value") && !only_id {
+            context = (**previous).clone();
+        }
+    }
+    if let Some(property_scoped) = &property_scoped {
+        context = process_context(&context, property_scoped, &[], true, true, true)?;
+    }
+
+    if element.contains_key("@value") || element.contains_key("@id") {
+        if let Some(compacted) = compact_value(&context, active_property, element)? {
+            return Ok(compacted);
+        }
+    }
+    if let Some(list) = element.get("@list") {
+        if context.has_container(active_property, "@list") {
+            return compact_element(&context, active_property, list);
+        }
+    }
+
+    let inside_reverse = active_property == Some("@reverse");
+    let type_scoped = context.clone();
+    if let Some(types) = element.get("@type") {
+        let mut terms = Vec::new();
+        for value in as_array(types.clone()) {
+            if let Some(value) = value.as_str() {
+                terms.push(compact_iri(&type_scoped, value, None, true, false)?);
+            }
+        }
+        terms.sort();
+        for term in terms {
+            if let Some(local) = type_scoped
+                .term(&term)
+                .and_then(|definition| definition.context.as_ref())
+            {
+                context = process_context(&context, local, &[], false, false, true)?;
+            }
+        }
+    }
+
+    let alias = |keyword: &str| compact_iri(&context, keyword, None, true, false);
+    let mut result = Map::new();
+    let mut keys: Vec<&String> = element.keys().collect();
+    keys.sort();
+    for expanded_property in keys {
+        let expanded_value = &element[expanded_property.as_str()];
+        match expanded_property.as_str() {
+            "@id" => {
+                if let Some(id) = expanded_value.as_str() {
+                    let id = compact_iri(&context, id, None, false, false)?;
+                    result.insert(alias("@id")?, Value::String(id));
+                }
+                continue;
+            }
+            "@type" => {
+                let mut types = Vec::new();
+                for value in as_array(expanded_value.clone()) {
+                    if let Some(value) = value.as_str() {
+                        types.push(Value::String(compact_iri(
+                            &type_scoped,
+                            value,
+                            None,
+                            true,
+                            false,
+                        )?));
+                    }
+                }
+                let compacted = match expanded_value {
+                    Value::Array(_) => Value::Array(types),
+                    _ => types.pop().unwrap_or(Value::Null),
+                };
+                let alias = alias("@type")?;
+                let as_array = context.has_container(Some(&alias), "@set");
+                add_compacted_value(&mut result, &alias, compacted, as_array);
+                continue;
+            }
+            "@reverse" => {
+                let mut remaining = Map::new();
+                if let Value::Object(compacted) =
+                    compact_element(&context, Some("@reverse"), expanded_value)?
+                {
+                    for (property, value) in compacted {
+                        match context.term(&property) {
+                            Some(definition) if definition.reverse => {
+                                let as_array = definition.has_container("@set");
+                                add_compacted_value(&mut result, &property, value, as_array);
+                            }
+                            _ => {
+                                remaining.insert(property, value);
+                            }
+                        }
+                    }
+                }
+                if !remaining.is_empty() {
+                    result.insert(alias("@reverse")?, Value::Object(remaining));
+                }
+                continue;
+            }
+            "@index" if context.has_container(active_property, "@index") => continue,
+            "@direction" | "@index" | "@language" | "@value" => {
+                result.insert(alias(expanded_property)?, expanded_value.clone());
+                continue;
+            }
+            _ => {}
+        }
+
+        let items = as_array(expanded_value.clone());
+        if items.is_empty() {
+            let item_property = compact_iri(
+                &context,
+                expanded_property,
+                Some(expanded_value),
+                true,
+                inside_reverse,
+            )?;
+            let nest_result = nest_result(&context, &mut result, &item_property)?;
+            add_compacted_value(nest_result, &item_property, Value::Array(Vec::new()), true);
+            continue;
+        }
+        for item in &items {
+            let item_property = compact_iri(
+                &context,
+                expanded_property,
+                Some(item),
+                true,
+                inside_reverse,
+            )?;
+            let definition = context.term(&item_property);
+            let has_container = |container: &str| {
+                definition.is_some_and(|definition| definition.has_container(container))
+            };
+            let always_array =
+                has_container("@set") || item_property == "@graph" || item_property == "@list";
+            let is_list = item.get("@list").is_some();
+            let is_graph = is_graph_object(item);
+            let inner = if is_list {
+                &item["@list"]
+            } else if is_graph {
+                &item["@graph"]
+            } else {
+                item
+            };
+            let mut compacted = compact_element(&context, Some(&item_property), inner)?;
+            let nest_result = nest_result(&context, &mut result, &item_property)?;
+
+            if is_list {
+                compacted = Value::Array(as_array(compacted));
+                if has_container("@list") {
+                    nest_result.insert(item_property, compacted);
+                } else {
+                    let mut list = Map::new();
+                    list.insert(alias("@list")?, compacted);
+                    if let Some(index) = item.get("@index") {
+                        list.insert(alias("@index")?, index.clone());
+                    }
+                    add_compacted_value(
+                        nest_result,
+                        &item_property,
+                        Value::Object(list),
+                        always_array,
+                    );
+                }
+            } else if is_graph {
+                let is_simple_graph = item.get("@id").is_none();
+                if has_container("@graph") && has_container("@id") {
+                    let map_key = match item.get("@id").and_then(Value::as_str) {
+                        Some(id) => compact_iri(&context, id, None, false, false)?,
+                        None => alias("@none")?,
+                    };
+                    let map_object = map_entry(nest_result, &item_property);
+                    add_compacted_value(map_object, &map_key, compacted, always_array);
+                } else if has_container("@graph") && has_container("@index") && is_simple_graph {
+                    let map_key = match item.get("@index").and_then(Value::as_str) {
+                        Some(index) => index.to_string(),
+                        None => alias("@none")?,
+                    };
+                    let map_object = map_entry(nest_result, &item_property);
+                    add_compacted_value(map_object, &map_key, compacted, always_array);
+                } else if has_container("@graph") && is_simple_graph {
+                    if compacted.as_array().is_some_and(|items| items.len() > 1) {
+                        compacted = single(&alias("@included")?, compacted);
+                    }
+                    add_compacted_value(nest_result, &item_property, compacted, always_array);
+                } else {
+                    let mut graph = Map::new();
+                    graph.insert(alias("@graph")?, compacted);
+                    if let Some(id) = item.get("@id").and_then(Value::as_str) {
+                        graph.insert(
+                            alias("@id")?,
+                            Value::String(compact_iri(&context, id, None, false, false)?),
+                        );
+                    }
+                    if let Some(index) = item.get("@index") {
+                        graph.insert(alias("@index")?, index.clone());
+                    }
+                    add_compacted_value(
+                        nest_result,
+                        &item_property,
+                        Value::Object(graph),
+                        always_array,
+                    );
+                }
+            } else if !has_container("@graph")
+                && ["@language", "@index", "@id", "@type"]
+                    .iter()
+                    .any(|container| has_container(container))
+            {
+                let map_key = compact_map_key(&context, &item_property, item, &mut compacted)?;
+                let map_key = match map_key {
+                    Some(map_key) => map_key,
+                    None => alias("@none")?,
+                };
+                let map_object = map_entry(nest_result, &item_property);
+                add_compacted_value(map_object, &map_key, compacted, always_array);
+            } else {
+                add_compacted_value(nest_result, &item_property, compacted, always_array);
+            }
+        }
+    }
+    Ok(Value::Object(result))
+}
+
+// 言語・インデックス・@id・@type のマップのキーを取り出す（キーにした値は項目から取り除く）
+fn compact_map_key(
+    context: &Context,
+    item_property: &str,
+    item: &Value,
+    compacted: &mut Value,
+) -> Result<Option<String>, UtilsError> {
+    let definition = context.term(item_property);
+    let has_container =
+        |container: &str| definition.is_some_and(|definition| definition.has_container(container));
+    let index_key = definition
+        .and_then(|definition| definition.index.clone())
+        .unwrap_or_else(|| "@index".to_string());
+    let as_string = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+
+    if has_container("@language") {
+        if let Some(value) = item.get("@value") {
+            *compacted = value.clone();
+        }
+        return Ok(as_string(item.get("@language")));
+    }
+    if has_container("@index") && index_key == "@index" {
+        return Ok(as_string(item.get("@index")));
+    }
+    let container_key = if has_container("@index") {
+        compact_iri(context, &index_key, None, true, false)?
+    } else if has_container("@id") {
+        compact_iri(context, "@id", None, true, false)?
+    } else {
+        compact_iri(context, "@type", None, true, false)?
+    };
+    let Some(object) = compacted.as_object_mut() else {
+        return Ok(None);
+    };
+    if has_container("@id") {
+        return Ok(as_string(object.remove(&container_key).as_ref()));
+    }
+
+    // プロパティのインデックスと @type は、最初の値をキーにして残りの値は項目に残す
+    let mut values = object
+        .remove(&container_key)
+        .map(as_array)
+        .unwrap_or_default();
+    let map_key = match values.first() {
+        Some(Value::String(_)) => values.remove(0).as_str().map(str::to_string),
+        _ => None,
+    };
+    match values.len() {
+        0 => {}
+        1 => {
+            object.insert(container_key.clone(), values.remove(0));
+        }
+        _ => {
+            object.insert(container_key.clone(), Value::Array(values));
+        }
+    }
+    if has_container("@type")
+        && object.len() == 1
+        && object.contains_key(&compact_iri(context, "@id", None, true, false)?)
+    {
+        if let Some(id) = item.get("@id") {
+            *compacted = compact_element(context, Some(item_property), &single("@id", id.clone()))?;
+        }
+    }
+    Ok(map_key)
+}
+
+fn nest_result<'a>(
+    context: &Context,
+    result: &'a mut Map<String, Value>,
+    item_property: &str,
+) -> Result<&'a mut Map<String, Value>, UtilsError> {
+    let Some(nest) = context
+        .term(item_property)
+        .and_then(|definition| definition.nest.clone())
+    else {
+        return Ok(result);
+    };
+    if expand_iri(context, &nest, false, true).as_deref() != Some("@nest") {
+        return Err(error(format!("Invalid @nest value: {}", nest)));
+    }
+    Ok(map_entry(result, &nest))
+}
+
+// Value Compaction（単純な値にできない場合は None）
+fn compact_value(
+    active: &Context,
+    active_property: Option<&str>,
+    value: &Map<String, Value>,
+) -> Result<Option<Value>, UtilsError> {
+    let definition = active_property.and_then(|property| active.term(property));
+    let type_mapping = definition.and_then(|definition| definition.type_mapping.as_deref());
+    let index_container = definition.is_some_and(|definition| definition.has_container("@index"));
+    let preserve_index = value.contains_key("@index") && !index_container;
+    let only_key =
+        value.len() == 1 || (value.len() == 2 && value.contains_key("@index") && !preserve_index);
+
+    if let Some(id) = value.get("@id").and_then(Value::as_str) {
+        if !only_key {
+            return Ok(None);
+        }
+        return Ok(match type_mapping {
+            Some("@id") => Some(Value::String(compact_iri(active, id, None, false, false)?)),
+            Some("@vocab") => Some(Value::String(compact_iri(active, id, None, true, false)?)),
+            _ => None,
+        });
+    }
+    let Some(literal) = value.get("@value") else {
+        return Ok(None);
+    };
+    if preserve_index || type_mapping == Some("@none") {
+        return Ok(None);
+    }
+    if let Some(datatype) = value.get("@type") {
+        return Ok((datatype.as_str() == type_mapping).then(|| literal.clone()));
+    }
+    let language = match definition.and_then(|definition| definition.language.clone()) {
+        Some(language) => language,
+        None => active.language.clone(),
+    };
+    let direction = match definition.and_then(|definition| definition.direction.clone()) {
+        Some(direction) => direction,
+        None => active.direction.clone(),
+    };
+    if value.contains_key("@language") || value.contains_key("@direction") {
+        let matches = value.get("@language").and_then(Value::as_str) == language.as_deref()
+            && value.get("@direction").and_then(Value::as_str) == direction.as_deref();
+        return Ok(matches.then(|| literal.clone()));
+    }
+    let no_language = !literal.is_string() || (language.is_none() && direction.is_none());
+    Ok((only_key && type_mapping.is_none() && no_language).then(|| literal.clone()))
+}
+
+// 逆引きのコンテキスト（IRI → コンテナ → @language / @type / @any → 値 → 用語）
+type InverseContext =
+    HashMap<String, HashMap<String, HashMap<&'static str, HashMap<String, String>>>>;
+
+fn inverse_context(active: &Context) -> InverseContext {
+    let mut terms: Vec<(&String, &TermDefinition)> = active.terms.iter().collect();
+    terms.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    let mut inverse = InverseContext::new();
+    for (term, definition) in terms {
+        let Some(iri) = &definition.iri else {
+            continue;
+        };
+        let mut container: Vec<&str> = definition.container.iter().map(String::as_str).collect();
+        container.sort();
+        let container = if container.is_empty() {
+            "@none".to_string()
+        } else {
+            container.concat()
+        };
+        let maps = inverse
+            .entry(iri.clone())
+            .or_default()
+            .entry(container)
+            .or_default();
+        let mut insert = |kind: &'static str, key: &str| {
+            maps.entry(kind)
+                .or_default()
+                .entry(key.to_string())
+                .or_insert_with(|| term.clone());
+        };
+        insert("@any", "@none");
+        if definition.reverse {
+            insert("@type", "@reverse");
+        } else if definition.type_mapping.as_deref() == Some("@none") {
+            insert("@language", "@any");
+            insert("@type", "@any");
+        } else if let Some(type_mapping) = &definition.type_mapping {
+            insert("@type", type_mapping);
+        } else if let (Some(language), Some(direction)) =
+            (&definition.language, &definition.direction)
+        {
+            insert(
+                "@language",
+                &language_direction(language.as_deref(), direction.as_deref()),
+            );
+        } else if let Some(language) = &definition.language {
+            insert("@language", language.as_deref().unwrap_or("@null"));
+        } else if let Some(direction) = &definition.direction {
+            let key = direction
+                .as_ref()
+                .map_or("@none".to_string(), |direction| format!("_{}", direction));
+            insert("@language", &key);
+        } else {
+            let default_language = match (&active.language, &active.direction) {
+                (language, Some(direction)) => {
+                    language_direction(language.as_deref(), Some(direction))
+                }
+                (Some(language), None) => language.clone(),
+                (None, None) => "@none".to_string(),
+            };
+            insert("@language", &default_language);
+            insert("@language", "@none");
+            insert("@type", "@none");
+        }
+    }
+    inverse
+}
+
+fn language_direction(language: Option<&str>, direction: Option<&str>) -> String {
+    match (language, direction) {
+        (Some(language), Some(direction)) => format!("{}_{}", language, direction),
+        (Some(language), None) => language.to_string(),
+        (None, Some(direction)) => format!("_{}", direction),
+        (None, None) => "@null".to_string(),
+    }
+}
+
+// IRI Compaction
+fn compact_iri(
+    active: &Context,
+    iri: &str,
+    value: Option<&Value>,
+    vocab: bool,
+    reverse: bool,
+) -> Result<String, UtilsError> {
+    if vocab {
+        if let Some(term) = select_term(active, iri, value, reverse)? {
+            return Ok(term);
+        }
+        if let Some(suffix) = active
+            .vocab
+            .as_deref()
+            .and_then(|vocab| iri.strip_prefix(vocab))
+        {
+            if !suffix.is_empty() && active.term(suffix).is_none() {
+                return Ok(suffix.to_string());
+            }
+        }
+    }
+
+    let mut compact_iri: Option<String> = None;
+    for (term, definition) in &active.terms {
+        let Some(prefix_iri) = definition.iri.as_deref() else {
+            continue;
+        };
+        if !definition.prefix || prefix_iri == iri {
+            continue;
+        }
+        let Some(suffix) = iri.strip_prefix(prefix_iri) else {
+            continue;
+        };
+        let candidate = format!("{}:{}", term, suffix);
+        let preferred = compact_iri
+            .as_ref()
+            .is_none_or(|current| (candidate.len(), &candidate) < (current.len(), current));
+        let usable = active
+            .term(&candidate)
+            .is_none_or(|definition| definition.iri.as_deref() == Some(iri) && value.is_none());
+        if preferred && usable {
+            compact_iri = Some(candidate);
+        }
+    }
+    if let Some(compact_iri) = compact_iri {
+        return Ok(compact_iri);
+    }
+    if let Some((prefix, suffix)) = split_prefix(iri) {
+        if !suffix.starts_with("//")
+            && active
+                .term(prefix)
+                .is_some_and(|definition| definition.prefix)
+        {
+            return Err(error(format!("IRI confused with prefix: {}", iri)));
+        }
+    }
+    Ok(iri.to_string())
+}
+
+// Term Selection
+fn select_term(
+    active: &Context,
+    iri: &str,
+    value: Option<&Value>,
+    reverse: bool,
+) -> Result<Option<String>, UtilsError> {
+    let inverse = inverse_context(active);
+    let Some(container_map) = inverse.get(iri) else {
+        return Ok(None);
+    };
+    let object = value.and_then(Value::as_object);
+    let has = |key: &str| object.is_some_and(|object| object.contains_key(key));
+    let get_str =
+        |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let default_language = match (&active.language, &active.direction) {
+        (None, None) => "@none".to_string(),
+        (language, direction) => language_direction(language.as_deref(), direction.as_deref()),
+    };
+
+    let mut containers: Vec<&str> = Vec::new();
+    let mut type_language = "@language";
+    let mut type_language_value = "@null".to_string();
+    let is_graph = value.is_some_and(is_graph_object);
+    if has("@index") && !is_graph {
+        containers.extend(["@index", "@index@set"]);
+    }
+    if reverse {
+        type_language = "@type";
+        type_language_value = "@reverse".to_string();
+        containers.push("@set");
+    } else if let Some(list) = value.and_then(|value| value.get("@list")) {
+        if !has("@index") {
+            containers.push("@list");
+        }
+        let list = as_array(list.clone());
+        let mut common_type: Option<String> = None;
+        let mut common_language = list.is_empty().then(|| default_language.clone());
+        for item in &list {
+            let mut item_language = "@none".to_string();
+            let mut item_type = "@none".to_string();
+            if item.get("@value").is_some() {
+                if let Some(direction) = get_str(item, "@direction") {
+                    item_language =
+                        language_direction(get_str(item, "@language").as_deref(), Some(&direction));
+                } else if let Some(language) = get_str(item, "@language") {
+                    item_language = language;
+                } else if let Some(datatype) = get_str(item, "@type") {
+                    item_type = datatype;
+                } else {
+                    item_language = "@null".to_string();
+                }
+            } else {
+                item_type = "@id".to_string();
+            }
+            match &common_language {
+                None => common_language = Some(item_language),
+                Some(common) if *common != item_language && item.get("@value").is_some() => {
+                    common_language = Some("@none".to_string())
+                }
+                _ => {}
+            }
+            match &common_type {
+                None => common_type = Some(item_type),
+                Some(common) if *common != item_type => common_type = Some("@none".to_string()),
+                _ => {}
+            }
+            if common_language.as_deref() == Some("@none")
+                && common_type.as_deref() == Some("@none")
+            {
+                break;
+            }
+        }
+        let common_type = common_type.unwrap_or_else(|| "@none".to_string());
+        if common_type != "@none" {
+            type_language = "@type";
+            type_language_value = common_type;
+        } else {
+            type_language_value = common_language.unwrap_or_else(|| "@none".to_string());
+        }
+    } else if is_graph {
+        if has("@index") {
+            containers.extend(["@graph@index", "@graph@index@set"]);
+        }
+        if has("@id") {
+            containers.extend(["@graph@id", "@graph@id@set"]);
+        }
+        containers.extend(["@graph", "@graph@set", "@set"]);
+        if !has("@index") {
+            containers.extend(["@graph@index", "@graph@index@set"]);
+        }
+        if !has("@id") {
+            containers.extend(["@graph@id", "@graph@id@set"]);
+        }
+        containers.extend(["@index", "@index@set"]);
+        type_language = "@type";
+        type_language_value = "@id".to_string();
+    } else {
+        if let Some(value) = value.filter(|_| has("@value")) {
+            if has("@direction") && !has("@index") {
+                type_language_value = language_direction(
+                    get_str(value, "@language").as_deref(),
+                    get_str(value, "@direction").as_deref(),
+                );
+                containers.extend(["@language", "@language@set"]);
+            } else if has("@language") && !has("@index") {
+                type_language_value = get_str(value, "@language").unwrap_or_default();
+                containers.extend(["@language", "@language@set"]);
+            } else if let Some(datatype) = get_str(value, "@type") {
+                type_language = "@type";
+                type_language_value = datatype;
+            }
+        } else {
+            type_language = "@type";
+            type_language_value = "@id".to_string();
+            containers.extend(["@id", "@id@set", "@type", "@set@type"]);
+        }
+        containers.push("@set");
+    }
+    containers.push("@none");
+    if !has("@index") {
+        containers.extend(["@index", "@index@set"]);
+    }
+    if has("@value") && object.is_some_and(|object| object.len() == 1) {
+        containers.extend(["@language", "@language@set"]);
+    }
+
+    let mut preferred_values: Vec<String> = Vec::new();
+    if type_language_value == "@reverse" {
+        preferred_values.push("@reverse".to_string());
+    }
+    let id = value.and_then(|value| get_str(value, "@id"));
+    match id {
+        Some(id) if type_language_value == "@id" || type_language_value == "@reverse" => {
+            let compacted = compact_iri(active, &id, None, true, false)?;
+            let is_term = active
+                .term(&compacted)
+                .is_some_and(|definition| definition.iri.as_deref() == Some(id.as_str()));
+            let order = if is_term {
+                ["@vocab", "@id", "@none"]
+            } else {
+                ["@id", "@vocab", "@none"]
+            };
+            preferred_values.extend(order.map(str::to_string));
+        }
+        _ => {
+            preferred_values.push(type_language_value.clone());
+            preferred_values.push("@none".to_string());
+            let empty_list = value
+                .and_then(|value| value.get("@list"))
+                .and_then(Value::as_array)
+                .is_some_and(Vec::is_empty);
+            if empty_list {
+                type_language = "@any";
+            }
+        }
+    }
+    preferred_values.push("@any".to_string());
+    if let Some(direction) = preferred_values
+        .iter()
+        .find_map(|value| value.find('_').map(|index| value[index..].to_string()))
+    {
+        preferred_values.push(direction);
+    }
+
+    for container in containers {
+        let Some(value_map) = container_map
+            .get(container)
+            .and_then(|type_language_map| type_language_map.get(type_language))
+        else {
+            continue;
+        };
+        if let Some(term) = preferred_values
+            .iter()
+            .find_map(|value| value_map.get(value))
+        {
+            return Ok(Some(term.clone()));
+        }
+    }
+    Ok(None)
+}
+
+fn add_compacted_value(map: &mut Map<String, Value>, key: &str, value: Value, always_array: bool) {
+    if always_array && !map.contains_key(key) {
+        map.insert(key.to_string(), Value::Array(Vec::new()));
+    }
+    if let Value::Array(values) = value {
+        for value in values {
+            add_compacted_value(map, key, value, always_array);
+        }
+        return;
+    }
+    match map.get_mut(key) {
+        None => {
+            map.insert(key.to_string(), value);
+        }
+        Some(existing) => {
+            if !existing.is_array() {
+                *existing = Value::Array(vec![existing.take()]);
+            }
+            if let Value::Array(existing) = existing {
+                existing.push(value);
+            }
+        }
+    }
+}
+
+fn is_graph_object(value: &Value) -> bool {
+    value.as_object().is_some_and(|object| {
+        object.contains_key("@graph")
+            && object
+                .keys()
+                .all(|key| matches!(key.as_str(), "@graph" | "@id" | "@index"))
+    })
+}
+
+#[derive(Default)]
+struct RdfWriter {
+    dataset: Dataset,
+    blank_nodes: HashMap<String, BlankNode>,
+    counter: usize,
+}
+
+enum NodeId {
+    Iri(NamedNode),
+    Blank(BlankNode),
+}
+
+impl NodeId {
+    fn subject(&self) -> Subject {
+        match self {
+            NodeId::Iri(iri) => iri.clone().into(),
+            NodeId::Blank(blank) => blank.clone().into(),
+        }
+    }
+
+    fn term(&self) -> Term {
+        match self {
+            NodeId::Iri(iri) => iri.clone().into(),
+            NodeId::Blank(blank) => blank.clone().into(),
+        }
+    }
+
+    fn graph_name(&self) -> GraphName {
+        match self {
+            NodeId::Iri(iri) => iri.clone().into(),
+            NodeId::Blank(blank) => blank.clone().into(),
+        }
+    }
+}
+
+impl RdfWriter {
+    fn blank_node(&mut self) -> BlankNode {
+        let blank_node = BlankNode::new_unchecked(format!("b{}", self.counter));
+        self.counter += 1;
+        blank_node
+    }
+
+    // 文書中の空白ノードのラベルは、文書内で一意なラベルに付け替える
+    fn node_id(&mut self, id: Option<&str>) -> Result<NodeId, UtilsError> {
+        match id {
+            None => Ok(NodeId::Blank(self.blank_node())),
+            Some(id) if is_blank_node(id) => {
+                if let Some(blank_node) = self.blank_nodes.get(id) {
+                    return Ok(NodeId::Blank(blank_node.clone()));
+                }
+                let blank_node = self.blank_node();
+                self.blank_nodes.insert(id.to_string(), blank_node.clone());
+                Ok(NodeId::Blank(blank_node))
+            }
+            Some(id) => named_node(id).map(NodeId::Iri),
+        }
+    }
+
+    fn insert(&mut self, subject: Subject, predicate: NamedNode, object: Term, graph: &GraphName) {
+        self.dataset
+            .insert(&Quad::new(subject, predicate, object, graph.clone()));
+    }
+
+    fn node(&mut self, node: &Map<String, Value>, graph: &GraphName) -> Result<NodeId, UtilsError> {
+        let id = self.node_id(node.get("@id").and_then(Value::as_str))?;
+        for (property, values) in node {
+            match property.as_str() {
+                "@type" => {
+                    for value in as_array(values.clone()) {
+                        let types = value
+                            .as_str()
+                            .ok_or_else(|| error(format!("Invalid @type: {}", value)))?;
+                        let object = self.node_id(Some(types))?.term();
+                        self.insert(id.subject(), named_node(RDF_TYPE)?, object, graph);
+                    }
+                }
+                "@reverse" => {
+                    for (reverse_property, values) in values.as_object().into_iter().flatten() {
+                        if is_blank_node(reverse_property) {
+                            continue;
+                        }
+                        let predicate = named_node(reverse_property)?;
+                        for value in as_array(values.clone()) {
+                            if let Value::Object(value) = value {
+                                let subject = self.node(&value, graph)?;
+                                self.insert(subject.subject(), predicate.clone(), id.term(), graph);
+                            }
+                        }
+                    }
+                }
+                "@graph" => {
+                    let graph_name = id.graph_name();
+                    for value in as_array(values.clone()) {
+                        if let Value::Object(value) = value {
+                            if !value.contains_key("@value") && !value.contains_key("@list") {
+                                self.node(&value, &graph_name)?;
+                            }
+                        }
+                    }
+                }
+                "@included" => {
+                    for value in as_array(values.clone()) {
+                        if let Value::Object(value) = value {
+                            self.node(&value, graph)?;
+                        }
+                    }
+                }
+                property if property.starts_with('@') || is_blank_node(property) => {}
+                property => {
+                    let predicate = named_node(property)?;
+                    for value in as_array(values.clone()) {
+                        if let Some(object) = self.object(&value, graph)? {
+                            self.insert(id.subject(), predicate.clone(), object, graph);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    fn object(&mut self, value: &Value, graph: &GraphName) -> Result<Option<Term>, UtilsError> {
+        let Value::Object(object) = value else {
+            return Ok(None);
+        };
+        if object.contains_key("@value") {
+            return literal(object).map(|literal| literal.map(Term::from));
+        }
+        if let Some(list) = object.get("@list") {
+            return self.list(&as_array(list.clone()), graph).map(Some);
+        }
+        Ok(Some(self.node(object, graph)?.term()))
+    }
+
+    fn list(&mut self, items: &[Value], graph: &GraphName) -> Result<Term, UtilsError> {
+        let nodes: Vec<BlankNode> = items.iter().map(|_| self.blank_node()).collect();
+        for (i, item) in items.iter().enumerate() {
+            if let Some(object) = self.object(item, graph)? {
+                self.insert(
+                    nodes[i].clone().into(),
+                    named_node(RDF_FIRST)?,
+                    object,
+                    graph,
+                );
+            }
+            let rest: Term = match nodes.get(i + 1) {
+                Some(next) => next.clone().into(),
+                None => named_node(RDF_NIL)?.into(),
+            };
+            self.insert(nodes[i].clone().into(), named_node(RDF_REST)?, rest, graph);
+        }
+        Ok(match nodes.first() {
+            Some(first) => first.clone().into(),
+            None => named_node(RDF_NIL)?.into(),
+        })
+    }
+}
+
+// 値オブジェクトを RDF のリテラルにする（数値の字句形式は JSON-LD の規定に合わせる）
+fn literal(object: &Map<String, Value>) -> Result<Option<Literal>, UtilsError> {
+    let value = &object["@value"];
+    let datatype = object.get("@type").and_then(Value::as_str);
+    if datatype == Some("@json") {
+        return Ok(Some(Literal::new_typed_literal(
+            jcs::canonicalize(value)?,
+            named_node(RDF_JSON)?,
+        )));
+    }
+    let (lexical, default_datatype) = match value {
+        Value::Bool(value) => (value.to_string(), XSD_BOOLEAN),
+        Value::Number(number) => {
+            let double = number.as_f64().unwrap_or_default();
+            let is_double = number.is_f64() && (double.fract() != 0.0 || double.abs() >= 1e21);
+            if is_double || datatype == Some(XSD_DOUBLE) {
+                (canonical_double(double), XSD_DOUBLE)
+            } else if number.is_f64() {
+                (format!("{:.0}", double), XSD_INTEGER)
+            } else {
+                (number.to_string(), XSD_INTEGER)
+            }
+        }
+        Value::String(value) => {
+            if datatype.is_none() {
+                if let Some(language) = object.get("@language").and_then(Value::as_str) {
+                    return Literal::new_language_tagged_literal(value, language)
+                        .map(Some)
+                        .map_err(|e| error(format!("Invalid language tag {}: {}", language, e)));
+                }
+                return Ok(Some(Literal::new_simple_literal(value)));
+            }
+            (value.clone(), "")
+        }
+        _ => return Ok(None),
+    };
+    let datatype = named_node(datatype.unwrap_or(default_datatype))?;
+    Ok(Some(Literal::new_typed_literal(lexical, datatype)))
+}
+
+// xsd:double の正規形（例: 1.1E0、1.0E21）
+fn canonical_double(value: f64) -> String {
+    let formatted = format!("{:.15E}", value);
+    let (mantissa, exponent) = formatted.split_once('E').unwrap_or((&formatted, "0"));
+    let mantissa = match mantissa.split_once('.') {
+        Some((integer, fraction)) => {
+            let fraction = fraction.trim_end_matches('0');
+            format!(
+                "{}.{}",
+                integer,
+                if fraction.is_empty() { "0" } else { fraction }
+            )
+        }
+        None => format!("{}.0", mantissa),
+    };
+    format!("{}E{}", mantissa, exponent)
+}
+
+fn named_node(iri: &str) -> Result<NamedNode, UtilsError> {
+    NamedNode::new(iri).map_err(|e| error(format!("Invalid IRI {}: {}", iri, e)))
+}
+
+fn error(message: String) -> UtilsError {
+    UtilsError::JsonLdError(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::type_metadata;
+    use crate::utils::data_integrity::DATA_INTEGRITY_CONTEXT;
+
+    // Issuer が発行する VC Data Model 1.1 と 2.0 のクレデンシャル
+    fn issued_credentials() -> Vec<Value> {
+        let proof = serde_json::json!({
+            "type": "DataIntegrityProof",
+            "cryptosuite": "eddsa-rdfc-2022",
+            "created": "2024-01-01T00:00:00Z",
+            "verificationMethod": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2#z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+            "proofPurpose": "assertionMethod",
+            "proofValue": "z2YwC8z3ap7yx1nZYCg4L3j3ApHsF8kgPdSb5xoS1VR7vPG3F561B52hYnQF9iseabecm3ijx4K1FBTQsCZahKZme"
+        });
+        vec![
+            serde_json::json!({
+                "@context": [
+                    "https://www.w3.org/2018/credentials/v1",
+                    DATA_INTEGRITY_CONTEXT,
+                    type_metadata::context_url("v1")
+                ],
+                "id": "http://example.edu/credentials/3732",
+                "type": ["VerifiableCredential", "UniversityDegreeCredential"],
+                "issuer": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+                "issuanceDate": "2024-01-01T00:00:00Z",
+                "credentialSubject": {
+                    "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+                    "name": "Alice",
+                    "degree": {
+                        "type": "BachelorDegree",
+                        "name": "Bachelor of Science in Mechanical Engineering"
+                    }
+                },
+                "proof": proof
+            }),
+            serde_json::json!({
+                "@context": [
+                    "https://www.w3.org/ns/credentials/v2",
+                    type_metadata::context_url("v1")
+                ],
+                "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+                "type": ["VerifiableCredential", "EmploymentCredential"],
+                "issuer": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+                "validFrom": "2024-01-01T00:00:00Z",
+                "credentialSubject": {
+                    "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+                    "name": "Alice",
+                    "employer": "Example Corp"
+                },
+                "proof": proof
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_json_ld_compaction() {
+        // 発行した VC は展開してから自身のコンテキストで圧縮すると元に戻る
+        for document in issued_credentials() {
+            let compacted = compact(&document, &document["@context"]).unwrap();
+            assert_eq!(compacted, document);
+
+            // 空のコンテキストで圧縮すると、すべてのプロパティが IRI になる
+            let compacted = compact(&document, &serde_json::json!({})).unwrap();
+            assert!(compacted.get("@context").is_none());
+            assert_eq!(compacted["@id"], document["id"]);
+            assert!(compacted["@type"]
+                .as_array()
+                .unwrap()
+                .contains(&"https://www.w3.org/2018/credentials#VerifiableCredential".into()));
+            let subject = &compacted["https://www.w3.org/2018/credentials#credentialSubject"];
+            assert_eq!(subject["https://schema.org/name"], "Alice");
+        }
+
+        // コンテナ、逆プロパティ、@nest、タイプのスコープのコンテキスト
+        let context = serde_json::json!({
+            "@version": 1.1,
+            "@vocab": "http://example.org/vocab#",
+            "ex": "http://example.org/",
+            "labels": { "@id": "ex:label", "@container": "@language" },
+            "tags": { "@id": "ex:tag", "@container": "@set" },
+            "items": { "@id": "ex:items", "@container": "@list" },
+            "knows": { "@id": "ex:knows", "@type": "@id" },
+            "parent": { "@reverse": "ex:child" },
+            "byId": { "@id": "ex:member", "@container": "@id" },
+            "byType": { "@id": "ex:part", "@container": "@type" },
+            "byIndex": { "@id": "ex:entry", "@container": "@index" },
+            "meta": "@nest",
+            "created": {
+                "@id": "ex:created",
+                "@type": "http://www.w3.org/2001/XMLSchema#dateTime",
+                "@nest": "meta"
+            },
+            "Person": { "@id": "ex:Person", "@context": { "nick": "ex:nickname" } },
+            "Widget": "ex:Widget"
+        });
+        let document = serde_json::json!({
+            "@context": context,
+            "@id": "ex:alice",
+            "@type": "Person",
+            "nick": "Al",
+            "labels": { "en": "Alice", "ja": "アリス" },
+            "tags": ["a"],
+            "items": [1, 2],
+            "knows": "ex:bob",
+            "parent": { "@id": "ex:carol" },
+            "byId": { "ex:m1": { "name": "m1" } },
+            "byType": { "Widget": "ex:w1" },
+            "byIndex": { "first": { "@id": "ex:e1" } },
+            "meta": { "created": "2024-01-01T00:00:00Z" }
+        });
+        let expanded = expand(&document).unwrap();
+        assert_eq!(
+            expanded[0]["http://example.org/nickname"],
+            serde_json::json!([{ "@value": "Al" }])
+        );
+        assert_eq!(
+            expanded[0]["@reverse"]["http://example.org/child"],
+            serde_json::json!([{ "@id": "http://example.org/carol" }])
+        );
+        let compacted = compact(&serde_json::Value::Array(expanded), &context).unwrap();
+        assert_eq!(compacted, document);
+    }
+
+    // W3C の json-ld-api のテストスイートと同じ形式のフィクスチャ
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/json-ld");
+
+    fn fixture(path: &Value) -> String {
+        let path = path.as_str().unwrap();
+        std::fs::read_to_string(format!("{}/{}", FIXTURES, path)).unwrap()
+    }
+
+    fn fixture_json(path: &Value) -> Value {
+        serde_json::from_str(&fixture(path)).unwrap()
+    }
+
+    // マニフェストのテストをすべて実行し、失敗したテストをまとめて報告する
+    fn run_manifest<T: std::fmt::Debug>(
+        manifest: &str,
+        run: impl Fn(&Value) -> Result<T, UtilsError>,
+        matches: impl Fn(&T, &Value) -> bool,
+    ) {
+        let manifest = fixture_json(&manifest.into());
+        let mut failures = Vec::new();
+        for test in manifest["sequence"].as_array().unwrap() {
+            let result = run(test);
+            let passed = match (&result, test["expectErrorCode"].as_str()) {
+                (Ok(actual), None) => matches(actual, test),
+                (Err(e), Some(code)) => e.to_string().to_lowercase().contains(code),
+                _ => false,
+            };
+            if !passed {
+                failures.push(format!("{} {}: {:?}", test["@id"], test["name"], result));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    // 配列は @list の値を除いて順序を問わずに比べる（テストスイートの比較方法）
+    fn json_ld_equal(actual: &Value, expected: &Value, ordered: bool) -> bool {
+        match (actual, expected) {
+            (Value::Array(actual), Value::Array(expected)) if ordered => {
+                actual.len() == expected.len()
+                    && actual
+                        .iter()
+                        .zip(expected)
+                        .all(|(actual, expected)| json_ld_equal(actual, expected, false))
+            }
+            (Value::Array(actual), Value::Array(expected)) => {
+                let mut matched = vec![false; expected.len()];
+                actual.len() == expected.len()
+                    && actual.iter().all(|actual| {
+                        let found = (0..expected.len())
+                            .find(|&i| !matched[i] && json_ld_equal(actual, &expected[i], false));
+                        found.map(|i| matched[i] = true).is_some()
+                    })
+            }
+            (Value::Object(actual), Value::Object(expected)) => {
+                actual.len() == expected.len()
+                    && actual.iter().all(|(key, actual)| match expected.get(key) {
+                        Some(expected) if key == "@value" => actual == expected,
+                        Some(expected) => json_ld_equal(actual, expected, key == "@list"),
+                        None => false,
+                    })
+            }
+            _ => actual == expected,
+        }
+    }
+
+    // 空白ノードのラベルの対応をすべて試し、付け替えると一致するかを調べる
+    fn isomorphic(actual: &[String], expected: &[String]) -> bool {
+        let labels = |lines: &[String]| {
+            let mut labels: Vec<String> = Vec::new();
+            for token in lines.iter().flat_map(|line| line.split(' ')) {
+                if token.starts_with("_:") && !labels.iter().any(|label| label == token) {
+                    labels.push(token.to_string());
+                }
+            }
+            labels
+        };
+        let (from, to) = (labels(actual), labels(expected));
+        if actual.len() != expected.len() || from.len() != to.len() {
+            return false;
+        }
+        let mut expected = expected.to_vec();
+        expected.sort();
+        permutations(to.len()).iter().any(|permutation| {
+            let mut relabeled: Vec<String> = actual
+                .iter()
+                .map(|line| {
+                    line.split(' ')
+                        .map(|token| match from.iter().position(|label| label == token) {
+                            Some(i) => to[permutation[i]].as_str(),
+                            None => token,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            relabeled.sort();
+            relabeled == expected
+        })
+    }
+
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        if n == 0 {
+            return vec![Vec::new()];
+        }
+        permutations(n - 1)
+            .into_iter()
+            .flat_map(|permutation| {
+                (0..n).map(move |i| {
+                    let mut permutation = permutation.clone();
+                    permutation.insert(i, n - 1);
+                    permutation
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_json_ld_expand_manifest() {
+        run_manifest(
+            "expand-manifest.jsonld",
+            |test| expand(&fixture_json(&test["input"])),
+            |actual, test| {
+                let actual = Value::Array(actual.clone());
+                json_ld_equal(&actual, &fixture_json(&test["expect"]), false)
+            },
+        );
+    }
+
+    #[test]
+    fn test_json_ld_compact_manifest() {
+        run_manifest(
+            "compact-manifest.jsonld",
+            |test| {
+                let input = fixture_json(&test["input"]);
+                compact(&input, &fixture_json(&test["context"]))
+            },
+            |actual, test| json_ld_equal(actual, &fixture_json(&test["expect"]), false),
+        );
+    }
+
+    #[test]
+    fn test_json_ld_to_rdf_manifest() {
+        run_manifest(
+            "toRdf-manifest.jsonld",
+            |test| {
+                let dataset = to_rdf(&fixture_json(&test["input"]))?;
+                Ok(dataset
+                    .iter()
+                    .map(|quad| format!("{} .", quad))
+                    .collect::<Vec<_>>())
+            },
+            |actual, test| {
+                let expected: Vec<String> = fixture(&test["expect"])
+                    .lines()
+                    .map(str::to_string)
+                    .collect();
+                isomorphic(actual, &expected)
+            },
+        );
+    }
+}
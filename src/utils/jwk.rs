@@ -0,0 +1,86 @@
+use crate::utils::error::UtilsError;
+use crate::utils::signer::VerifyingKey;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ed25519_dalek::PublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, RsaPublicKey};
+use serde_json::{json, Value};
+
+// Ed25519 は OKP (RFC 8037)、ECDSA は EC、RSA は RSA 形式の JWK で表す
+pub fn to_jwk(public_key: &VerifyingKey) -> Value {
+    match public_key {
+        VerifyingKey::Ed25519(key) => json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(key.to_bytes()),
+        }),
+        VerifyingKey::P256(key) => {
+            let point = key.to_encoded_point(false);
+            ec_jwk("P-256", point.x().unwrap(), point.y().unwrap())
+        }
+        VerifyingKey::P384(key) => {
+            let point = key.to_encoded_point(false);
+            ec_jwk("P-384", point.x().unwrap(), point.y().unwrap())
+        }
+        VerifyingKey::Rsa(key) => json!({
+            "kty": "RSA",
+            "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        }),
+    }
+}
+
+pub fn from_jwk(jwk: &Value) -> Result<VerifyingKey, UtilsError> {
+    let invalid_key = |e: String| UtilsError::InvalidKey(e);
+    match (jwk["kty"].as_str(), jwk["crv"].as_str()) {
+        (Some("OKP"), Some("Ed25519")) => PublicKey::from_bytes(&member(jwk, "x")?)
+            .map(VerifyingKey::Ed25519)
+            .map_err(|e| invalid_key(e.to_string())),
+        (Some("EC"), Some("P-256")) => {
+            p256::ecdsa::VerifyingKey::from_sec1_bytes(&uncompressed_point(jwk)?)
+                .map(VerifyingKey::P256)
+                .map_err(|e| invalid_key(e.to_string()))
+        }
+        (Some("EC"), Some("P-384")) => {
+            p384::ecdsa::VerifyingKey::from_sec1_bytes(&uncompressed_point(jwk)?)
+                .map(VerifyingKey::P384)
+                .map_err(|e| invalid_key(e.to_string()))
+        }
+        (Some("RSA"), _) => RsaPublicKey::new(
+            BigUint::from_bytes_be(&member(jwk, "n")?),
+            BigUint::from_bytes_be(&member(jwk, "e")?),
+        )
+        .map(VerifyingKey::Rsa)
+        .map_err(|e| invalid_key(e.to_string())),
+        _ => Err(UtilsError::InvalidKey(format!(
+            "Unsupported JWK: kty={}, crv={}",
+            jwk["kty"], jwk["crv"]
+        ))),
+    }
+}
+
+fn ec_jwk(crv: &str, x: &[u8], y: &[u8]) -> Value {
+    json!({
+        "kty": "EC",
+        "crv": crv,
+        "x": URL_SAFE_NO_PAD.encode(x),
+        "y": URL_SAFE_NO_PAD.encode(y),
+    })
+}
+
+// SEC1 の非圧縮形式（0x04 || x || y）
+fn uncompressed_point(jwk: &Value) -> Result<Vec<u8>, UtilsError> {
+    let mut point = vec![0x04];
+    point.extend(member(jwk, "x")?);
+    point.extend(member(jwk, "y")?);
+    Ok(point)
+}
+
+fn member(jwk: &Value, name: &str) -> Result<Vec<u8>, UtilsError> {
+    let value = jwk[name]
+        .as_str()
+        .ok_or_else(|| UtilsError::InvalidKey(format!("Missing {} in JWK", name)))?;
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| UtilsError::InvalidKey("Invalid base64 encoding in JWK".to_string()))
+}
@@ -1,52 +1,969 @@
-use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+use crate::did;
+use crate::utils::data_integrity::Cryptosuite;
+use crate::utils::error::UtilsError;
+use crate::utils::key_store::{self, DerivedKey, KdfParams};
+use crate::utils::pkcs11::Pkcs11KeyManager;
+use crate::utils::signer::{
+    SignatureAlgorithm, SignatureVerifier, Signer, SigningKey, VerifyingKey,
+};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::Read;
-use base58::FromBase58;
-use log::debug;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use zeroize::{Zeroize, Zeroizing};
 
 #[derive(Serialize, Deserialize)]
 struct KeyPair {
+    // 省略時は Ed25519 の鍵とみなす
+    #[serde(default = "default_algorithm")]
+    algorithm: SignatureAlgorithm,
     public_key: String,
     private_key: String,
 }
 
-pub trait KeyManager {
-    fn get_keypair(&self) -> Result<Keypair, String>;
-    fn get_public_key(&self) -> Result<PublicKey, String>;
+impl KeyPair {
+    fn new(signing_key: &SigningKey) -> Result<Self, String> {
+        Ok(KeyPair {
+            algorithm: signing_key.algorithm(),
+            public_key: bs58::encode(
+                signing_key
+                    .public_key()
+                    .to_bytes()
+                    .map_err(|e| e.to_string())?,
+            )
+            .into_string(),
+            private_key: bs58::encode(signing_key.to_bytes().map_err(|e| e.to_string())?)
+                .into_string(),
+        })
+    }
+}
+
+fn default_algorithm() -> SignatureAlgorithm {
+    SignatureAlgorithm::EdDSA
+}
+
+// 読み込んだ秘密鍵はメモリに残さない
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+// 鍵の状態：active の鍵で署名し、retired の鍵は署名済みのクレデンシャルの検証のためだけに公開する
+// revoked の鍵は公開せず、それで署名されたクレデンシャルは検証できなくなる
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    Active,
+    Retired,
+    Revoked,
 }
 
+#[derive(Serialize, Deserialize)]
+struct KeyEntry {
+    kid: String,
+    status: KeyStatus,
+    // 署名に使える期間（省略時は無期限）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_before: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_after: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    key_pair: KeyPair,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeySet {
+    keys: Vec<KeyEntry>,
+}
+
+// 鍵は base58 で表す（RSA の鍵も扱えるよう長さに制限のない bs58 で符号化する）
+// 以前の形式（鍵ペア一つだけのファイル）は key-1 の active な鍵として読み込む
+fn parse_key_set(contents: &[u8]) -> Result<KeySet, String> {
+    if let Ok(key_set) = serde_json::from_slice::<KeySet>(contents) {
+        return Ok(key_set);
+    }
+    let key_pair: KeyPair =
+        serde_json::from_slice(contents).map_err(|e| format!("Invalid key file: {}", e))?;
+    Ok(KeySet {
+        keys: vec![KeyEntry {
+            kid: LEGACY_KEY_ID.to_string(),
+            status: KeyStatus::Active,
+            not_before: None,
+            not_after: None,
+            key_pair,
+        }],
+    })
+}
+
+const LEGACY_KEY_ID: &str = "key-1";
+// 鍵ファイルを書き出すときのバッファの初期容量（再確保で秘密鍵のコピーが残らないよう、あらかじめ確保する）
+const KEY_FILE_BUFFER_SIZE: usize = 64 * 1024;
+
+// 鍵セットの中の一つの鍵（秘密鍵は含まない）
+#[derive(Clone, Debug)]
+pub struct ManagedKey {
+    pub kid: String,
+    pub status: KeyStatus,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub public_key: VerifyingKey,
+}
+
+impl ManagedKey {
+    // active な鍵で、現在が署名に使える期間内であるか
+    pub fn can_sign_at(&self, now: DateTime<Utc>) -> bool {
+        self.status == KeyStatus::Active
+            && self.not_before.is_none_or(|not_before| not_before <= now)
+            && self.not_after.is_none_or(|not_after| now < not_after)
+    }
+}
+
+// 秘密鍵を外に出さずに署名できるよう、鍵そのものではなく Signer を返す
+pub trait KeyManager: Send + Sync {
+    // 現在の署名鍵（署名に使える active な鍵のうち、最も新しいもの）
+    fn get_current_key(&self) -> Result<ManagedKey, String>;
+    // 鍵セットのすべての鍵（退役・失効したものを含む）
+    fn get_keys(&self) -> Result<Vec<ManagedKey>, String>;
+    fn get_signer(&self, kid: &str) -> Result<Box<dyn Signer>, String>;
+    // 新しい鍵を生成して現在の署名鍵とし、それまでの active な鍵は退役（revoke_current の場合は現在の鍵を失効）させる
+    fn rotate_key(
+        &self,
+        algorithm: Option<SignatureAlgorithm>,
+        revoke_current: bool,
+    ) -> Result<ManagedKey, String>;
+    // 他のツールで鍵ファイルやトークンの鍵を変更した場合に、鍵セットを読み直す
+    fn reload(&self) -> Result<(), String>;
+}
+
+// 鍵ファイルから読み込んだ鍵（署名のたびに鍵ファイルを読まないよう、ローテーションするまで使い回す）
+struct LoadedKeys {
+    keys: Vec<ManagedKey>,
+    // 失効していない鍵の署名鍵
+    signing_keys: HashMap<String, Arc<SigningKey>>,
+}
+
+impl LoadedKeys {
+    fn new(key_set: &KeySet) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        let mut signing_keys = HashMap::new();
+        for entry in &key_set.keys {
+            keys.push(managed_key(entry)?);
+            if entry.status != KeyStatus::Revoked {
+                let private_key = Zeroizing::new(
+                    bs58::decode(&entry.key_pair.private_key)
+                        .into_vec()
+                        .map_err(|_| format!("Invalid private key: {}", entry.kid))?,
+                );
+                let signing_key = SigningKey::from_bytes(entry.key_pair.algorithm, &private_key)
+                    .map_err(|e| e.to_string())?;
+                signing_keys.insert(entry.kid.clone(), Arc::new(signing_key));
+            }
+        }
+        Ok(LoadedKeys { keys, signing_keys })
+    }
+}
+
+// 鍵ファイルは最初に使うときに読み込み、以降はメモリ上の鍵を使う（鍵ファイルを読み直すのはローテーションのときだけ）
 pub struct FileKeyManager {
     file_path: String,
+    // 設定されていれば、鍵ファイルをこのパスフレーズで暗号化する（key_store を参照）
+    passphrase: Option<Zeroizing<String>>,
+    derived_key: Mutex<Option<DerivedKey>>,
+    // 新しく鍵ファイルを作るときの KDF のパラメータ（省略時は既定のパラメータ）
+    kdf: Option<KdfParams>,
+    loaded: RwLock<Option<Arc<LoadedKeys>>>,
+    // ローテーションによる鍵ファイルの更新を直列化する
+    lock: Mutex<()>,
 }
 
 impl FileKeyManager {
     pub fn new(file_path: String) -> Self {
-        FileKeyManager { file_path }
+        FileKeyManager {
+            file_path,
+            passphrase: None,
+            derived_key: Mutex::new(None),
+            kdf: None,
+            loaded: RwLock::new(None),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn encrypted(file_path: String, passphrase: Zeroizing<String>) -> Self {
+        FileKeyManager {
+            passphrase: Some(passphrase),
+            ..FileKeyManager::new(file_path)
+        }
+    }
+
+    pub fn with_kdf_params(mut self, kdf: KdfParams) -> Self {
+        self.kdf = Some(kdf);
+        self
+    }
+
+    // パスフレーズから鍵を導出する（同じ KDF のパラメータであれば前回導出した鍵を使う）
+    fn derive_key(&self, passphrase: &str, kdf: KdfParams) -> Result<DerivedKey, String> {
+        let mut derived_key = self
+            .derived_key
+            .lock()
+            .map_err(|_| "Failed to acquire lock")?;
+        if let Some(key) = derived_key.as_ref().filter(|key| *key.kdf() == kdf) {
+            return Ok(key.clone());
+        }
+        let key = DerivedKey::derive(passphrase, kdf).map_err(|e| e.to_string())?;
+        *derived_key = Some(key.clone());
+        Ok(key)
+    }
+
+    // 鍵ファイルを読み込み、現在の署名鍵があることを確かめる
+    pub fn load(&self) -> Result<(), String> {
+        current_key(&self.loaded_keys()?.keys).map(|_| ())
+    }
+
+    fn loaded_keys(&self) -> Result<Arc<LoadedKeys>, String> {
+        if let Some(loaded) = self
+            .loaded
+            .read()
+            .map_err(|_| "Failed to acquire lock")?
+            .as_ref()
+        {
+            return Ok(loaded.clone());
+        }
+        let mut loaded = self.loaded.write().map_err(|_| "Failed to acquire lock")?;
+        if let Some(loaded) = loaded.as_ref() {
+            return Ok(loaded.clone());
+        }
+        let keys = Arc::new(LoadedKeys::new(&self.load_key_set()?)?);
+        *loaded = Some(keys.clone());
+        Ok(keys)
+    }
+
+    fn load_key_set(&self) -> Result<KeySet, String> {
+        let contents = Zeroizing::new(
+            fs::read(&self.file_path).map_err(|e| format!("{}: {}", self.file_path, e))?,
+        );
+        let key_set = match &self.passphrase {
+            Some(passphrase) => {
+                let key = self.derive_key(
+                    passphrase,
+                    key_store::kdf_params(&contents).map_err(|e| e.to_string())?,
+                )?;
+                parse_key_set(&key_store::decrypt(&contents, &key).map_err(|e| e.to_string())?)?
+            }
+            None if key_store::is_encrypted(&contents) => {
+                return Err(format!(
+                    "{} is encrypted but no passphrase is set",
+                    self.file_path
+                ))
+            }
+            None => parse_key_set(&contents)?,
+        };
+
+        // 秘密鍵はログに出さない
+        for entry in &key_set.keys {
+            debug!(
+                "Loaded {} key {} ({:?}): {}",
+                entry.key_pair.algorithm.name(),
+                entry.kid,
+                entry.status,
+                entry.key_pair.public_key
+            );
+        }
+        Ok(key_set)
+    }
+
+    // 書きかけのファイルを読まないよう、一時ファイルに書いてから置き換える
+    fn save_key_set(&self, key_set: &KeySet) -> Result<(), String> {
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(KEY_FILE_BUFFER_SIZE));
+        serde_json::to_writer_pretty(&mut *plaintext, key_set).map_err(|e| e.to_string())?;
+        let contents = match &self.passphrase {
+            Some(passphrase) => {
+                // 既存の鍵ファイルのソルトと KDF のパラメータを引き継ぐ（ナンスは書き込みのたびに変わる）
+                let kdf = fs::read(&self.file_path)
+                    .ok()
+                    .and_then(|contents| key_store::kdf_params(&contents).ok())
+                    .or_else(|| self.kdf.clone())
+                    .unwrap_or_else(KdfParams::generate);
+                let key = self.derive_key(passphrase, kdf)?;
+                Zeroizing::new(key_store::encrypt(&plaintext, &key).map_err(|e| e.to_string())?)
+            }
+            None => plaintext,
+        };
+
+        let temp_path = format!("{}.tmp", self.file_path);
+        write_private_file(&temp_path, &contents)?;
+        fs::rename(&temp_path, &self.file_path).map_err(|e| e.to_string())
     }
 }
 
-impl KeyManager for FileKeyManager {
-    fn get_keypair(&self) -> Result<Keypair, String> {
-        let mut file = File::open(&self.file_path).map_err(|e| e.to_string())?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).map_err(|e| e.to_string())?;
+// 鍵ファイルは所有者だけが読み書きできるようにする
+fn write_private_file(path: &str, contents: &[u8]) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|e| format!("{}: {}", path, e))?;
+    file.write_all(contents).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())
+}
 
-        let key_pair: KeyPair = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+// 平文の鍵ファイルを暗号化した鍵ファイルに変換する
+// 暗号化したファイルを読み戻して同じ鍵が得られることを確かめてから、平文のファイルを削除する
+pub fn migrate_key_file(
+    plaintext_path: &str,
+    encrypted_path: &str,
+    passphrase: Zeroizing<String>,
+    kdf: KdfParams,
+) -> Result<(), String> {
+    if Path::new(encrypted_path).exists() {
+        return Err(format!("{} already exists", encrypted_path));
+    }
+    let plaintext = FileKeyManager::new(plaintext_path.to_string());
+    let key_set = plaintext.load_key_set()?;
 
-        debug!("Loaded public key: {}", key_pair.public_key);
-        debug!("Loaded private key: {}", key_pair.private_key);
+    let encrypted =
+        FileKeyManager::encrypted(encrypted_path.to_string(), passphrase).with_kdf_params(kdf);
+    encrypted.save_key_set(&key_set)?;
+    let migrated = encrypted.load_key_set()?;
+    let matches = migrated.keys.len() == key_set.keys.len()
+        && migrated
+            .keys
+            .iter()
+            .zip(&key_set.keys)
+            .all(|(a, b)| a.kid == b.kid && a.key_pair.private_key == b.key_pair.private_key);
+    if !matches {
+        let _ = fs::remove_file(encrypted_path);
+        return Err(format!("Failed to verify {}", encrypted_path));
+    }
 
-        let secret = SecretKey::from_bytes(&key_pair.private_key.from_base58().map_err(|e| format!("Invalid private key: {:?}", e))?)
-            .map_err(|e| e.to_string())?;
-        let public = PublicKey::from_bytes(&key_pair.public_key.from_base58().map_err(|e| format!("Invalid public key: {:?}", e))?)
+    fs::remove_file(plaintext_path).map_err(|e| format!("{}: {}", plaintext_path, e))?;
+    info!(
+        "Migrated {} key(s) from {} to {}",
+        key_set.keys.len(),
+        plaintext_path,
+        encrypted_path
+    );
+    Ok(())
+}
+
+fn managed_key(entry: &KeyEntry) -> Result<ManagedKey, String> {
+    let public_key = bs58::decode(&entry.key_pair.public_key)
+        .into_vec()
+        .map_err(|e| format!("Invalid public key: {:?}", e))?;
+    Ok(ManagedKey {
+        kid: entry.kid.clone(),
+        status: entry.status,
+        not_before: entry.not_before,
+        not_after: entry.not_after,
+        public_key: VerifyingKey::from_bytes(entry.key_pair.algorithm, &public_key)
+            .map_err(|e| e.to_string())?,
+    })
+}
+
+fn current_key(keys: &[ManagedKey]) -> Result<&ManagedKey, String> {
+    let now = Utc::now();
+    let mut current: Option<&ManagedKey> = None;
+    for key in keys.iter().filter(|key| key.can_sign_at(now)) {
+        if current.is_none_or(|current| current.not_before <= key.not_before) {
+            current = Some(key);
+        }
+    }
+    current.ok_or_else(|| "No active signing key".to_string())
+}
+
+// Holder の鍵ファイル（鍵ペア一つだけの形式）を読み込む。ファイルがなければ Ed25519 の鍵を生成して書き出す
+pub fn load_or_create_signing_key(path: &str) -> Result<SigningKey, String> {
+    if !Path::new(path).exists() {
+        let signing_key =
+            SigningKey::generate(SignatureAlgorithm::EdDSA).map_err(|e| e.to_string())?;
+        let mut contents = Zeroizing::new(Vec::with_capacity(KEY_FILE_BUFFER_SIZE));
+        serde_json::to_writer_pretty(&mut *contents, &KeyPair::new(&signing_key)?)
             .map_err(|e| e.to_string())?;
+        write_private_file(path, &contents)?;
+        info!("Generated new key in {}", path);
+        return Ok(signing_key);
+    }
+
+    let contents = Zeroizing::new(fs::read(path).map_err(|e| format!("{}: {}", path, e))?);
+    let key_pair: KeyPair =
+        serde_json::from_slice(&contents).map_err(|e| format!("Invalid key file: {}", e))?;
+    let private_key = Zeroizing::new(
+        bs58::decode(&key_pair.private_key)
+            .into_vec()
+            .map_err(|_| format!("Invalid private key: {}", path))?,
+    );
+    let signing_key =
+        SigningKey::from_bytes(key_pair.algorithm, &private_key).map_err(|e| e.to_string())?;
+    if KeyPair::new(&signing_key)?.public_key != key_pair.public_key {
+        return Err(format!("Public key does not match private key: {}", path));
+    }
+    debug!(
+        "Loaded {} key from {}: {}",
+        key_pair.algorithm.name(),
+        path,
+        key_pair.public_key
+    );
+    Ok(signing_key)
+}
+
+// ローテーションで生成した鍵 ID（key-N）の N
+pub fn key_number(kid: &str) -> Option<u32> {
+    kid.strip_prefix("key-")?.parse().ok()
+}
+
+// 既存の鍵 ID と重ならない key-N
+pub fn next_key_id<'a>(kids: impl IntoIterator<Item = &'a str>) -> String {
+    let next = kids.into_iter().filter_map(key_number).max().unwrap_or(0) + 1;
+    format!("key-{}", next)
+}
+
+impl KeyManager for FileKeyManager {
+    fn get_current_key(&self) -> Result<ManagedKey, String> {
+        current_key(&self.loaded_keys()?.keys).cloned()
+    }
+
+    fn get_keys(&self) -> Result<Vec<ManagedKey>, String> {
+        Ok(self.loaded_keys()?.keys.clone())
+    }
+
+    fn get_signer(&self, kid: &str) -> Result<Box<dyn Signer>, String> {
+        let loaded = self.loaded_keys()?;
+        if let Some(signing_key) = loaded.signing_keys.get(kid) {
+            return Ok(Box::new(signing_key.clone()));
+        }
+        if loaded.keys.iter().any(|key| key.kid == kid) {
+            Err(format!("Key {} is revoked", kid))
+        } else {
+            Err(format!("Unknown key: {}", kid))
+        }
+    }
 
-        Ok(Keypair { secret, public })
+    fn rotate_key(
+        &self,
+        algorithm: Option<SignatureAlgorithm>,
+        revoke_current: bool,
+    ) -> Result<ManagedKey, String> {
+        let _guard = self.lock.lock().map_err(|_| "Failed to acquire lock")?;
+        let mut key_set = self.load_key_set()?;
+        let keys = key_set
+            .keys
+            .iter()
+            .map(managed_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        let current = current_key(&keys)?;
+        let current_kid = current.kid.clone();
+        let algorithm = algorithm.unwrap_or(current.public_key.algorithm());
+
+        let now = Utc::now();
+        for entry in key_set
+            .keys
+            .iter_mut()
+            .filter(|entry| entry.status == KeyStatus::Active)
+        {
+            entry.status = if revoke_current && entry.kid == current_kid {
+                KeyStatus::Revoked
+            } else {
+                KeyStatus::Retired
+            };
+            if entry.not_after.is_none_or(|not_after| now < not_after) {
+                entry.not_after = Some(now);
+            }
+        }
+
+        let signing_key = SigningKey::generate(algorithm).map_err(|e| e.to_string())?;
+        let entry = KeyEntry {
+            kid: next_key_id(key_set.keys.iter().map(|entry| entry.kid.as_str())),
+            status: KeyStatus::Active,
+            not_before: Some(now),
+            not_after: None,
+            key_pair: KeyPair::new(&signing_key)?,
+        };
+        info!(
+            "Rotated signing key {} to {} ({})",
+            current_kid,
+            entry.kid,
+            algorithm.name()
+        );
+        let key = managed_key(&entry)?;
+        key_set.keys.push(entry);
+        self.save_key_set(&key_set)?;
+        *self.loaded.write().map_err(|_| "Failed to acquire lock")? =
+            Some(Arc::new(LoadedKeys::new(&key_set)?));
+        Ok(key)
+    }
+
+    fn reload(&self) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|_| "Failed to acquire lock")?;
+        let keys = LoadedKeys::new(&self.load_key_set()?)?;
+        *self.loaded.write().map_err(|_| "Failed to acquire lock")? = Some(Arc::new(keys));
+        info!("Reloaded signing keys from {}", self.file_path);
+        Ok(())
+    }
+}
+
+// 平文の鍵ファイルと、暗号化した鍵ファイルの既定のパス
+pub const KEY_FILE: &str = "keys/keys.json";
+pub const ENCRYPTED_KEY_FILE: &str = "keys/keys.enc.json";
+// Holder の鍵ファイルの既定のパスと、それを変更する環境変数
+const HOLDER_KEY_FILE: &str = "keys/holder.json";
+const HOLDER_KEY_FILE_ENV: &str = "HOLDER_KEY_FILE";
+// 鍵ファイルのパス（省略時は KEY_FILE / ENCRYPTED_KEY_FILE）
+const KEY_FILE_ENV: &str = "ISSUER_KEY_FILE";
+// 暗号化した鍵ファイルのパスフレーズ
+const KEY_PASSPHRASE_ENV: &str = "ISSUER_KEY_PASSPHRASE";
+// PKCS#11 のライブラリ、トークンのラベル、ユーザー PIN
+const PKCS11_MODULE_ENV: &str = "ISSUER_PKCS11_MODULE";
+const PKCS11_TOKEN_ENV: &str = "ISSUER_PKCS11_TOKEN";
+const PKCS11_PIN_ENV: &str = "ISSUER_PKCS11_PIN";
+// did:web で発行する場合の Issuer の DID（例: did:web:our.domain）
+const ISSUER_DID_ENV: &str = "ISSUER_DID";
+// Ed25519 の鍵で発行する VC の cryptosuite（既定は eddsa-jcs-2022）
+const ISSUER_CRYPTOSUITE_ENV: &str = "ISSUER_CRYPTOSUITE";
+
+// Issuer の署名鍵と、発行に使う DID・cryptosuite の設定
+// 起動時に一度だけ読み込み、KeyManager と IssuerConfig を web::Data で共有する
+pub struct KeyManagerConfig {
+    pub keys: KeySource,
+    pub issuer: IssuerConfig,
+}
+
+impl KeyManagerConfig {
+    pub fn from_env() -> Result<Self, UtilsError> {
+        let cryptosuite = std::env::var(ISSUER_CRYPTOSUITE_ENV).ok();
+        Ok(KeyManagerConfig {
+            keys: KeySource::from_env()?,
+            issuer: IssuerConfig::new(std::env::var(ISSUER_DID_ENV).ok(), cryptosuite.as_deref())?,
+        })
+    }
+}
+
+// Issuer の DID（None の場合は現在の署名鍵の did:key）と、Ed25519 の鍵で発行する VC の cryptosuite
+#[derive(Clone, Debug, Default)]
+pub struct IssuerConfig {
+    pub did: Option<String>,
+    pub cryptosuite: Cryptosuite,
+}
+
+impl IssuerConfig {
+    // DID は did:web だけを受け付け、cryptosuite が空の場合は既定のものを使う
+    pub fn new(did: Option<String>, cryptosuite: Option<&str>) -> Result<Self, UtilsError> {
+        if let Some(did) = did.as_deref().filter(|did| !did::web::is_did_web(did)) {
+            return Err(UtilsError::InvalidKey(format!(
+                "{} must be a did:web: {}",
+                ISSUER_DID_ENV, did
+            )));
+        }
+        let cryptosuite = match cryptosuite {
+            Some(name) if !name.is_empty() => Cryptosuite::from_name(name)?,
+            _ => Cryptosuite::default(),
+        };
+        Ok(IssuerConfig { did, cryptosuite })
+    }
+}
+
+// Issuer の署名鍵の読み込み元
+pub enum KeySource {
+    File(String),
+    EncryptedFile(String, Zeroizing<String>),
+    Pkcs11 {
+        module: String,
+        token: String,
+        pin: Zeroizing<String>,
+    },
+}
+
+impl KeySource {
+    // ISSUER_PKCS11_MODULE が設定されていれば PKCS#11 トークンの鍵を使う
+    // ISSUER_KEY_FILE が設定されていればその鍵ファイルを（ISSUER_KEY_PASSPHRASE があれば暗号化したものとして）、
+    // なければ keys/keys.enc.json があればそれを、なければ平文の keys/keys.json を使う
+    fn from_env() -> Result<Self, UtilsError> {
+        if let Ok(module) = std::env::var(PKCS11_MODULE_ENV) {
+            let token = std::env::var(PKCS11_TOKEN_ENV).map_err(|_| {
+                UtilsError::KeyStoreError(format!("{} is not set", PKCS11_TOKEN_ENV))
+            })?;
+            let pin = Zeroizing::new(std::env::var(PKCS11_PIN_ENV).unwrap_or_default());
+            return Ok(KeySource::Pkcs11 { module, token, pin });
+        }
+
+        let passphrase = std::env::var(KEY_PASSPHRASE_ENV).ok().map(Zeroizing::new);
+        Ok(match (std::env::var(KEY_FILE_ENV), passphrase) {
+            (Ok(path), Some(passphrase)) => KeySource::EncryptedFile(path, passphrase),
+            (Ok(path), None) => KeySource::File(path),
+            // 未設定の場合は空のパスフレーズとなり、鍵ファイルを読み込むときにエラーになる
+            (Err(_), passphrase) if Path::new(ENCRYPTED_KEY_FILE).exists() => {
+                KeySource::EncryptedFile(
+                    ENCRYPTED_KEY_FILE.to_string(),
+                    passphrase.unwrap_or_default(),
+                )
+            }
+            (Err(_), _) => KeySource::File(KEY_FILE.to_string()),
+        })
+    }
+
+    // 鍵ファイルは起動時に読み込み、読み込めない場合や現在の署名鍵がない場合は起動しない
+    pub fn build(self) -> Result<Arc<dyn KeyManager>, UtilsError> {
+        let key_manager = match self {
+            KeySource::File(path) => FileKeyManager::new(path),
+            KeySource::EncryptedFile(path, passphrase) => {
+                FileKeyManager::encrypted(path, passphrase)
+            }
+            KeySource::Pkcs11 { module, token, pin } => {
+                return Ok(Arc::new(
+                    Pkcs11KeyManager::new(&module, &token, &pin)
+                        .map_err(UtilsError::KeyStoreError)?,
+                ));
+            }
+        };
+        key_manager.load().map_err(UtilsError::KeyStoreError)?;
+        Ok(Arc::new(key_manager))
+    }
+}
+
+// 暗号化した鍵ファイルのパスフレーズ（migrate-keys で使う）
+pub fn key_passphrase() -> Zeroizing<String> {
+    Zeroizing::new(std::env::var(KEY_PASSPHRASE_ENV).unwrap_or_default())
+}
+
+pub fn holder_key_file() -> String {
+    std::env::var(HOLDER_KEY_FILE_ENV).unwrap_or_else(|_| HOLDER_KEY_FILE.to_string())
+}
+
+#[cfg(test)]
+pub mod test_key_manager {
+    use super::{FileKeyManager, KeyManager, KEY_FILE};
+    use std::sync::{Arc, OnceLock};
+
+    // テスト用の Issuer の鍵（keys/keys.json）をテスト全体で共有する
+    pub fn issuer_key_manager() -> Arc<dyn KeyManager> {
+        static KEY_MANAGER: OnceLock<Arc<FileKeyManager>> = OnceLock::new();
+        KEY_MANAGER
+            .get_or_init(|| Arc::new(FileKeyManager::new(KEY_FILE.to_string())))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_key_file(contents: &serde_json::Value) -> String {
+        let path = std::env::temp_dir().join(format!("keys-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, contents.to_string()).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    // 鍵ペアを一つだけ書いた形式の鍵ファイルの内容
+    fn key_pair(signing_key: &SigningKey) -> serde_json::Value {
+        serde_json::json!({
+            "algorithm": signing_key.algorithm(),
+            "public_key": bs58::encode(signing_key.public_key().to_bytes().unwrap()).into_string(),
+            "private_key": bs58::encode(signing_key.to_bytes().unwrap()).into_string(),
+        })
     }
 
-    fn get_public_key(&self) -> Result<PublicKey, String> {
-        let keypair = self.get_keypair()?;
-        Ok(keypair.public)
+    fn assert_signs(key_manager: &dyn KeyManager, key: &ManagedKey) {
+        let signature = key_manager
+            .get_signer(&key.kid)
+            .unwrap()
+            .sign(b"message")
+            .unwrap();
+        assert!(key.public_key.verify(b"message", &signature).is_ok());
+    }
+
+    #[test]
+    fn test_key_manager_config_loads_key_file() {
+        let signing_key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let key_pair = key_pair(&signing_key);
+        let path = write_key_file(&key_pair);
+        let key_manager = KeySource::File(path.clone()).build().unwrap();
+        // 起動時に読み込んでいるため、鍵ファイルがなくなっても署名できる
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            key_manager.get_current_key().unwrap().public_key,
+            signing_key.public_key()
+        );
+        assert!(key_manager.get_signer("key-1").is_ok());
+
+        // 鍵ファイルがない場合、壊れている場合、現在の署名鍵がない場合は起動時にエラーにする
+        assert!(KeySource::File(path).build().is_err());
+        let invalid =
+            write_key_file(&serde_json::json!({"public_key": "invalid", "private_key": "invalid"}));
+        let result = KeySource::File(invalid.clone()).build();
+        fs::remove_file(&invalid).unwrap();
+        assert!(result.is_err());
+        let mut retired = key_pair.clone();
+        retired["kid"] = "key-1".into();
+        retired["status"] = "retired".into();
+        let retired = write_key_file(&serde_json::json!({ "keys": [retired] }));
+        let result = KeySource::File(retired.clone()).build();
+        fs::remove_file(&retired).unwrap();
+        assert!(
+            matches!(result, Err(UtilsError::KeyStoreError(e)) if e.contains("No active signing key"))
+        );
+
+        let encrypted = write_key_file(&key_pair);
+        let result =
+            KeySource::EncryptedFile(encrypted.clone(), Zeroizing::new("passphrase".to_string()))
+                .build();
+        fs::remove_file(&encrypted).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_issuer_config() {
+        // 未設定の場合は did:key と既定の cryptosuite で発行する
+        let config = IssuerConfig::new(None, None).unwrap();
+        assert!(config.did.is_none());
+        assert_eq!(config.cryptosuite, Cryptosuite::EddsaJcs2022);
+        let config = IssuerConfig::new(None, Some("")).unwrap();
+        assert_eq!(config.cryptosuite, Cryptosuite::EddsaJcs2022);
+
+        let config = IssuerConfig::new(
+            Some("did:web:issuer.example.com".to_string()),
+            Some("eddsa-rdfc-2022"),
+        )
+        .unwrap();
+        assert_eq!(config.did.as_deref(), Some("did:web:issuer.example.com"));
+        assert_eq!(config.cryptosuite, Cryptosuite::EddsaRdfc2022);
+
+        // did:web 以外の DID と未対応の cryptosuite は起動時にエラーにする
+        let did_key = "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+        assert!(matches!(
+            IssuerConfig::new(Some(did_key.to_string()), None),
+            Err(UtilsError::InvalidKey(_))
+        ));
+        assert!(IssuerConfig::new(None, Some("ecdsa-rdfc-2019")).is_err());
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let signing_key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let path = write_key_file(&key_pair(&signing_key));
+        let key_manager = FileKeyManager::new(path.clone());
+
+        // 新しい鍵を現在の署名鍵にし、それまでの鍵は退役させる（退役した鍵の期間はローテーションした時点で終わる）
+        let rotated = key_manager
+            .rotate_key(Some(SignatureAlgorithm::ES256), false)
+            .unwrap();
+        assert_eq!(rotated.kid, "key-2");
+        assert_eq!(rotated.public_key.algorithm(), SignatureAlgorithm::ES256);
+        let current_key = key_manager.get_current_key().unwrap();
+        assert_eq!(current_key.kid, "key-2");
+        assert_signs(&key_manager, &current_key);
+        let keys = key_manager.get_keys().unwrap();
+        let statuses: Vec<_> = keys.iter().map(|key| key.status).collect();
+        assert_eq!(statuses, vec![KeyStatus::Retired, KeyStatus::Active]);
+        assert_eq!(keys[0].public_key, signing_key.public_key());
+        assert!(keys[0].not_after.is_some());
+        assert!(!keys[0].can_sign_at(Utc::now()));
+
+        // 失効させた鍵では署名できない（algorithm を省略した場合は現在の鍵と同じアルゴリズム）
+        let rotated = key_manager.rotate_key(None, true).unwrap();
+        assert_eq!(rotated.kid, "key-3");
+        assert_eq!(rotated.public_key.algorithm(), SignatureAlgorithm::ES256);
+        let result = key_manager.get_signer("key-2");
+        assert!(matches!(result, Err(e) if e.contains("revoked")));
+        let statuses: Vec<_> = key_manager
+            .get_keys()
+            .unwrap()
+            .iter()
+            .map(|key| key.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![KeyStatus::Retired, KeyStatus::Revoked, KeyStatus::Active]
+        );
+
+        // ローテーションした鍵は鍵ファイルに保存される
+        let reloaded = FileKeyManager::new(path.clone());
+        assert_eq!(reloaded.get_current_key().unwrap().kid, "key-3");
+        assert_eq!(reloaded.get_keys().unwrap().len(), 3);
+
+        // 他のプロセスが鍵ファイルを更新しても、読み直すまでは読み込んだ鍵を使う
+        reloaded.rotate_key(None, false).unwrap();
+        assert_eq!(key_manager.get_current_key().unwrap().kid, "key-3");
+        key_manager.reload().unwrap();
+        fs::remove_file(&path).unwrap();
+        let current_key = key_manager.get_current_key().unwrap();
+        assert_eq!(current_key.kid, "key-4");
+        assert_signs(&key_manager, &current_key);
+    }
+
+    #[test]
+    fn test_migrate_to_encrypted_key_store() {
+        let signing_key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let key_pair = key_pair(&signing_key);
+        let private_key = key_pair["private_key"].as_str().unwrap().to_string();
+        let directory = std::env::temp_dir().join(format!("keys-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&directory).unwrap();
+        let plaintext_path = directory.join("keys.json").to_string_lossy().to_string();
+        let encrypted_path = directory
+            .join("keys.enc.json")
+            .to_string_lossy()
+            .to_string();
+        fs::write(&plaintext_path, key_pair.to_string()).unwrap();
+        let passphrase = || Zeroizing::new("correct horse".to_string());
+
+        // パスフレーズがなければ移行しない
+        let result = migrate_key_file(
+            &plaintext_path,
+            &encrypted_path,
+            Zeroizing::new(String::new()),
+            KdfParams::for_tests(),
+        );
+        assert!(result.is_err());
+        assert!(Path::new(&plaintext_path).exists());
+
+        migrate_key_file(
+            &plaintext_path,
+            &encrypted_path,
+            passphrase(),
+            KdfParams::for_tests(),
+        )
+        .unwrap();
+        assert!(!Path::new(&plaintext_path).exists());
+        let contents = fs::read_to_string(&encrypted_path).unwrap();
+        assert!(key_store::is_encrypted(contents.as_bytes()));
+        assert!(!contents.contains(&private_key));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&encrypted_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // パスフレーズがないか誤っていれば鍵を読み込めない
+        assert!(FileKeyManager::new(encrypted_path.clone())
+            .get_current_key()
+            .is_err());
+        let wrong_passphrase = Zeroizing::new("battery staple".to_string());
+        assert!(
+            FileKeyManager::encrypted(encrypted_path.clone(), wrong_passphrase)
+                .get_current_key()
+                .is_err()
+        );
+
+        // 暗号化した鍵ファイルの鍵で署名し、ローテーションした鍵も暗号化して保存する
+        let key_manager = FileKeyManager::encrypted(encrypted_path.clone(), passphrase());
+        let key = key_manager.get_current_key().unwrap();
+        assert_eq!(key.kid, "key-1");
+        assert_eq!(key.public_key, signing_key.public_key());
+        assert_signs(&key_manager, &key);
+
+        let rotated = key_manager.rotate_key(None, false).unwrap();
+        assert_eq!(rotated.kid, "key-2");
+        let contents = fs::read_to_string(&encrypted_path).unwrap();
+        assert!(key_store::is_encrypted(contents.as_bytes()));
+        assert!(!contents.contains(&private_key));
+        let key_manager = FileKeyManager::encrypted(encrypted_path.clone(), passphrase());
+        assert_eq!(key_manager.get_keys().unwrap().len(), 2);
+        assert_eq!(key_manager.get_current_key().unwrap().kid, "key-2");
+
+        // 既存の暗号化した鍵ファイルは上書きしない
+        fs::write(&plaintext_path, key_pair.to_string()).unwrap();
+        let result = migrate_key_file(
+            &plaintext_path,
+            &encrypted_path,
+            passphrase(),
+            KdfParams::for_tests(),
+        );
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_or_create_signing_key() {
+        let path = std::env::temp_dir()
+            .join(format!("holder-{}.json", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
+
+        // 鍵ファイルがなければ生成し、次からは同じ鍵を読み込む
+        let generated = load_or_create_signing_key(&path).unwrap();
+        assert_eq!(generated.algorithm(), SignatureAlgorithm::EdDSA);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let loaded = load_or_create_signing_key(&path).unwrap();
+        assert_eq!(
+            loaded.to_bytes().unwrap().as_slice(),
+            generated.to_bytes().unwrap().as_slice()
+        );
+
+        // 公開鍵が秘密鍵と一致しない鍵ファイルは読み込まない
+        let mut contents = key_pair(&SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap());
+        contents["public_key"] = key_pair(&generated)["public_key"].clone();
+        fs::write(&path, contents.to_string()).unwrap();
+        assert!(load_or_create_signing_key(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_key_manager_caches_keys() {
+        let signing_key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let key_pair = key_pair(&signing_key);
+        let path = write_key_file(&key_pair);
+        let key_manager = FileKeyManager::new(path.clone());
+
+        // 鍵ファイルは最初に使うときに一度だけ読み込む
+        let key = key_manager.get_current_key().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(key.kid, "key-1");
+        assert_eq!(key.public_key, signing_key.public_key());
+        assert_signs(&key_manager, &key);
+
+        // ローテーションすると読み込んだ鍵も更新する
+        fs::write(&path, key_pair.to_string()).unwrap();
+        let rotated = key_manager
+            .rotate_key(Some(SignatureAlgorithm::ES256), true)
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(key_manager.get_current_key().unwrap().kid, rotated.kid);
+        let signer = key_manager.get_signer(&rotated.kid).unwrap();
+        assert_eq!(signer.algorithm(), SignatureAlgorithm::ES256);
+        let result = key_manager.get_signer("key-1");
+        assert!(matches!(result, Err(e) if e.contains("revoked")));
+        let keys = key_manager.get_keys().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].status, KeyStatus::Revoked);
+    }
+
+    #[test]
+    fn test_file_key_manager_algorithms() {
+        let key = p256::ecdsa::SigningKey::random(&mut rand_core::OsRng);
+        let path = write_key_file(&serde_json::json!({
+            "algorithm": "ES256",
+            "public_key": bs58::encode(key.verifying_key().to_encoded_point(true).as_bytes()).into_string(),
+            "private_key": bs58::encode(key.to_bytes()).into_string(),
+        }));
+
+        let key_manager = FileKeyManager::new(path.clone());
+        let current_key = key_manager.get_current_key();
+        let signer = key_manager.get_signer("key-1");
+        fs::remove_file(&path).unwrap();
+
+        let signer = signer.unwrap();
+        assert_eq!(signer.algorithm(), SignatureAlgorithm::ES256);
+        let current_key = current_key.unwrap();
+        assert_eq!(current_key.kid, "key-1");
+        assert_eq!(current_key.public_key, SigningKey::P256(key).public_key());
+
+        // algorithm を省略した鍵ファイルは Ed25519 として読み込む
+        let current_key = FileKeyManager::new(KEY_FILE.to_string()).get_current_key();
+        assert!(matches!(
+            current_key,
+            Ok(ManagedKey {
+                public_key: VerifyingKey::Ed25519(_),
+                ..
+            })
+        ));
     }
 }
@@ -0,0 +1,281 @@
+use crate::utils::error::UtilsError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// 暗号化した鍵ファイルの形式：パスフレーズから Argon2id で導出した鍵で、鍵ファイルの内容を XChaCha20-Poly1305 で暗号化する
+const KEY_STORE_VERSION: u32 = 1;
+const KDF_NAME: &str = "argon2id";
+const CIPHER_NAME: &str = "xchacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+// 鍵ファイルに書かれた KDF のパラメータの上限（細工した鍵ファイルで起動時にメモリや CPU を使い果たさないように）
+const MAX_MEMORY_COST: u32 = 1024 * 1024; // KiB（1 GiB）
+const MAX_TIME_COST: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct KdfParams {
+    name: String,
+    salt: String,
+    memory_cost: u32,
+    time_cost: u32,
+    parallelism: u32,
+}
+
+impl KdfParams {
+    // Argon2 の既定のパラメータ（19 MiB、2 回、並列度 1）と新しいソルト
+    pub fn generate() -> Self {
+        let params = Params::default();
+        KdfParams::with_costs(params.m_cost(), params.t_cost(), params.p_cost())
+    }
+
+    fn with_costs(memory_cost: u32, time_cost: u32, parallelism: u32) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        KdfParams {
+            name: KDF_NAME.to_string(),
+            salt: STANDARD.encode(salt),
+            memory_cost,
+            time_cost,
+            parallelism,
+        }
+    }
+
+    // テストで鍵の導出に時間がかからないようにするためのもの
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        KdfParams::with_costs(Params::MIN_M_COST, Params::MIN_T_COST, Params::MIN_P_COST)
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<Zeroizing<[u8; KEY_LEN]>, UtilsError> {
+        if self.name != KDF_NAME {
+            return Err(UtilsError::KeyStoreError(format!(
+                "Unsupported KDF: {}",
+                self.name
+            )));
+        }
+        if passphrase.is_empty() {
+            return Err(UtilsError::KeyStoreError(
+                "Passphrase is not set".to_string(),
+            ));
+        }
+
+        if self.memory_cost > MAX_MEMORY_COST
+            || self.time_cost > MAX_TIME_COST
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(UtilsError::KeyStoreError(format!(
+                "KDF parameters exceed the limits: memory_cost {} (max {}), time_cost {} (max {}), parallelism {} (max {})",
+                self.memory_cost,
+                MAX_MEMORY_COST,
+                self.time_cost,
+                MAX_TIME_COST,
+                self.parallelism,
+                MAX_PARALLELISM
+            )));
+        }
+
+        let salt = STANDARD
+            .decode(&self.salt)
+            .map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+        let params = Params::new(
+            self.memory_cost,
+            self.time_cost,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+// パスフレーズから導出した鍵（導出に時間がかかるため、同じソルトの間は使い回す）
+#[derive(Clone)]
+pub struct DerivedKey {
+    kdf: KdfParams,
+    key: Zeroizing<[u8; KEY_LEN]>,
+}
+
+impl DerivedKey {
+    pub fn derive(passphrase: &str, kdf: KdfParams) -> Result<Self, UtilsError> {
+        let key = kdf.derive_key(passphrase)?;
+        Ok(DerivedKey { kdf, key })
+    }
+
+    pub fn kdf(&self) -> &KdfParams {
+        &self.kdf
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct EncryptedKeyStore {
+    version: u32,
+    kdf: KdfParams,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedKeyStore {
+    // バージョンと KDF のパラメータを書き換えられないよう、AEAD の関連データに含める
+    fn associated_data(&self) -> Result<Vec<u8>, UtilsError> {
+        serde_json::to_vec(&(self.version, &self.kdf, &self.cipher))
+            .map_err(|e| UtilsError::JsonSerializationError(e.to_string()))
+    }
+}
+
+// 暗号化した鍵ファイルか（鍵ファイルの形式を判別するため）
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    serde_json::from_slice::<EncryptedKeyStore>(contents).is_ok()
+}
+
+// 暗号化した鍵ファイルの KDF のパラメータ（鍵ファイルを書き換えるときに同じソルトを使い続けるため）
+pub fn kdf_params(contents: &[u8]) -> Result<KdfParams, UtilsError> {
+    let store: EncryptedKeyStore =
+        serde_json::from_slice(contents).map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+    Ok(store.kdf)
+}
+
+pub fn encrypt(plaintext: &[u8], key: &DerivedKey) -> Result<Vec<u8>, UtilsError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut store = EncryptedKeyStore {
+        version: KEY_STORE_VERSION,
+        kdf: key.kdf.clone(),
+        cipher: CIPHER_NAME.to_string(),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: String::new(),
+    };
+    let aad = store.associated_data()?;
+    let ciphertext = XChaCha20Poly1305::new(key.key.as_ref().into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| UtilsError::KeyStoreError("Failed to encrypt key store".to_string()))?;
+    store.ciphertext = STANDARD.encode(ciphertext);
+
+    serde_json::to_vec_pretty(&store).map_err(|e| UtilsError::JsonSerializationError(e.to_string()))
+}
+
+// key は kdf_params で得たパラメータから導出したもの
+pub fn decrypt(contents: &[u8], key: &DerivedKey) -> Result<Zeroizing<Vec<u8>>, UtilsError> {
+    let store: EncryptedKeyStore =
+        serde_json::from_slice(contents).map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+    if store.version != KEY_STORE_VERSION || store.cipher != CIPHER_NAME {
+        return Err(UtilsError::KeyStoreError(format!(
+            "Unsupported key store: version {}, cipher {}",
+            store.version, store.cipher
+        )));
+    }
+
+    let nonce = STANDARD
+        .decode(&store.nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == NONCE_LEN)
+        .ok_or_else(|| UtilsError::KeyStoreError("Invalid nonce".to_string()))?;
+    let ciphertext = STANDARD
+        .decode(&store.ciphertext)
+        .map_err(|e| UtilsError::KeyStoreError(e.to_string()))?;
+
+    if store.kdf != key.kdf {
+        return Err(UtilsError::KeyStoreError(
+            "Key was derived with different KDF parameters".to_string(),
+        ));
+    }
+    let aad = store.associated_data()?;
+    XChaCha20Poly1305::new(key.key.as_ref().into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map(Zeroizing::new)
+        // パスフレーズが誤っている場合と改ざんされている場合は区別できない
+        .map_err(|_| {
+            UtilsError::KeyStoreError(
+                "Failed to decrypt key store (wrong passphrase or corrupted file)".to_string(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_key_store() {
+        let kdf = KdfParams::for_tests();
+        let key = DerivedKey::derive("correct horse", kdf.clone()).unwrap();
+        let contents = encrypt(b"secret key material", &key).unwrap();
+        assert!(is_encrypted(&contents));
+        assert!(!is_encrypted(b"{\"keys\": []}"));
+
+        let params = kdf_params(&contents).unwrap();
+        assert_eq!(params, kdf);
+        let key = DerivedKey::derive("correct horse", params.clone()).unwrap();
+        let plaintext = decrypt(&contents, &key).unwrap();
+        assert_eq!(plaintext.as_slice(), b"secret key material");
+
+        // パスフレーズが誤っていれば復号できない
+        let wrong_key = DerivedKey::derive("battery staple", params.clone()).unwrap();
+        let result = decrypt(&contents, &wrong_key);
+        assert!(matches!(result, Err(UtilsError::KeyStoreError(_))));
+        assert!(DerivedKey::derive("", params).is_err());
+
+        // 暗号文や KDF のパラメータを書き換えたものは復号できない
+        let mut envelope: serde_json::Value = serde_json::from_slice(&contents).unwrap();
+        let mut ciphertext = STANDARD
+            .decode(envelope["ciphertext"].as_str().unwrap())
+            .unwrap();
+        ciphertext[0] ^= 1;
+        let mut tampered = envelope.clone();
+        tampered["ciphertext"] = STANDARD.encode(&ciphertext).into();
+        let result = decrypt(tampered.to_string().as_bytes(), &key);
+        assert!(matches!(result, Err(UtilsError::KeyStoreError(_))));
+
+        envelope["kdf"]["time_cost"] = 2.into();
+        let tampered = envelope.to_string();
+        let params = kdf_params(tampered.as_bytes()).unwrap();
+        let key = DerivedKey::derive("correct horse", params).unwrap();
+        let result = decrypt(tampered.as_bytes(), &key);
+        assert!(matches!(result, Err(UtilsError::KeyStoreError(_))));
+    }
+
+    #[test]
+    fn test_kdf_params_limits() {
+        let kdf = KdfParams::for_tests();
+        for (memory_cost, time_cost, parallelism) in [
+            (MAX_MEMORY_COST + 1, kdf.time_cost, kdf.parallelism),
+            (kdf.memory_cost, MAX_TIME_COST + 1, kdf.parallelism),
+            (kdf.memory_cost, kdf.time_cost, MAX_PARALLELISM + 1),
+            (u32::MAX, u32::MAX, u32::MAX),
+        ] {
+            let params = KdfParams {
+                memory_cost,
+                time_cost,
+                parallelism,
+                ..kdf.clone()
+            };
+            let result = DerivedKey::derive("correct horse", params);
+            assert!(matches!(result, Err(UtilsError::KeyStoreError(_))));
+        }
+    }
+}
@@ -1,4 +1,12 @@
 pub mod crypto;
+pub mod data_integrity;
+pub mod error;
+pub mod integrity;
+pub mod jcs;
+pub mod json_ld;
+pub mod jwk;
 pub mod key_manager;
+pub mod key_store;
+pub mod pkcs11;
 pub mod sd_jwt;
-pub mod error;
\ No newline at end of file
+pub mod signer;
@@ -0,0 +1,522 @@
+use crate::utils::error::UtilsError;
+use crate::utils::key_manager::{self, KeyManager, KeyStatus, ManagedKey};
+use crate::utils::signer::{SignatureAlgorithm, SignatureVerifier, Signer, VerifyingKey};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::{Error as Pkcs11Error, RvError};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use log::{debug, info};
+use rsa::{BigUint, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384};
+use std::sync::{Arc, Mutex};
+
+// CKA_EC_PARAMS に入る曲線の OID（DER）
+const P256_PARAMS: [u8; 10] = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const P384_PARAMS: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+// 一部のトークンは Ed25519 の曲線を OID ではなく PrintableString の曲線名で表す
+const ED25519_CURVE_NAME: &[u8] = b"edwards25519";
+
+const RSA_KEY_BITS: u64 = 2048;
+const RSA_PUBLIC_EXPONENT: [u8; 3] = [0x01, 0x00, 0x01];
+
+// PKCS#11 トークン（HSM）の鍵で署名する KeyManager
+// 秘密鍵は抽出できない（CKA_SENSITIVE / CKA_EXTRACTABLE）オブジェクトとしてトークン内で生成し、署名もトークンで行う
+// 鍵 ID は CKA_LABEL で、公開鍵と秘密鍵のオブジェクトは CKA_ID で対応付ける
+// 秘密鍵のある鍵を active、公開鍵だけが残っている鍵を退役した鍵とみなし、失効した鍵はトークンから削除する
+pub struct Pkcs11KeyManager {
+    // ログインしたセッション（同じセッションを複数のスレッドから同時に使わないようにする）
+    session: Arc<Mutex<Session>>,
+    // トークン上の鍵（署名のたびにオブジェクトを探さないよう、ローテーションするか読み直すまで使い回す）
+    // ロックは session、keys の順に取る
+    keys: Mutex<Vec<TokenKey>>,
+}
+
+// トークン上の鍵（公開鍵のオブジェクトと、あれば秘密鍵のオブジェクト）
+#[derive(Clone)]
+struct TokenKey {
+    key: ManagedKey,
+    public_key: ObjectHandle,
+    private_key: Option<ObjectHandle>,
+}
+
+impl Pkcs11KeyManager {
+    // module は PKCS#11 のライブラリ（例: /usr/lib/softhsm/libsofthsm2.so）、token_label はトークンのラベル
+    pub fn new(module: &str, token_label: &str, pin: &str) -> Result<Self, String> {
+        let pkcs11 = Pkcs11::new(module).map_err(|e| format!("{}: {}", module, e))?;
+        match pkcs11.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+
+        let mut slots = Vec::new();
+        for slot in pkcs11.get_slots_with_token().map_err(|e| e.to_string())? {
+            if pkcs11
+                .get_token_info(slot)
+                .map_err(|e| e.to_string())?
+                .label()
+                == token_label
+            {
+                slots.push(slot);
+            }
+        }
+        let slot = match slots.as_slice() {
+            [slot] => *slot,
+            [] => return Err(format!("Token not found: {}", token_label)),
+            _ => return Err(format!("Multiple tokens labelled {}", token_label)),
+        };
+
+        let session = pkcs11.open_rw_session(slot).map_err(|e| e.to_string())?;
+        match session.login(UserType::User, Some(&AuthPin::new(pin.to_string()))) {
+            Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+            Err(e) => return Err(format!("Failed to log in to {}: {}", token_label, e)),
+        }
+        info!("Using PKCS#11 token {} from {}", token_label, module);
+
+        let keys = Pkcs11KeyManager::load_keys(&session)?;
+        Ok(Pkcs11KeyManager {
+            session: Arc::new(Mutex::new(session)),
+            keys: Mutex::new(keys),
+        })
+    }
+
+    fn load_keys(session: &Session) -> Result<Vec<TokenKey>, String> {
+        let public_keys = session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::PUBLIC_KEY),
+                Attribute::Token(true),
+            ])
+            .map_err(|e| e.to_string())?;
+
+        let mut keys = Vec::new();
+        for public_key in public_keys {
+            let attributes = session
+                .get_attributes(
+                    public_key,
+                    &[
+                        AttributeType::Label,
+                        AttributeType::Id,
+                        AttributeType::KeyType,
+                        AttributeType::EcParams,
+                        AttributeType::EcPoint,
+                        AttributeType::Modulus,
+                        AttributeType::PublicExponent,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            let kid = key_id(&attributes);
+            let verifying_key = match verifying_key(&attributes) {
+                Ok(verifying_key) => verifying_key,
+                // 署名に使えない種類の鍵（暗号化用の鍵など）は無視する
+                Err(e) => {
+                    debug!("Skipping PKCS#11 key {}: {}", kid, e);
+                    continue;
+                }
+            };
+
+            let private_key = find_private_key(session, &attributes)?;
+            debug!(
+                "Loaded {} key {} from PKCS#11 token (private key: {})",
+                verifying_key.algorithm().name(),
+                kid,
+                private_key.is_some()
+            );
+            keys.push(TokenKey {
+                key: ManagedKey {
+                    kid,
+                    status: if private_key.is_some() {
+                        KeyStatus::Active
+                    } else {
+                        KeyStatus::Retired
+                    },
+                    not_before: None,
+                    not_after: None,
+                    public_key: verifying_key,
+                },
+                public_key,
+                private_key,
+            });
+        }
+
+        // ローテーションで生成した鍵（key-N）の順に並べる
+        keys.sort_by(|a, b| {
+            (key_manager::key_number(&a.key.kid), &a.key.kid)
+                .cmp(&(key_manager::key_number(&b.key.kid), &b.key.kid))
+        });
+        Ok(keys)
+    }
+
+    fn generate_key(
+        session: &Session,
+        algorithm: SignatureAlgorithm,
+        kid: &str,
+    ) -> Result<(), String> {
+        let (mechanism, mut public_template) = match algorithm {
+            SignatureAlgorithm::EdDSA => (
+                Mechanism::EccEdwardsKeyPairGen,
+                vec![Attribute::EcParams(ED25519_PARAMS.to_vec())],
+            ),
+            SignatureAlgorithm::ES256 => (
+                Mechanism::EccKeyPairGen,
+                vec![Attribute::EcParams(P256_PARAMS.to_vec())],
+            ),
+            SignatureAlgorithm::ES384 => (
+                Mechanism::EccKeyPairGen,
+                vec![Attribute::EcParams(P384_PARAMS.to_vec())],
+            ),
+            SignatureAlgorithm::RS256 => (
+                Mechanism::RsaPkcsKeyPairGen,
+                vec![
+                    Attribute::ModulusBits(RSA_KEY_BITS.into()),
+                    Attribute::PublicExponent(RSA_PUBLIC_EXPONENT.to_vec()),
+                ],
+            ),
+        };
+        public_template.extend([
+            Attribute::Token(true),
+            Attribute::Verify(true),
+            Attribute::Label(kid.as_bytes().to_vec()),
+            Attribute::Id(kid.as_bytes().to_vec()),
+        ]);
+        let private_template = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Sign(true),
+            Attribute::Label(kid.as_bytes().to_vec()),
+            Attribute::Id(kid.as_bytes().to_vec()),
+        ];
+
+        session
+            .generate_key_pair(&mechanism, &public_template, &private_template)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to generate {} key: {}", algorithm.name(), e))
+    }
+}
+
+impl KeyManager for Pkcs11KeyManager {
+    fn get_current_key(&self) -> Result<ManagedKey, String> {
+        let keys = self.keys.lock().map_err(|_| "Failed to acquire lock")?;
+        keys.iter()
+            .rev()
+            .find(|key| key.key.status == KeyStatus::Active)
+            .map(|key| key.key.clone())
+            .ok_or_else(|| "No active signing key".to_string())
+    }
+
+    fn get_keys(&self) -> Result<Vec<ManagedKey>, String> {
+        let keys = self.keys.lock().map_err(|_| "Failed to acquire lock")?;
+        Ok(keys.iter().map(|key| key.key.clone()).collect())
+    }
+
+    fn get_signer(&self, kid: &str) -> Result<Box<dyn Signer>, String> {
+        let keys = self.keys.lock().map_err(|_| "Failed to acquire lock")?;
+        let key = keys
+            .iter()
+            .find(|key| key.key.kid == kid)
+            .ok_or_else(|| format!("Unknown key: {}", kid))?;
+        let private_key = key
+            .private_key
+            .ok_or_else(|| format!("Key {} is retired", kid))?;
+
+        Ok(Box::new(Pkcs11Signer {
+            session: self.session.clone(),
+            private_key,
+            algorithm: key.key.public_key.algorithm(),
+        }))
+    }
+
+    // トークンに鍵がなければ、最初の鍵（省略時は Ed25519）を生成する
+    // 他のツールによる変更も反映するよう、トークンの鍵を読み直してからローテーションする
+    // 新しい鍵を生成してから、それまでの active な鍵の秘密鍵を削除して退役させる（revoke_current の場合は現在の鍵を公開鍵ごと削除する）
+    fn rotate_key(
+        &self,
+        algorithm: Option<SignatureAlgorithm>,
+        revoke_current: bool,
+    ) -> Result<ManagedKey, String> {
+        let session = self.session.lock().map_err(|_| "Failed to acquire lock")?;
+        let keys = Pkcs11KeyManager::load_keys(&session)?;
+        let active: Vec<&TokenKey> = keys
+            .iter()
+            .filter(|key| key.key.status == KeyStatus::Active)
+            .collect();
+        let current = active.last().map(|key| key.key.kid.clone());
+        let algorithm = algorithm
+            .or_else(|| active.last().map(|key| key.key.public_key.algorithm()))
+            .unwrap_or(SignatureAlgorithm::EdDSA);
+
+        let kid = key_manager::next_key_id(keys.iter().map(|key| key.key.kid.as_str()));
+        Pkcs11KeyManager::generate_key(&session, algorithm, &kid)?;
+
+        // 削除に失敗しても残りの鍵の削除は続け、トークンに残ったオブジェクトをまとめて報告する
+        // （退役の状態は秘密鍵の有無で決まるため、失敗した鍵は active のまま残る）
+        let mut failures = Vec::new();
+        for key in active {
+            let private_key = key.private_key.expect("active keys have a private key");
+            if let Err(e) = session.destroy_object(private_key) {
+                failures.push(format!(
+                    "{} (private key handle {}): {}",
+                    key.key.kid, private_key, e
+                ));
+            }
+            if revoke_current && Some(&key.key.kid) == current.as_ref() {
+                if let Err(e) = session.destroy_object(key.public_key) {
+                    failures.push(format!(
+                        "{} (public key handle {}): {}",
+                        key.key.kid, key.public_key, e
+                    ));
+                }
+            }
+        }
+
+        // 削除に失敗した場合も、生成した鍵を使えるようトークンの鍵を読み直す
+        let keys = Pkcs11KeyManager::load_keys(&session)?;
+        let key = keys
+            .iter()
+            .find(|key| key.key.kid == kid)
+            .map(|key| key.key.clone());
+        *self.keys.lock().map_err(|_| "Failed to acquire lock")? = keys;
+        if !failures.is_empty() {
+            return Err(format!(
+                "Generated key {} but failed to remove old keys, which remain on the token: {}",
+                kid,
+                failures.join(", ")
+            ));
+        }
+        info!(
+            "Rotated PKCS#11 signing key {} to {} ({})",
+            current.as_deref().unwrap_or("-"),
+            kid,
+            algorithm.name()
+        );
+
+        key.ok_or_else(|| format!("Generated key {} not found", kid))
+    }
+
+    fn reload(&self) -> Result<(), String> {
+        let session = self.session.lock().map_err(|_| "Failed to acquire lock")?;
+        let keys = Pkcs11KeyManager::load_keys(&session)?;
+        info!("Reloaded {} keys from PKCS#11 token", keys.len());
+        *self.keys.lock().map_err(|_| "Failed to acquire lock")? = keys;
+        Ok(())
+    }
+}
+
+// トークン内の秘密鍵で署名する（ECDSA はハッシュ値をトークンに渡し、r || s の署名を受け取る）
+struct Pkcs11Signer {
+    session: Arc<Mutex<Session>>,
+    private_key: ObjectHandle,
+    algorithm: SignatureAlgorithm,
+}
+
+impl Signer for Pkcs11Signer {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, UtilsError> {
+        let (mechanism, data) = match self.algorithm {
+            SignatureAlgorithm::EdDSA => (Mechanism::Eddsa, message.to_vec()),
+            SignatureAlgorithm::ES256 => (Mechanism::Ecdsa, Sha256::digest(message).to_vec()),
+            SignatureAlgorithm::ES384 => (Mechanism::Ecdsa, Sha384::digest(message).to_vec()),
+            SignatureAlgorithm::RS256 => (Mechanism::Sha256RsaPkcs, message.to_vec()),
+        };
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| UtilsError::SignatureError("Failed to acquire lock".to_string()))?;
+        session
+            .sign(&mechanism, self.private_key, &data)
+            .map_err(|e| UtilsError::SignatureError(e.to_string()))
+    }
+}
+
+// CKA_LABEL（なければ CKA_ID の16進数）
+fn key_id(attributes: &[Attribute]) -> String {
+    for attribute in attributes {
+        if let Attribute::Label(label) = attribute {
+            if !label.is_empty() {
+                return String::from_utf8_lossy(label).to_string();
+            }
+        }
+    }
+    object_id(attributes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn object_id(attributes: &[Attribute]) -> &[u8] {
+    attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Id(id) => Some(id.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// 公開鍵と同じ CKA_ID（CKA_ID がなければ CKA_LABEL）の、署名に使える秘密鍵
+fn find_private_key(
+    session: &Session,
+    attributes: &[Attribute],
+) -> Result<Option<ObjectHandle>, String> {
+    let mut template = vec![
+        Attribute::Class(ObjectClass::PRIVATE_KEY),
+        Attribute::Token(true),
+        Attribute::Sign(true),
+    ];
+    let id = object_id(attributes);
+    if id.is_empty() {
+        template.push(Attribute::Label(key_id(attributes).into_bytes()));
+    } else {
+        template.push(Attribute::Id(id.to_vec()));
+    }
+
+    let private_keys = session.find_objects(&template).map_err(|e| e.to_string())?;
+    match private_keys.as_slice() {
+        [] => Ok(None),
+        [private_key] => Ok(Some(*private_key)),
+        _ => Err(format!("Multiple private keys for {}", key_id(attributes))),
+    }
+}
+
+fn verifying_key(attributes: &[Attribute]) -> Result<VerifyingKey, UtilsError> {
+    let mut key_type = None;
+    let (mut ec_params, mut ec_point, mut modulus, mut exponent) = (None, None, None, None);
+    for attribute in attributes {
+        match attribute {
+            Attribute::KeyType(value) => key_type = Some(*value),
+            Attribute::EcParams(value) => ec_params = Some(value.as_slice()),
+            Attribute::EcPoint(value) => ec_point = Some(value.as_slice()),
+            Attribute::Modulus(value) => modulus = Some(value.as_slice()),
+            Attribute::PublicExponent(value) => exponent = Some(value.as_slice()),
+            _ => {}
+        }
+    }
+    let missing = |name: &str| UtilsError::InvalidKey(format!("Missing {}", name));
+
+    match key_type.ok_or_else(|| missing("CKA_KEY_TYPE"))? {
+        KeyType::EC => {
+            let algorithm = match ec_params.ok_or_else(|| missing("CKA_EC_PARAMS"))? {
+                params if params == P256_PARAMS => SignatureAlgorithm::ES256,
+                params if params == P384_PARAMS => SignatureAlgorithm::ES384,
+                _ => return Err(UtilsError::UnsupportedAlgorithm("EC curve".to_string())),
+            };
+            ec_public_key(algorithm, ec_point.ok_or_else(|| missing("CKA_EC_POINT"))?)
+        }
+        KeyType::EC_EDWARDS => {
+            let params = ec_params.ok_or_else(|| missing("CKA_EC_PARAMS"))?;
+            if params != ED25519_PARAMS && !params.ends_with(ED25519_CURVE_NAME) {
+                return Err(UtilsError::UnsupportedAlgorithm(
+                    "Edwards curve".to_string(),
+                ));
+            }
+            ec_public_key(
+                SignatureAlgorithm::EdDSA,
+                ec_point.ok_or_else(|| missing("CKA_EC_POINT"))?,
+            )
+        }
+        KeyType::RSA => {
+            let modulus = modulus.ok_or_else(|| missing("CKA_MODULUS"))?;
+            let exponent = exponent.ok_or_else(|| missing("CKA_PUBLIC_EXPONENT"))?;
+            RsaPublicKey::new(
+                BigUint::from_bytes_be(modulus),
+                BigUint::from_bytes_be(exponent),
+            )
+            .map(VerifyingKey::Rsa)
+            .map_err(|e| UtilsError::InvalidKey(e.to_string()))
+        }
+        key_type => Err(UtilsError::UnsupportedAlgorithm(key_type.to_string())),
+    }
+}
+
+// CKA_EC_POINT は DER の OCTET STRING で包まれていることが多いが、そのままの点を返すトークンもある
+fn ec_public_key(algorithm: SignatureAlgorithm, point: &[u8]) -> Result<VerifyingKey, UtilsError> {
+    if let [0x04, len, rest @ ..] = point {
+        if *len as usize == rest.len() {
+            if let Ok(key) = VerifyingKey::from_bytes(algorithm, rest) {
+                return Ok(key);
+            }
+        }
+    }
+    VerifyingKey::from_bytes(algorithm, point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto;
+    use crate::utils::data_integrity::ProofOptions;
+    use crate::utils::signer::SignatureVerifier;
+
+    // SoftHSM などの PKCS#11 トークンが必要なため、明示的に指定した場合だけ実行する
+    // softhsm2-util --init-token --free --label vc-test --pin 1234 --so-pin 5678
+    // PKCS11_TEST_MODULE=/usr/lib/softhsm/libsofthsm2.so PKCS11_TEST_TOKEN=vc-test PKCS11_TEST_PIN=1234 cargo test pkcs11 -- --ignored
+    #[test]
+    #[ignore = "requires a PKCS#11 token (set PKCS11_TEST_MODULE, PKCS11_TEST_TOKEN and PKCS11_TEST_PIN)"]
+    fn test_pkcs11_key_manager() {
+        let env =
+            |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name));
+        let key_manager = Pkcs11KeyManager::new(
+            &env("PKCS11_TEST_MODULE"),
+            &env("PKCS11_TEST_TOKEN"),
+            &env("PKCS11_TEST_PIN"),
+        )
+        .unwrap();
+        let document = serde_json::json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2024-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject"}
+        });
+
+        let mut previous: Option<ManagedKey> = None;
+        for algorithm in SignatureAlgorithm::ALL {
+            let key = key_manager.rotate_key(Some(algorithm), false).unwrap();
+            assert_eq!(key.public_key.algorithm(), algorithm);
+            assert_eq!(key_manager.get_current_key().unwrap().kid, key.kid);
+
+            let signer = key_manager.get_signer(&key.kid).unwrap();
+            assert_eq!(signer.algorithm(), algorithm);
+            let signature = signer.sign(b"message").unwrap();
+            assert!(key.public_key.verify(b"message", &signature).is_ok());
+            let proof = crypto::create_proof(
+                &document,
+                signer.as_ref(),
+                "did:example:issuer#key-1",
+                &ProofOptions::default(),
+            )
+            .unwrap();
+            let result =
+                crypto::verify_vc(&document, &proof, &key.public_key, &SignatureAlgorithm::ALL);
+            assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+            // 退役した鍵は公開鍵だけが残り、署名には使えない
+            if let Some(previous) = previous {
+                let keys = key_manager.get_keys().unwrap();
+                let retired = keys.iter().find(|key| key.kid == previous.kid).unwrap();
+                assert_eq!(retired.status, KeyStatus::Retired);
+                assert!(key_manager.get_signer(&previous.kid).is_err());
+            }
+            previous = Some(key);
+        }
+
+        // 失効させた鍵はトークンから削除される
+        let revoked = previous.unwrap();
+        let key = key_manager.rotate_key(None, true).unwrap();
+        assert_eq!(key.public_key.algorithm(), SignatureAlgorithm::RS256);
+        let kids: Vec<String> = key_manager
+            .get_keys()
+            .unwrap()
+            .into_iter()
+            .map(|key| key.kid)
+            .collect();
+        assert!(!kids.contains(&revoked.kid));
+        assert!(kids.contains(&key.kid));
+    }
+}
@@ -1,25 +1,327 @@
+use crate::utils::crypto;
+use crate::utils::error::UtilsError;
+use crate::utils::signer::Signer;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use sha2::{Sha256, Digest};
-use serde_json::Value;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::Sha3_256;
+use std::collections::HashMap;
 
-pub fn create_salt(input: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(input);
-    let result = hasher.finalize();
-    URL_SAFE_NO_PAD.encode(&result[..16]) // 最初の16バイトを使用
+// SD-JWT の Combined Format における区切り文字
+pub const SEPARATOR: char = '~';
+
+// SD-JWT / SD-JWT VC の仕様上、選択的開示にしてはならないクレーム
+pub const NON_SELECTIVELY_DISCLOSABLE_CLAIMS: [&str; 9] = [
+    "iss",
+    "nbf",
+    "exp",
+    "cnf",
+    "vct",
+    "vct#integrity",
+    "status",
+    "_sd",
+    "_sd_alg",
+];
+
+// Issuer が設定するその他のクレーム（sub は credentialSubject.id から設定する）
+pub const ISSUER_SET_CLAIMS: [&str; 2] = ["iat", "sub"];
+
+// SD-JWT VC の typ（vc+sd-jwt は旧仕様との互換のため検証時のみ受け入れる）
+pub const SD_JWT_VC_TYPE: &str = "dc+sd-jwt";
+pub const LEGACY_SD_JWT_VC_TYPE: &str = "vc+sd-jwt";
+
+pub const KEY_BINDING_JWT_TYPE: &str = "kb+jwt";
+
+// 配列要素の Disclosure を参照するためのキー
+pub const ARRAY_ELEMENT_DIGEST_KEY: &str = "...";
+
+// ソルトは 128 ビット以上の乱数とする
+pub const SALT_LENGTH: usize = 16;
+
+// _sd_alg に指定できるハッシュアルゴリズム（名前は IANA Named Information Hash Algorithm Registry に従う）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SdAlg {
+    #[default]
+    #[serde(rename = "sha-256")]
+    Sha256,
+    #[serde(rename = "sha-384")]
+    Sha384,
+    #[serde(rename = "sha-512")]
+    Sha512,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+}
+
+impl SdAlg {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SdAlg::Sha256 => "sha-256",
+            SdAlg::Sha384 => "sha-384",
+            SdAlg::Sha512 => "sha-512",
+            SdAlg::Sha3_256 => "sha3-256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, UtilsError> {
+        match name {
+            "sha-256" => Ok(SdAlg::Sha256),
+            "sha-384" => Ok(SdAlg::Sha384),
+            "sha-512" => Ok(SdAlg::Sha512),
+            "sha3-256" => Ok(SdAlg::Sha3_256),
+            _ => Err(UtilsError::UnsupportedSdAlg(name.to_string())),
+        }
+    }
+
+    // _sd_alg がない場合は sha-256 とみなす
+    pub fn from_payload(payload: &Value) -> Result<Self, UtilsError> {
+        match payload.get("_sd_alg") {
+            None => Ok(SdAlg::default()),
+            Some(Value::String(name)) => SdAlg::from_name(name),
+            Some(other) => Err(UtilsError::UnsupportedSdAlg(other.to_string())),
+        }
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            SdAlg::Sha256 => Sha256::digest(data).to_vec(),
+            SdAlg::Sha384 => Sha384::digest(data).to_vec(),
+            SdAlg::Sha512 => Sha512::digest(data).to_vec(),
+            SdAlg::Sha3_256 => Sha3_256::digest(data).to_vec(),
+        }
+    }
+}
+
+pub trait SaltSource: Send + Sync {
+    fn generate(&self) -> String;
 }
 
+// OS の CSPRNG からソルトを生成する
+pub struct OsRngSaltSource;
+
+impl SaltSource for OsRngSaltSource {
+    fn generate(&self) -> String {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        URL_SAFE_NO_PAD.encode(salt)
+    }
+}
+
+// Disclosure は [salt, claim_name, claim_value] の JSON 配列を base64url エンコードしたもの
 pub fn create_disclosure(salt: &str, claim_name: &str, claim_value: &Value) -> String {
-    let value_str = match claim_value {
-        Value::String(s) => s.clone(),
-        _ => claim_value.to_string().trim_matches('"').to_string(),
-    };
-    format!("{}.{}.{}", salt, claim_name, value_str)
-}
-
-pub fn hash_disclosure(disclosure: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(disclosure);
-    let result = hasher.finalize();
-    URL_SAFE_NO_PAD.encode(result)
-}
\ No newline at end of file
+    let disclosure = json!([salt, claim_name, claim_value]);
+    URL_SAFE_NO_PAD.encode(disclosure.to_string())
+}
+
+// 配列要素の Disclosure はクレーム名を持たない [salt, value]
+pub fn create_array_element_disclosure(salt: &str, value: &Value) -> String {
+    let disclosure = json!([salt, value]);
+    URL_SAFE_NO_PAD.encode(disclosure.to_string())
+}
+
+// ダイジェストはエンコード済みの Disclosure 文字列（ASCII）に対して計算する
+pub fn hash_disclosure(alg: SdAlg, disclosure: &str) -> String {
+    URL_SAFE_NO_PAD.encode(alg.digest(disclosure.as_bytes()))
+}
+
+// クレームのパスは "degree.name" のようにドット区切りで表し、
+// 配列の各要素は "nationalities[]" のように [] を付けて表す
+pub fn child_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+pub fn element_path(parent: &str) -> String {
+    format!("{}[]", parent)
+}
+
+// ancestor の配下（ancestor 自身は含まない）に path があるか
+pub fn is_descendant_path(ancestor: &str, path: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .map(|rest| rest.starts_with('.') || rest.starts_with("[]"))
+        .unwrap_or(false)
+}
+
+pub fn decode_disclosure(disclosure: &str) -> Result<Vec<Value>, UtilsError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(disclosure)
+        .map_err(|_| UtilsError::InvalidDisclosure("Invalid base64 encoding".to_string()))?;
+    match serde_json::from_slice(&bytes) {
+        Ok(Value::Array(elements)) if elements.len() == 2 || elements.len() == 3 => Ok(elements),
+        _ => Err(UtilsError::InvalidDisclosure(
+            "Disclosure must be a JSON array of two or three elements".to_string(),
+        )),
+    }
+}
+
+// 各 Disclosure が SD-JWT のどのクレームパスに対応するかを求める
+// 参照されていない Disclosure は結果に含まれない
+pub fn disclosure_paths(
+    payload: &Value,
+    disclosures: &[&str],
+) -> Result<Vec<(String, String)>, UtilsError> {
+    let alg = SdAlg::from_payload(payload)?;
+    let by_digest: HashMap<String, &str> = disclosures
+        .iter()
+        .map(|disclosure| (hash_disclosure(alg, disclosure), *disclosure))
+        .collect();
+
+    let mut paths = Vec::new();
+    collect_disclosure_paths(payload, "", &by_digest, &mut paths)?;
+    Ok(paths)
+}
+
+fn collect_disclosure_paths(
+    value: &Value,
+    path: &str,
+    by_digest: &HashMap<String, &str>,
+    paths: &mut Vec<(String, String)>,
+) -> Result<(), UtilsError> {
+    match value {
+        Value::Object(object) => {
+            let digests = object.get("_sd").and_then(Value::as_array);
+            for digest in digests.into_iter().flatten() {
+                // 対応する Disclosure がないダイジェストはデコイか非開示のクレーム
+                let Some(disclosure) = digest.as_str().and_then(|d| by_digest.get(d)) else {
+                    continue;
+                };
+                let decoded = decode_disclosure(disclosure)?;
+                let [_, Value::String(name), value] = decoded.as_slice() else {
+                    return Err(UtilsError::InvalidDisclosure(
+                        "Object property disclosure must have a claim name".to_string(),
+                    ));
+                };
+                let claim_path = child_path(path, name);
+                paths.push((claim_path.clone(), disclosure.to_string()));
+                collect_disclosure_paths(value, &claim_path, by_digest, paths)?;
+            }
+
+            for (key, value) in object.iter().filter(|(key, _)| *key != "_sd") {
+                collect_disclosure_paths(value, &child_path(path, key), by_digest, paths)?;
+            }
+        }
+        Value::Array(elements) => {
+            let path = element_path(path);
+            for element in elements {
+                let Some(digest) = array_element_digest(element) else {
+                    collect_disclosure_paths(element, &path, by_digest, paths)?;
+                    continue;
+                };
+                let Some(disclosure) = by_digest.get(digest) else {
+                    continue;
+                };
+                let decoded = decode_disclosure(disclosure)?;
+                let [_, value] = decoded.as_slice() else {
+                    return Err(UtilsError::InvalidDisclosure(
+                        "Array element disclosure must not have a claim name".to_string(),
+                    ));
+                };
+                paths.push((path.clone(), disclosure.to_string()));
+                collect_disclosure_paths(value, &path, by_digest, paths)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// {"...": digest} の形の配列要素であればダイジェストを返す
+pub fn array_element_digest(element: &Value) -> Option<&str> {
+    match element.as_object() {
+        Some(object) if object.len() == 1 => {
+            object.get(ARRAY_ELEMENT_DIGEST_KEY).and_then(Value::as_str)
+        }
+        _ => None,
+    }
+}
+
+// デコイは実在しない Disclosure のダイジェストと区別できないよう、乱数のソルトをハッシュする
+pub fn create_decoy_digest(alg: SdAlg, salt: &str) -> String {
+    hash_disclosure(alg, salt)
+}
+
+// <Issuer-signed JWT>~<Disclosure 1>~...~<Disclosure N>~
+pub fn combine(jwt: &str, disclosures: &[String]) -> String {
+    let mut combined = String::from(jwt);
+    combined.push(SEPARATOR);
+    for disclosure in disclosures {
+        combined.push_str(disclosure);
+        combined.push(SEPARATOR);
+    }
+    combined
+}
+
+// Combined Format を Issuer-signed JWT、Disclosure のリスト、KB-JWT に分解する
+pub fn split(sd_jwt: &str) -> (&str, Vec<&str>, Option<&str>) {
+    let mut parts: Vec<&str> = sd_jwt.split(SEPARATOR).collect();
+    let jwt = parts.remove(0);
+    // 最後の区切り文字の後ろは KB-JWT（なければ空文字列）
+    let key_binding_jwt = parts.pop().filter(|part| !part.is_empty());
+    (jwt, parts, key_binding_jwt)
+}
+
+// KB-JWT を除いた SD-JWT 部分（最後の区切り文字まで）
+pub fn strip_key_binding(sd_jwt: &str) -> &str {
+    match sd_jwt.rfind(SEPARATOR) {
+        Some(index) => &sd_jwt[..=index],
+        None => sd_jwt,
+    }
+}
+
+// KB-JWT の sd_hash は KB-JWT を除いた Combined Format 全体を _sd_alg でハッシュしたもの
+pub fn sd_hash(alg: SdAlg, sd_jwt: &str) -> String {
+    hash_disclosure(alg, strip_key_binding(sd_jwt))
+}
+
+pub fn create_key_binding_jwt(
+    sd_jwt: &str,
+    alg: SdAlg,
+    audience: &str,
+    nonce: &str,
+    issued_at: i64,
+    signer: &dyn Signer,
+) -> Result<String, UtilsError> {
+    let header = json!({
+        "typ": KEY_BINDING_JWT_TYPE,
+    });
+    let payload = json!({
+        "iat": issued_at,
+        "aud": audience,
+        "nonce": nonce,
+        "sd_hash": sd_hash(alg, sd_jwt),
+    });
+    crypto::sign_jwt(&header, &payload, signer)
+}
+
+#[cfg(test)]
+pub mod test_salt_source {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // テスト用に決定的なソルトを順番に払い出す
+    pub struct SequentialSaltSource {
+        counter: AtomicU64,
+    }
+
+    impl SequentialSaltSource {
+        pub fn new() -> Self {
+            SequentialSaltSource {
+                counter: AtomicU64::new(0),
+            }
+        }
+    }
+
+    impl SaltSource for SequentialSaltSource {
+        fn generate(&self) -> String {
+            let mut salt = [0u8; SALT_LENGTH];
+            let count = self.counter.fetch_add(1, Ordering::SeqCst);
+            salt[SALT_LENGTH - 8..].copy_from_slice(&count.to_be_bytes());
+            URL_SAFE_NO_PAD.encode(salt)
+        }
+    }
+}
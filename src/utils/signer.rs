@@ -0,0 +1,261 @@
+use crate::utils::error::UtilsError;
+use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+use p256::ecdsa::signature::{Signer as _, Verifier as _};
+use rand::rngs::OsRng;
+use rsa::pkcs1v15;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use rsa::signature::SignatureEncoding;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+// JOSE の alg として扱う署名アルゴリズム
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum SignatureAlgorithm {
+    EdDSA,
+    ES256,
+    ES384,
+    RS256,
+}
+
+impl SignatureAlgorithm {
+    pub const ALL: [SignatureAlgorithm; 4] = [
+        SignatureAlgorithm::EdDSA,
+        SignatureAlgorithm::ES256,
+        SignatureAlgorithm::ES384,
+        SignatureAlgorithm::RS256,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignatureAlgorithm::EdDSA => "EdDSA",
+            SignatureAlgorithm::ES256 => "ES256",
+            SignatureAlgorithm::ES384 => "ES384",
+            SignatureAlgorithm::RS256 => "RS256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, UtilsError> {
+        SignatureAlgorithm::ALL
+            .into_iter()
+            .find(|alg| alg.name() == name)
+            .ok_or_else(|| UtilsError::UnsupportedAlgorithm(name.to_string()))
+    }
+
+    // 許可リストにないアルゴリズムは署名の検証前に拒否する
+    pub fn ensure_allowed(&self, allowed: &[SignatureAlgorithm]) -> Result<(), UtilsError> {
+        if allowed.contains(self) {
+            Ok(())
+        } else {
+            Err(UtilsError::AlgorithmNotAllowed(self.name().to_string()))
+        }
+    }
+}
+
+pub trait Signer {
+    fn algorithm(&self) -> SignatureAlgorithm;
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, UtilsError>;
+}
+
+pub trait SignatureVerifier {
+    fn algorithm(&self) -> SignatureAlgorithm;
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), UtilsError>;
+}
+
+const RSA_KEY_SIZE: usize = 2048;
+
+// 署名鍵（ECDSA の署名は JOSE と同じ r || s の固定長、RSA は RSASSA-PKCS1-v1_5）
+pub enum SigningKey {
+    Ed25519(Keypair),
+    P256(p256::ecdsa::SigningKey),
+    P384(p384::ecdsa::SigningKey),
+    Rsa(RsaPrivateKey),
+}
+
+impl SigningKey {
+    // 鍵ファイルの形式：Ed25519 と ECDSA は秘密鍵のスカラー、RSA は PKCS#8 DER
+    pub fn from_bytes(algorithm: SignatureAlgorithm, bytes: &[u8]) -> Result<Self, UtilsError> {
+        let invalid_key = |e: String| UtilsError::InvalidKey(e);
+        match algorithm {
+            SignatureAlgorithm::EdDSA => {
+                let secret =
+                    SecretKey::from_bytes(bytes).map_err(|e| invalid_key(e.to_string()))?;
+                let public = PublicKey::from(&secret);
+                Ok(SigningKey::Ed25519(Keypair { secret, public }))
+            }
+            SignatureAlgorithm::ES256 => p256::ecdsa::SigningKey::from_slice(bytes)
+                .map(SigningKey::P256)
+                .map_err(|e| invalid_key(e.to_string())),
+            SignatureAlgorithm::ES384 => p384::ecdsa::SigningKey::from_slice(bytes)
+                .map(SigningKey::P384)
+                .map_err(|e| invalid_key(e.to_string())),
+            SignatureAlgorithm::RS256 => RsaPrivateKey::from_pkcs8_der(bytes)
+                .map(SigningKey::Rsa)
+                .map_err(|e| invalid_key(e.to_string())),
+        }
+    }
+
+    pub fn generate(algorithm: SignatureAlgorithm) -> Result<Self, UtilsError> {
+        Ok(match algorithm {
+            SignatureAlgorithm::EdDSA => SigningKey::Ed25519(Keypair::generate(&mut OsRng)),
+            SignatureAlgorithm::ES256 => {
+                SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng))
+            }
+            SignatureAlgorithm::ES384 => {
+                SigningKey::P384(p384::ecdsa::SigningKey::random(&mut rand_core::OsRng))
+            }
+            SignatureAlgorithm::RS256 => SigningKey::Rsa(
+                RsaPrivateKey::new(&mut rand_core::OsRng, RSA_KEY_SIZE)
+                    .map_err(|e| UtilsError::InvalidKey(e.to_string()))?,
+            ),
+        })
+    }
+
+    // from_bytes と同じ形式
+    // 秘密鍵のバイト列は使い終わったらゼロで上書きする
+    pub fn to_bytes(&self) -> Result<Zeroizing<Vec<u8>>, UtilsError> {
+        Ok(Zeroizing::new(match self {
+            SigningKey::Ed25519(keypair) => keypair.secret.as_bytes().to_vec(),
+            SigningKey::P256(key) => key.to_bytes().to_vec(),
+            SigningKey::P384(key) => key.to_bytes().to_vec(),
+            SigningKey::Rsa(key) => key
+                .to_pkcs8_der()
+                .map_err(|e| UtilsError::InvalidKey(e.to_string()))?
+                .as_bytes()
+                .to_vec(),
+        }))
+    }
+
+    pub fn public_key(&self) -> VerifyingKey {
+        match self {
+            SigningKey::Ed25519(keypair) => VerifyingKey::Ed25519(keypair.public),
+            SigningKey::P256(key) => VerifyingKey::P256(*key.verifying_key()),
+            SigningKey::P384(key) => VerifyingKey::P384(*key.verifying_key()),
+            SigningKey::Rsa(key) => VerifyingKey::Rsa(key.to_public_key()),
+        }
+    }
+}
+
+// 読み込んだ鍵を複製せずに共有したまま署名に使う
+impl<T: Signer + ?Sized> Signer for Arc<T> {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.as_ref().algorithm()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, UtilsError> {
+        self.as_ref().sign(message)
+    }
+}
+
+impl Signer for SigningKey {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.public_key().algorithm()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, UtilsError> {
+        match self {
+            SigningKey::Ed25519(keypair) => Ok(ed25519_dalek::Signer::sign(keypair, message)
+                .to_bytes()
+                .to_vec()),
+            SigningKey::P256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(message);
+                Ok(signature.to_bytes().to_vec())
+            }
+            SigningKey::P384(key) => {
+                let signature: p384::ecdsa::Signature = key.sign(message);
+                Ok(signature.to_bytes().to_vec())
+            }
+            SigningKey::Rsa(key) => {
+                let signing_key = pkcs1v15::SigningKey::<Sha256>::new(key.clone());
+                Ok(signing_key.sign(message).to_vec())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerifyingKey {
+    Ed25519(PublicKey),
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+    Rsa(RsaPublicKey),
+}
+
+impl VerifyingKey {
+    // 鍵ファイルの形式：Ed25519 は 32 バイト、ECDSA は SEC1、RSA は SPKI DER
+    pub fn from_bytes(algorithm: SignatureAlgorithm, bytes: &[u8]) -> Result<Self, UtilsError> {
+        let invalid_key = |e: String| UtilsError::InvalidKey(e);
+        match algorithm {
+            SignatureAlgorithm::EdDSA => PublicKey::from_bytes(bytes)
+                .map(VerifyingKey::Ed25519)
+                .map_err(|e| invalid_key(e.to_string())),
+            SignatureAlgorithm::ES256 => p256::ecdsa::VerifyingKey::from_sec1_bytes(bytes)
+                .map(VerifyingKey::P256)
+                .map_err(|e| invalid_key(e.to_string())),
+            SignatureAlgorithm::ES384 => p384::ecdsa::VerifyingKey::from_sec1_bytes(bytes)
+                .map(VerifyingKey::P384)
+                .map_err(|e| invalid_key(e.to_string())),
+            SignatureAlgorithm::RS256 => RsaPublicKey::from_public_key_der(bytes)
+                .map(VerifyingKey::Rsa)
+                .map_err(|e| invalid_key(e.to_string())),
+        }
+    }
+
+    // from_bytes と同じ形式
+    pub fn to_bytes(&self) -> Result<Vec<u8>, UtilsError> {
+        Ok(match self {
+            VerifyingKey::Ed25519(key) => key.to_bytes().to_vec(),
+            VerifyingKey::P256(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+            VerifyingKey::P384(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+            VerifyingKey::Rsa(key) => key
+                .to_public_key_der()
+                .map_err(|e| UtilsError::InvalidKey(e.to_string()))?
+                .as_bytes()
+                .to_vec(),
+        })
+    }
+}
+
+impl SignatureVerifier for VerifyingKey {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        match self {
+            VerifyingKey::Ed25519(_) => SignatureAlgorithm::EdDSA,
+            VerifyingKey::P256(_) => SignatureAlgorithm::ES256,
+            VerifyingKey::P384(_) => SignatureAlgorithm::ES384,
+            VerifyingKey::Rsa(_) => SignatureAlgorithm::RS256,
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), UtilsError> {
+        let signature_error = |e: String| UtilsError::SignatureError(e);
+        match self {
+            VerifyingKey::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_bytes(signature)
+                    .map_err(|e| signature_error(e.to_string()))?;
+                ed25519_dalek::Verifier::verify(key, message, &signature)
+                    .map_err(|e| signature_error(e.to_string()))
+            }
+            VerifyingKey::P256(key) => {
+                let signature = p256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|e| signature_error(e.to_string()))?;
+                key.verify(message, &signature)
+                    .map_err(|e| signature_error(e.to_string()))
+            }
+            VerifyingKey::P384(key) => {
+                let signature = p384::ecdsa::Signature::from_slice(signature)
+                    .map_err(|e| signature_error(e.to_string()))?;
+                key.verify(message, &signature)
+                    .map_err(|e| signature_error(e.to_string()))
+            }
+            VerifyingKey::Rsa(key) => {
+                let signature = pkcs1v15::Signature::try_from(signature)
+                    .map_err(|e| signature_error(e.to_string()))?;
+                pkcs1v15::VerifyingKey::<Sha256>::new(key.clone())
+                    .verify(message, &signature)
+                    .map_err(|e| signature_error(e.to_string()))
+            }
+        }
+    }
+}
@@ -1,29 +1,100 @@
 use actix_web::{web, HttpResponse, Responder};
 use crate::models::credential::{CredentialResponse, VerifiablePresentation};
 use crate::verifier::error::VerifierError;
-use crate::verifier::verifier;
+use crate::verifier::key_resolver::IssuerKeyResolver;
+use crate::verifier::type_metadata::TypeMetadataResolver;
+use crate::verifier::verifier::{self, VerificationOptions};
+use serde::Deserialize;
 
 // エラーメッセージを VerifierError に基づいてマッピング
 fn map_verifier_error_to_string(error: &VerifierError) -> String {
     match error {
         VerifierError::MissingProof => "Proof is missing".to_string(),
-        VerifierError::UntrustedIssuer => "Untrusted issuer".to_string(),
+        VerifierError::UnknownIssuer(_) => "Unknown issuer".to_string(),
+        VerifierError::UnknownVerificationMethod(_) => "Unknown verification method".to_string(),
+        VerifierError::InvalidIssuerKey(_) => "Invalid issuer key".to_string(),
+        VerifierError::DidResolutionFailed(_) => "DID resolution failed".to_string(),
+        VerifierError::HolderMismatch => "Presentation is not signed by its holder".to_string(),
         VerifierError::InvalidCredentialFormat => "Invalid credential format".to_string(),
+        VerifierError::InvalidDataModel(_) => "Credential does not conform to the VC data model".to_string(),
         VerifierError::InvalidBase64Encoding => "Invalid base64 encoding in payload".to_string(),
         VerifierError::InvalidJsonPayload => "Invalid JSON in payload".to_string(),
         VerifierError::MissingSdAlgClaim => "Missing _sd_alg claim in SD-JWT".to_string(),
+        VerifierError::InvalidSdJwtType(_) => "Invalid typ in SD-JWT header".to_string(),
+        VerifierError::CredentialExpired => "Credential has expired".to_string(),
+        VerifierError::CredentialNotYetValid => "Credential is not yet valid".to_string(),
+        VerifierError::PresentationExpired => "Presentation has expired".to_string(),
+        VerifierError::PresentationNotYetValid => "Presentation is not yet valid".to_string(),
+        VerifierError::InvalidIssuedAt => "Credential is issued in the future".to_string(),
+        VerifierError::UnsupportedSdAlg(_) => "Unsupported _sd_alg in SD-JWT".to_string(),
+        VerifierError::InvalidDisclosure(_) => "Invalid disclosure".to_string(),
+        VerifierError::DuplicateDisclosure => "Duplicate disclosure".to_string(),
+        VerifierError::DuplicateDigest => "Digest appears more than once in SD-JWT".to_string(),
+        VerifierError::UnreferencedDisclosure => "Disclosure is not referenced by SD-JWT".to_string(),
+        VerifierError::ClaimNameCollision(_) => "Disclosed claim collides with an existing claim".to_string(),
+        VerifierError::TypeMetadataNotFound(_) => "Type metadata for vct not found".to_string(),
+        VerifierError::InvalidTypeMetadata(_) => "Invalid type metadata".to_string(),
+        VerifierError::TypeMetadataIntegrityMismatch(_) => "Type metadata integrity mismatch".to_string(),
+        VerifierError::TypeSchemaValidationFailed(_) => "Credential does not match its type".to_string(),
+        VerifierError::MissingKeyBinding => "Key binding JWT is missing".to_string(),
+        VerifierError::InvalidKeyBinding(_) => "Invalid key binding JWT".to_string(),
+        VerifierError::MissingKeyBindingContext => "Expected aud and nonce are required for key binding".to_string(),
+        VerifierError::StaleKeyBinding => "Key binding JWT is not fresh".to_string(),
+        VerifierError::AudienceMismatch => "Audience does not match".to_string(),
+        VerifierError::NonceMismatch => "Nonce does not match".to_string(),
+        VerifierError::AlgorithmNotAllowed(_) => "Signature algorithm is not allowed".to_string(),
         VerifierError::SignatureVerificationFailed(_) => "Signature verification failed".to_string(),
         VerifierError::InternalError(_) => "Internal server error".to_string(),
         VerifierError::UtilsError(_) => "Utility error".to_string(), // UtilsError もカバー
     }
 }
 
+// Type Metadata のリゾルバが登録されていれば、SD-JWT VC を vct のタイプで検証する
+// Issuer の鍵のリゾルバが登録されていなければ、どの Issuer も信頼しない
+fn verification_options(
+    type_metadata: Option<web::Data<dyn TypeMetadataResolver>>,
+    issuer_keys: Option<web::Data<dyn IssuerKeyResolver>>,
+) -> VerificationOptions {
+    let defaults = VerificationOptions::default();
+    VerificationOptions {
+        type_metadata: type_metadata.map(|resolver| resolver.into_inner()),
+        issuer_keys: issuer_keys
+            .map(|resolver| resolver.into_inner())
+            .unwrap_or(defaults.issuer_keys.clone()),
+        ..defaults
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CredentialVerificationQuery {
+    pub aud: Option<String>,
+    pub nonce: Option<String>,
+}
+
 // 認証を検証するエンドポイント
-pub async fn verify_credential(credential: web::Json<CredentialResponse>) -> impl Responder {
-    match verifier::verify_credential(&credential) {
-        Ok(is_valid) => HttpResponse::Ok().json(serde_json::json!({
-            "verified": is_valid,
-            "errors": if is_valid { Vec::<String>::new() } else { vec!["Invalid credential".to_string()] }
+// cnf を持つ SD-JWT は、KB-JWT の aud / nonce として期待する値の指定が必要
+pub async fn verify_credential(
+    credential: web::Json<CredentialResponse>,
+    query: web::Query<CredentialVerificationQuery>,
+    type_metadata: Option<web::Data<dyn TypeMetadataResolver>>,
+    issuer_keys: Option<web::Data<dyn IssuerKeyResolver>>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let options = VerificationOptions {
+        audience: query.aud,
+        nonce: query.nonce,
+        ..verification_options(type_metadata, issuer_keys)
+    };
+    // did:web の Issuer の DID ドキュメントはブロッキングの HTTP リクエストで取得するので、web::block で検証する
+    let credential = credential.into_inner();
+    let result = web::block(move || verifier::verify_credential(&credential, &options))
+        .await
+        .unwrap_or_else(|e| Err(VerifierError::InternalError(e.to_string())));
+    match result {
+        Ok(claims) => HttpResponse::Ok().json(serde_json::json!({
+            "verified": true,
+            "errors": Vec::<String>::new(),
+            "claims": claims
         })),
         Err(e) => {
             let error_message = map_verifier_error_to_string(&e);
@@ -32,12 +103,35 @@ pub async fn verify_credential(credential: web::Json<CredentialResponse>) -> imp
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PresentationVerificationQuery {
+    pub domain: Option<String>,
+    pub challenge: Option<String>,
+}
+
 // プレゼンテーションを検証するエンドポイント
-pub async fn verify_presentation(presentation: web::Json<VerifiablePresentation>) -> impl Responder {
-    match verifier::verify_presentation(&presentation) {
-        Ok(is_valid) => HttpResponse::Ok().json(serde_json::json!({
-            "verified": is_valid,
-            "errors": if is_valid { Vec::<String>::new() } else { vec!["Invalid presentation".to_string()] }
+// VP と KB-JWT が Verifier の指定した domain / challenge に対して作成されていることを確認する（指定は必須）
+pub async fn verify_presentation(
+    presentation: web::Json<VerifiablePresentation>,
+    query: web::Query<PresentationVerificationQuery>,
+    type_metadata: Option<web::Data<dyn TypeMetadataResolver>>,
+    issuer_keys: Option<web::Data<dyn IssuerKeyResolver>>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let options = VerificationOptions {
+        audience: query.domain,
+        nonce: query.challenge,
+        ..verification_options(type_metadata, issuer_keys)
+    };
+    let presentation = presentation.into_inner();
+    let result = web::block(move || verifier::verify_presentation(&presentation, &options))
+        .await
+        .unwrap_or_else(|e| Err(VerifierError::InternalError(e.to_string())));
+    match result {
+        Ok(claims) => HttpResponse::Ok().json(serde_json::json!({
+            "verified": true,
+            "errors": Vec::<String>::new(),
+            "claims": claims
         })),
         Err(e) => {
             let error_message = map_verifier_error_to_string(&e);
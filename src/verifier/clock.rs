@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+
+// 有効期間の判定に使う現在時刻（テストでは固定した時刻に差し替える）
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+#[cfg(test)]
+pub mod test_clock {
+    use super::*;
+
+    // 常に同じ時刻を返す
+    #[derive(Debug)]
+    pub struct FixedClock(pub DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+}
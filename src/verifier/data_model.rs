@@ -0,0 +1,150 @@
+use crate::models::credential::{CredentialResponse, DataModelVersion};
+use crate::verifier::error::VerifierError;
+use chrono::{DateTime, FixedOffset, Utc};
+use log::error;
+use serde_json::Value;
+
+const VERIFIABLE_CREDENTIAL_TYPE: &str = "VerifiableCredential";
+
+// @context の最初の要素で VC Data Model のバージョンを判定し、そのバージョンの必須項目と形式を確認する
+pub fn validate_credential(
+    credential: &CredentialResponse,
+) -> Result<DataModelVersion, VerifierError> {
+    let version = credential
+        .context
+        .first()
+        .and_then(|context| DataModelVersion::from_context(context))
+        .ok_or_else(|| {
+            invalid(format!(
+                "Unsupported @context: {:?}",
+                credential.context.first()
+            ))
+        })?;
+
+    if !credential
+        .types
+        .iter()
+        .any(|t| t == VERIFIABLE_CREDENTIAL_TYPE)
+    {
+        return Err(invalid(format!(
+            "type does not include {}",
+            VERIFIABLE_CREDENTIAL_TYPE
+        )));
+    }
+    if credential.issuer.id().is_empty() {
+        return Err(invalid("issuer has no id".to_string()));
+    }
+    let has_subject = match &credential.credential_subject {
+        Value::Object(_) => true,
+        Value::Array(subjects) => !subjects.is_empty() && subjects.iter().all(Value::is_object),
+        _ => false,
+    };
+    if !has_subject {
+        return Err(invalid("Invalid credentialSubject".to_string()));
+    }
+
+    match version {
+        // 1.1 は issuanceDate が必須で、2.0 の validFrom / validUntil は定義されていない
+        DataModelVersion::V1_1 => {
+            if credential.issuance_date.is_none() {
+                return Err(invalid("Missing issuanceDate".to_string()));
+            }
+            if credential.valid_from.is_some() || credential.valid_until.is_some() {
+                return Err(invalid(
+                    "validFrom / validUntil are not defined in VCDM 1.1".to_string(),
+                ));
+            }
+        }
+        // 2.0 では issuanceDate / expirationDate の代わりに validFrom / validUntil（いずれも省略可）を使う
+        DataModelVersion::V2_0 => {
+            if credential.issuance_date.is_some() || credential.expiration_date.is_some() {
+                return Err(invalid(
+                    "issuanceDate / expirationDate are not defined in VCDM 2.0".to_string(),
+                ));
+            }
+        }
+    }
+    if let (Some(valid_from), Some(valid_until)) = validity_period(credential, version)? {
+        if valid_until < valid_from {
+            return Err(invalid(
+                "Credential expires before it becomes valid".to_string(),
+            ));
+        }
+    }
+
+    // credentialSchema は id と type、それ以外は type を持たなければならない
+    typed_objects(
+        "credentialSchema",
+        credential.credential_schema.as_ref(),
+        true,
+    )?;
+    typed_objects(
+        "credentialStatus",
+        credential.credential_status.as_ref(),
+        false,
+    )?;
+    typed_objects("evidence", credential.evidence.as_ref(), false)?;
+    typed_objects("termsOfUse", credential.terms_of_use.as_ref(), false)?;
+    typed_objects("refreshService", credential.refresh_service.as_ref(), false)?;
+
+    Ok(version)
+}
+
+// 有効期間の開始と終了（1.1 は issuanceDate / expirationDate、2.0 は validFrom / validUntil）
+pub type ValidityPeriod = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+pub fn validity_period(
+    credential: &CredentialResponse,
+    version: DataModelVersion,
+) -> Result<ValidityPeriod, VerifierError> {
+    let (from, until) = match version {
+        DataModelVersion::V1_1 => (
+            ("issuanceDate", &credential.issuance_date),
+            ("expirationDate", &credential.expiration_date),
+        ),
+        DataModelVersion::V2_0 => (
+            ("validFrom", &credential.valid_from),
+            ("validUntil", &credential.valid_until),
+        ),
+    };
+    let parse = |(name, value): (&str, &Option<String>)| {
+        value
+            .as_deref()
+            .map(|value| date_time(name, value).map(|date_time| date_time.with_timezone(&Utc)))
+            .transpose()
+    };
+    Ok((parse(from)?, parse(until)?))
+}
+
+fn date_time(name: &str, value: &str) -> Result<DateTime<FixedOffset>, VerifierError> {
+    DateTime::parse_from_rfc3339(value).map_err(|_| invalid(format!("Invalid {}: {}", name, value)))
+}
+
+// 一つのオブジェクトか、オブジェクトの配列
+fn typed_objects(
+    name: &str,
+    value: Option<&Value>,
+    requires_id: bool,
+) -> Result<(), VerifierError> {
+    let objects = match value {
+        None => return Ok(()),
+        Some(Value::Array(objects)) => objects.iter().collect(),
+        Some(object) => vec![object],
+    };
+    for object in objects {
+        let has_type = matches!(
+            object.get("type"),
+            Some(Value::String(_)) | Some(Value::Array(_))
+        );
+        let has_id = !requires_id || object.get("id").and_then(Value::as_str).is_some();
+        if !object.is_object() || !has_type || !has_id {
+            return Err(invalid(format!("Invalid {}: {}", name, object)));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> VerifierError {
+    error!("Invalid credential: {}", message);
+    VerifierError::InvalidDataModel(message)
+}
@@ -5,16 +5,72 @@ use crate::utils::error::UtilsError;
 pub enum VerifierError {
     #[error("Missing proof")]
     MissingProof,
-    #[error("Untrusted issuer")]
-    UntrustedIssuer,
+    #[error("Unknown issuer: {0}")]
+    UnknownIssuer(String),
+    #[error("Unknown verification method: {0}")]
+    UnknownVerificationMethod(String),
+    #[error("Invalid issuer key: {0}")]
+    InvalidIssuerKey(String),
+    #[error("DID resolution failed: {0}")]
+    DidResolutionFailed(String),
+    #[error("Presentation is not signed by its holder")]
+    HolderMismatch,
     #[error("Invalid credential format")]
     InvalidCredentialFormat,
+    #[error("Credential does not conform to the data model: {0}")]
+    InvalidDataModel(String),
     #[error("Invalid base64 encoding")]
     InvalidBase64Encoding,
     #[error("Invalid JSON payload")]
     InvalidJsonPayload,
     #[error("Missing SD-ALG claim")]
     MissingSdAlgClaim,
+    #[error("Invalid SD-JWT typ: {0}")]
+    InvalidSdJwtType(String),
+    #[error("Credential has expired")]
+    CredentialExpired,
+    #[error("Credential is not yet valid")]
+    CredentialNotYetValid,
+    #[error("Presentation has expired")]
+    PresentationExpired,
+    #[error("Presentation is not yet valid")]
+    PresentationNotYetValid,
+    #[error("Invalid issued at")]
+    InvalidIssuedAt,
+    #[error("Unsupported _sd_alg: {0}")]
+    UnsupportedSdAlg(String),
+    #[error("Invalid disclosure: {0}")]
+    InvalidDisclosure(String),
+    #[error("Duplicate disclosure")]
+    DuplicateDisclosure,
+    #[error("Duplicate digest")]
+    DuplicateDigest,
+    #[error("Disclosure is not referenced by the SD-JWT")]
+    UnreferencedDisclosure,
+    #[error("Disclosed claim name collides with an existing claim: {0}")]
+    ClaimNameCollision(String),
+    #[error("Type metadata not found: {0}")]
+    TypeMetadataNotFound(String),
+    #[error("Invalid type metadata: {0}")]
+    InvalidTypeMetadata(String),
+    #[error("Type metadata integrity mismatch: {0}")]
+    TypeMetadataIntegrityMismatch(String),
+    #[error("Credential does not match its type: {0}")]
+    TypeSchemaValidationFailed(String),
+    #[error("Missing key binding JWT")]
+    MissingKeyBinding,
+    #[error("Invalid key binding JWT: {0}")]
+    InvalidKeyBinding(String),
+    #[error("Expected audience and nonce are required for key binding")]
+    MissingKeyBindingContext,
+    #[error("Key binding JWT is not fresh")]
+    StaleKeyBinding,
+    #[error("Audience mismatch")]
+    AudienceMismatch,
+    #[error("Nonce mismatch")]
+    NonceMismatch,
+    #[error("Algorithm not allowed: {0}")]
+    AlgorithmNotAllowed(String),
     #[error("Signature verification failed: {0}")]
     SignatureVerificationFailed(String),
     #[error("Internal error: {0}")]
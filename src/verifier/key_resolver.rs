@@ -0,0 +1,258 @@
+use crate::did;
+use crate::did::resolver::DidResolverRegistry;
+use crate::models::credential::{IssuerMetadata, PublicKeyInfo};
+use crate::utils::jwk;
+use crate::utils::key_manager::KeyStatus;
+use crate::utils::signer::VerifyingKey;
+use crate::verifier::error::VerifierError;
+use ed25519_dalek::PublicKey;
+use log::debug;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+const MULTIKEY_TYPE: &str = "Multikey";
+
+// Issuer の識別子と鍵 ID（VC の proof.verificationMethod / JWT の kid）から検証鍵を解決する
+pub trait IssuerKeyResolver: Send + Sync + Debug {
+    fn resolve(&self, issuer: &str, key_id: Option<&str>) -> Result<VerifyingKey, VerifierError>;
+}
+
+// 信頼する Issuer の鍵をあらかじめ登録しておくリゾルバ（登録されていない Issuer は解決できない）
+// DID だけを登録した Issuer の鍵は、DID リゾルバで解決した DID ドキュメントの assertionMethod から解決する
+#[derive(Debug, Default)]
+pub struct InMemoryIssuerKeyResolver {
+    keys: HashMap<String, HashMap<String, VerifyingKey>>,
+    trusted_dids: HashSet<String>,
+    did_resolver: Arc<DidResolverRegistry>,
+}
+
+impl InMemoryIssuerKeyResolver {
+    pub fn new() -> Self {
+        InMemoryIssuerKeyResolver::default()
+    }
+
+    pub fn with_did_resolver(mut self, did_resolver: Arc<DidResolverRegistry>) -> Self {
+        self.did_resolver = did_resolver;
+        self
+    }
+
+    pub fn insert_did(&mut self, did: &str) {
+        self.trusted_dids.insert(did.to_string());
+    }
+
+    pub fn insert(&mut self, issuer: &str, key_id: &str, key: VerifyingKey) {
+        self.keys
+            .entry(issuer.to_string())
+            .or_default()
+            .insert(absolute_key_id(issuer, key_id), key);
+    }
+
+    // Issuer メタデータ（GET /issuer/metadata のレスポンス）の公開鍵を登録する
+    // publicKeys がなければ publicKey だけを登録し、失効した鍵は登録しない
+    pub fn insert_metadata(&mut self, metadata: &IssuerMetadata) -> Result<(), VerifierError> {
+        let infos = if metadata.public_keys.is_empty() {
+            std::slice::from_ref(&metadata.public_key)
+        } else {
+            metadata.public_keys.as_slice()
+        };
+
+        for info in infos {
+            if info.status == Some(KeyStatus::Revoked) {
+                continue;
+            }
+            let key = public_key_from_info(info)?;
+            // did:key の Issuer は鍵ごとに DID が異なるため、退役した鍵はその鍵の did:key の鍵として登録する
+            let issuer = match info.id.split_once('#') {
+                Some((did, _)) if did != metadata.id && did::key::is_did_key(did) => {
+                    if did::key::resolve_public_key(&info.id).ok().as_ref() != Some(&key) {
+                        return Err(VerifierError::InvalidIssuerKey(format!(
+                            "{} does not match its did:key",
+                            info.id
+                        )));
+                    }
+                    did
+                }
+                _ => metadata.id.as_str(),
+            };
+            self.insert(issuer, &info.id, key);
+        }
+        Ok(())
+    }
+
+    fn resolve_from_did_document(
+        &self,
+        did: &str,
+        key_id: Option<&str>,
+    ) -> Result<VerifyingKey, VerifierError> {
+        let document = self
+            .did_resolver
+            .resolve(did)
+            .map_err(|e| VerifierError::DidResolutionFailed(e.to_string()))?;
+
+        let key_id = match (key_id, document.assertion_method.as_slice()) {
+            (Some(key_id), _) => key_id,
+            // 鍵 ID がない場合は、assertionMethod が一つに決まるときだけ受け入れる
+            (None, [key_id]) => key_id.as_str(),
+            (None, _) => {
+                return Err(VerifierError::UnknownVerificationMethod(format!(
+                    "No key id for {}",
+                    did
+                )))
+            }
+        };
+        if !did::document::is_assertion_method(&document, key_id) {
+            return Err(VerifierError::UnknownVerificationMethod(key_id.to_string()));
+        }
+        let method = did::document::find_verification_method(&document, key_id)
+            .map_err(|_| VerifierError::UnknownVerificationMethod(key_id.to_string()))?;
+        did::document::public_key(method)
+            .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))
+    }
+}
+
+impl IssuerKeyResolver for InMemoryIssuerKeyResolver {
+    fn resolve(&self, issuer: &str, key_id: Option<&str>) -> Result<VerifyingKey, VerifierError> {
+        if self.trusted_dids.contains(issuer) {
+            return self.resolve_from_did_document(issuer, key_id);
+        }
+        let keys = self
+            .keys
+            .get(issuer)
+            .ok_or_else(|| VerifierError::UnknownIssuer(issuer.to_string()))?;
+
+        match key_id {
+            Some(key_id) => keys
+                .get(&absolute_key_id(issuer, key_id))
+                .cloned()
+                .ok_or_else(|| VerifierError::UnknownVerificationMethod(key_id.to_string())),
+            // 鍵 ID がない場合は、Issuer の鍵が一つに決まるときだけ受け入れる
+            None if keys.len() == 1 => Ok(keys.values().next().unwrap().clone()),
+            None => Err(VerifierError::UnknownVerificationMethod(format!(
+                "No key id for {}",
+                issuer
+            ))),
+        }
+    }
+}
+
+// 解決のたびに Issuer メタデータを取得し直すリゾルバ（同じプロセスの Issuer の鍵のローテーションに追従する）
+type MetadataSource = Box<dyn Fn() -> Result<IssuerMetadata, String> + Send + Sync>;
+
+pub struct MetadataIssuerKeyResolver {
+    metadata: MetadataSource,
+}
+
+impl MetadataIssuerKeyResolver {
+    pub fn new(
+        metadata: impl Fn() -> Result<IssuerMetadata, String> + Send + Sync + 'static,
+    ) -> Self {
+        MetadataIssuerKeyResolver {
+            metadata: Box::new(metadata),
+        }
+    }
+}
+
+impl Debug for MetadataIssuerKeyResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataIssuerKeyResolver")
+            .finish_non_exhaustive()
+    }
+}
+
+impl IssuerKeyResolver for MetadataIssuerKeyResolver {
+    fn resolve(&self, issuer: &str, key_id: Option<&str>) -> Result<VerifyingKey, VerifierError> {
+        let metadata = (self.metadata)().map_err(VerifierError::InvalidIssuerKey)?;
+        let mut keys = InMemoryIssuerKeyResolver::new();
+        keys.insert_metadata(&metadata)?;
+        keys.resolve(issuer, key_id)
+    }
+}
+
+// ディレクトリ内の *.json を Issuer メタデータとして読み込み、各 Issuer の鍵を信頼する
+// id だけのファイル（{"id": "did:web:..."}）は、その DID ドキュメントの鍵を信頼する
+#[derive(Debug)]
+pub struct FileIssuerKeyResolver {
+    keys: InMemoryIssuerKeyResolver,
+}
+
+impl FileIssuerKeyResolver {
+    pub fn new(
+        directory: impl AsRef<Path>,
+        did_resolver: Arc<DidResolverRegistry>,
+    ) -> Result<Self, VerifierError> {
+        let entries = fs::read_dir(directory.as_ref())
+            .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))?;
+
+        let mut keys = InMemoryIssuerKeyResolver::new().with_did_resolver(did_resolver);
+        for entry in entries {
+            let path = entry
+                .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let document =
+                fs::read(&path).map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))?;
+            let document: Value = serde_json::from_slice(&document)
+                .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))?;
+            if document.get("publicKey").is_none() {
+                let did = document["id"].as_str().ok_or_else(|| {
+                    VerifierError::InvalidIssuerKey(format!("Missing id in {:?}", path))
+                })?;
+                debug!("Trusting issuer DID {} from {:?}", did, path);
+                keys.insert_did(did);
+                continue;
+            }
+
+            let metadata: IssuerMetadata = serde_json::from_value(document)
+                .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))?;
+            debug!("Loaded issuer {} from {:?}", metadata.id, path);
+            keys.insert_metadata(&metadata)?;
+        }
+
+        Ok(FileIssuerKeyResolver { keys })
+    }
+}
+
+impl IssuerKeyResolver for FileIssuerKeyResolver {
+    fn resolve(&self, issuer: &str, key_id: Option<&str>) -> Result<VerifyingKey, VerifierError> {
+        self.keys.resolve(issuer, key_id)
+    }
+}
+
+// publicKeyJwk があればそれを、なければ publicKeyMultibase を使う
+// （Multikey は multicodec 付き、Ed25519VerificationKey2020 は鍵そのものの base58btc）
+fn public_key_from_info(info: &PublicKeyInfo) -> Result<VerifyingKey, VerifierError> {
+    if let Some(public_key_jwk) = &info.public_key_jwk {
+        return jwk::from_jwk(public_key_jwk)
+            .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()));
+    }
+    if info.key_type == MULTIKEY_TYPE {
+        return did::key::decode_multikey(info.public_key_multibase.as_deref().unwrap_or_default())
+            .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()));
+    }
+
+    let bytes = info
+        .public_key_multibase
+        .as_deref()
+        .and_then(|multibase| multibase.strip_prefix('z'))
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .ok_or_else(|| VerifierError::InvalidIssuerKey(format!("No public key in {}", info.id)))?;
+    PublicKey::from_bytes(&bytes)
+        .map(VerifyingKey::Ed25519)
+        .map_err(|e| VerifierError::InvalidIssuerKey(e.to_string()))
+}
+
+// "#key-1" のような相対的な鍵 ID は Issuer の DID からの参照とみなす
+fn absolute_key_id(issuer: &str, key_id: &str) -> String {
+    if key_id.starts_with('#') {
+        format!("{}{}", issuer, key_id)
+    } else {
+        key_id.to_string()
+    }
+}
@@ -1,3 +1,9 @@
 pub mod api;
+#[allow(clippy::module_inception)]
 pub mod verifier;
-pub mod error;
\ No newline at end of file
+pub mod clock;
+pub mod data_model;
+pub mod error;
+pub mod key_resolver;
+pub mod sd_jwt;
+pub mod type_metadata;
\ No newline at end of file
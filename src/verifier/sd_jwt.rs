@@ -0,0 +1,141 @@
+use crate::utils::sd_jwt::{self, SdAlg};
+use crate::verifier::error::VerifierError;
+use log::error;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+// 提示された Disclosure を _sd / "..." のダイジェストと照合し、開示されたクレームを復元する
+// 対応する Disclosure のないダイジェスト（デコイや非開示のクレーム）は無視する
+pub fn process_payload(
+    payload: &Value,
+    alg: SdAlg,
+    disclosures: &[&str],
+) -> Result<Value, VerifierError> {
+    let mut by_digest = HashMap::new();
+    for disclosure in disclosures {
+        let decoded = sd_jwt::decode_disclosure(disclosure)
+            .map_err(|e| VerifierError::InvalidDisclosure(e.to_string()))?;
+        if by_digest
+            .insert(sd_jwt::hash_disclosure(alg, disclosure), decoded)
+            .is_some()
+        {
+            error!("Duplicate disclosure: {}", disclosure);
+            return Err(VerifierError::DuplicateDisclosure);
+        }
+    }
+
+    let mut processor = PayloadProcessor {
+        by_digest,
+        seen_digests: HashSet::new(),
+        used_digests: HashSet::new(),
+    };
+    let mut processed = processor.process_value(payload)?;
+
+    if processor.used_digests.len() != processor.by_digest.len() {
+        error!("Some disclosures are not referenced by the SD-JWT");
+        return Err(VerifierError::UnreferencedDisclosure);
+    }
+
+    if let Some(claims) = processed.as_object_mut() {
+        claims.remove("_sd_alg");
+    }
+    Ok(processed)
+}
+
+struct PayloadProcessor {
+    by_digest: HashMap<String, Vec<Value>>,
+    seen_digests: HashSet<String>,
+    used_digests: HashSet<String>,
+}
+
+impl PayloadProcessor {
+    fn process_value(&mut self, value: &Value) -> Result<Value, VerifierError> {
+        match value {
+            Value::Object(object) => self.process_object(object).map(Value::Object),
+            Value::Array(elements) => self.process_array(elements).map(Value::Array),
+            _ => Ok(value.clone()),
+        }
+    }
+
+    fn process_object(
+        &mut self,
+        object: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, VerifierError> {
+        let mut processed = Map::new();
+        for (key, value) in object.iter().filter(|(key, _)| *key != "_sd") {
+            processed.insert(key.clone(), self.process_value(value)?);
+        }
+
+        let Some(digests) = object.get("_sd") else {
+            return Ok(processed);
+        };
+        let digests = digests
+            .as_array()
+            .ok_or(VerifierError::InvalidCredentialFormat)?;
+
+        for digest in digests {
+            let digest = digest
+                .as_str()
+                .ok_or(VerifierError::InvalidCredentialFormat)?;
+            let Some(disclosure) = self.take_disclosure(digest)? else {
+                continue;
+            };
+
+            let [_, Value::String(name), value] = disclosure.as_slice() else {
+                return Err(VerifierError::InvalidDisclosure(
+                    "Object property disclosure must have a claim name".to_string(),
+                ));
+            };
+            if name == "_sd" || name == sd_jwt::ARRAY_ELEMENT_DIGEST_KEY {
+                return Err(VerifierError::InvalidDisclosure(format!(
+                    "Invalid claim name: {}",
+                    name
+                )));
+            }
+            if processed.contains_key(name) {
+                error!("Disclosed claim collides with an existing claim: {}", name);
+                return Err(VerifierError::ClaimNameCollision(name.clone()));
+            }
+
+            let value = self.process_value(value)?;
+            processed.insert(name.clone(), value);
+        }
+
+        Ok(processed)
+    }
+
+    fn process_array(&mut self, elements: &[Value]) -> Result<Vec<Value>, VerifierError> {
+        let mut processed = Vec::new();
+        for element in elements {
+            let Some(digest) = sd_jwt::array_element_digest(element) else {
+                processed.push(self.process_value(element)?);
+                continue;
+            };
+            let Some(disclosure) = self.take_disclosure(digest)? else {
+                continue;
+            };
+
+            let [_, value] = disclosure.as_slice() else {
+                return Err(VerifierError::InvalidDisclosure(
+                    "Array element disclosure must not have a claim name".to_string(),
+                ));
+            };
+            processed.push(self.process_value(value)?);
+        }
+        Ok(processed)
+    }
+
+    // 同じダイジェストが複数回現れる SD-JWT は不正とする
+    fn take_disclosure(&mut self, digest: &str) -> Result<Option<Vec<Value>>, VerifierError> {
+        if !self.seen_digests.insert(digest.to_string()) {
+            error!("Digest appears more than once: {}", digest);
+            return Err(VerifierError::DuplicateDigest);
+        }
+
+        let disclosure = self.by_digest.get(digest).cloned();
+        if disclosure.is_some() {
+            self.used_digests.insert(digest.to_string());
+        }
+        Ok(disclosure)
+    }
+}
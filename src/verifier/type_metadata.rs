@@ -0,0 +1,153 @@
+use crate::models::type_metadata::TypeMetadata;
+use crate::utils::integrity;
+use crate::verifier::error::VerifierError;
+use log::{debug, error};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+// extends を辿る深さの上限
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+// vct から Type Metadata ドキュメントを取得する
+// integrity の検証のため、解析前のバイト列をそのまま返す
+pub trait TypeMetadataResolver: Send + Sync + Debug {
+    fn resolve(&self, vct: &str) -> Result<Vec<u8>, VerifierError>;
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryTypeMetadataResolver {
+    documents: HashMap<String, Vec<u8>>,
+}
+
+impl InMemoryTypeMetadataResolver {
+    pub fn new() -> Self {
+        InMemoryTypeMetadataResolver::default()
+    }
+
+    pub fn insert(&mut self, vct: &str, document: Vec<u8>) {
+        self.documents.insert(vct.to_string(), document);
+    }
+}
+
+impl TypeMetadataResolver for InMemoryTypeMetadataResolver {
+    fn resolve(&self, vct: &str) -> Result<Vec<u8>, VerifierError> {
+        self.documents
+            .get(vct)
+            .cloned()
+            .ok_or_else(|| VerifierError::TypeMetadataNotFound(vct.to_string()))
+    }
+}
+
+// ディレクトリ内の *.json を Type Metadata として読み込み、各ドキュメントの vct で引けるようにする
+#[derive(Debug)]
+pub struct FileTypeMetadataResolver {
+    documents: InMemoryTypeMetadataResolver,
+}
+
+impl FileTypeMetadataResolver {
+    pub fn new(directory: impl AsRef<Path>) -> Result<Self, VerifierError> {
+        let entries = fs::read_dir(directory.as_ref())
+            .map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))?;
+
+        let mut documents = InMemoryTypeMetadataResolver::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let document =
+                fs::read(&path).map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))?;
+            let metadata = parse_type_metadata(&document)?;
+            debug!("Loaded type metadata {} from {:?}", metadata.vct, path);
+            documents.insert(&metadata.vct, document);
+        }
+
+        Ok(FileTypeMetadataResolver { documents })
+    }
+}
+
+impl TypeMetadataResolver for FileTypeMetadataResolver {
+    fn resolve(&self, vct: &str) -> Result<Vec<u8>, VerifierError> {
+        self.documents.resolve(vct)
+    }
+}
+
+// 処理済みのペイロードを vct の Type Metadata と、その extends を辿ったすべてのタイプの JSON Schema で検証する
+pub fn validate_type(
+    claims: &Value,
+    resolver: &dyn TypeMetadataResolver,
+) -> Result<(), VerifierError> {
+    let vct = claims["vct"]
+        .as_str()
+        .ok_or(VerifierError::InvalidCredentialFormat)?;
+    let mut next = Some((
+        vct.to_string(),
+        claims["vct#integrity"].as_str().map(str::to_string),
+    ));
+    let mut visited = HashSet::new();
+
+    while let Some((vct, expected_integrity)) = next {
+        if !visited.insert(vct.clone()) || visited.len() > MAX_EXTENDS_DEPTH {
+            error!("Type metadata extends chain is too deep or cyclic: {}", vct);
+            return Err(VerifierError::InvalidTypeMetadata(format!(
+                "Invalid extends chain at {}",
+                vct
+            )));
+        }
+
+        let document = resolver.resolve(&vct)?;
+        if let Some(expected) = expected_integrity {
+            let matched = integrity::verify_integrity(&document, &expected)
+                .map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))?;
+            if !matched {
+                error!("Type metadata integrity mismatch: {}", vct);
+                return Err(VerifierError::TypeMetadataIntegrityMismatch(vct));
+            }
+        }
+
+        let metadata = parse_type_metadata(&document)?;
+        if metadata.vct != vct {
+            return Err(VerifierError::InvalidTypeMetadata(format!(
+                "Type metadata for {} declares vct {}",
+                vct, metadata.vct
+            )));
+        }
+        if let Some(schema) = &metadata.schema {
+            validate_schema(claims, schema, &vct)?;
+        }
+
+        next = metadata
+            .extends
+            .map(|extends| (extends, metadata.extends_integrity));
+    }
+
+    Ok(())
+}
+
+fn parse_type_metadata(document: &[u8]) -> Result<TypeMetadata, VerifierError> {
+    serde_json::from_slice(document).map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))
+}
+
+fn validate_schema(claims: &Value, schema: &Value, vct: &str) -> Result<(), VerifierError> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| VerifierError::InvalidTypeMetadata(e.to_string()))?;
+
+    let errors: Vec<String> = validator
+        .iter_errors(claims)
+        .map(|e| format!("{} at {}", e, e.instance_path))
+        .collect();
+    if !errors.is_empty() {
+        error!(
+            "Credential does not match the schema of {}: {:?}",
+            vct, errors
+        );
+        return Err(VerifierError::TypeSchemaValidationFailed(errors.join(", ")));
+    }
+    Ok(())
+}
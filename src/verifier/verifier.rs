@@ -1,21 +1,85 @@
+use crate::did;
 use crate::models::credential::{CredentialResponse, VerifiablePresentation};
-use crate::utils::crypto;
+use crate::utils::error::UtilsError;
+use crate::utils::sd_jwt::SdAlg;
+use crate::utils::signer::SignatureAlgorithm;
+use crate::utils::{crypto, jwk, sd_jwt};
+use crate::verifier::clock::{Clock, SystemClock};
+use crate::verifier::data_model;
 use crate::verifier::error::VerifierError;
+use crate::verifier::key_resolver::{InMemoryIssuerKeyResolver, IssuerKeyResolver};
+use crate::verifier::sd_jwt as sd_jwt_processor;
+use crate::verifier::type_metadata::{self, TypeMetadataResolver};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use log::{debug, error, info};
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
 use serde_json::Value;
+use std::sync::Arc;
 
-pub fn verify_credential(credential: &CredentialResponse) -> Result<bool, VerifierError> {
+#[derive(Debug, Clone)]
+pub struct VerificationOptions {
+    // KB-JWT の aud / nonce として期待する値（cnf を持つ SD-JWT の検証では必須）
+    // VP の検証では proof の domain / challenge として期待する値で、常に必須
+    pub audience: Option<String>,
+    pub nonce: Option<String>,
+    // KB-JWT の iat として許容する古さ
+    pub key_binding_max_age: Duration,
+    // iat / nbf / exp の判定で許容する時刻のずれ
+    pub clock_skew: Duration,
+    // 指定された場合は SD-JWT VC を vct の Type Metadata で検証する
+    pub type_metadata: Option<Arc<dyn TypeMetadataResolver>>,
+    // 署名の検証で受け入れる JOSE の alg
+    pub allowed_algorithms: Vec<SignatureAlgorithm>,
+    // 信頼する Issuer の検証鍵（既定ではどの Issuer も信頼しない）
+    pub issuer_keys: Arc<dyn IssuerKeyResolver>,
+    // 有効期間の判定に使う現在時刻
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for VerificationOptions {
+    fn default() -> Self {
+        VerificationOptions {
+            audience: None,
+            nonce: None,
+            key_binding_max_age: Duration::minutes(5),
+            clock_skew: Duration::seconds(60),
+            type_metadata: None,
+            allowed_algorithms: SignatureAlgorithm::ALL.to_vec(),
+            issuer_keys: Arc::new(InMemoryIssuerKeyResolver::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+// 検証に成功した場合は検証済みのクレームを返す（SD-JWT の場合は開示されたクレームから復元したもの）
+pub fn verify_credential(
+    credential: &CredentialResponse,
+    options: &VerificationOptions,
+) -> Result<Value, VerifierError> {
     info!("Verifying credential: {:?}", credential);
 
     if let Some(sd_jwt) = &credential.sd_jwt {
-        verify_sd_jwt_credential(sd_jwt)
+        verify_sd_jwt_credential(sd_jwt, options)
     } else {
-        verify_vc_credential(credential)
+        verify_vc_credential(credential, options)
     }
 }
 
-fn verify_vc_credential(credential: &CredentialResponse) -> Result<bool, VerifierError> {
+fn verify_vc_credential(
+    credential: &CredentialResponse,
+    options: &VerificationOptions,
+) -> Result<Value, VerifierError> {
+    let version = data_model::validate_credential(credential)?;
+    info!("Credential data model: {:?}", version);
+    let (valid_from, valid_until) = data_model::validity_period(credential, version)?;
+    verify_time_window(
+        valid_from,
+        valid_until,
+        options,
+        VerifierError::CredentialNotYetValid,
+        VerifierError::CredentialExpired,
+    )?;
+
     let credential_without_proof = {
         let mut cred = credential.clone();
         cred.proof = None;
@@ -29,46 +93,244 @@ fn verify_vc_credential(credential: &CredentialResponse) -> Result<bool, Verifie
         .ok_or(VerifierError::MissingProof)?;
     info!("Proof: {:?}", proof);
 
-    crypto::verify_vc(&credential_without_proof, proof).map_err(|e| {
+    let public_key = options
+        .issuer_keys
+        .resolve(credential.issuer.id(), proof["verificationMethod"].as_str())
+        .inspect_err(|e| error!("Failed to resolve issuer key: {}", e))?;
+
+    crypto::verify_vc(
+        &credential_without_proof,
+        proof,
+        &public_key,
+        &options.allowed_algorithms,
+    )
+    .map_err(|e| {
         error!("Signature verification failed: {}", e);
-        VerifierError::SignatureVerificationFailed(e.to_string())
+        signature_error(e, VerifierError::SignatureVerificationFailed)
     })?;
 
-    if !is_trusted_issuer(&credential.issuer) {
-        error!("Untrusted issuer: {}", credential.issuer);
-        return Err(VerifierError::UntrustedIssuer);
-    }
-
-    Ok(true)
+    Ok(credential.credential_subject.clone())
 }
 
-fn verify_sd_jwt_credential(sd_jwt: &str) -> Result<bool, VerifierError> {
+fn verify_sd_jwt_credential(
+    sd_jwt: &str,
+    options: &VerificationOptions,
+) -> Result<Value, VerifierError> {
     info!("Verifying SD-JWT: {}", sd_jwt);
 
-    let parts: Vec<&str> = sd_jwt.split('.').collect();
+    let (jwt, disclosures, key_binding_jwt) = sd_jwt::split(sd_jwt);
+
+    let parts: Vec<&str> = jwt.split('.').collect();
     if parts.len() != 3 {
         return Err(VerifierError::InvalidCredentialFormat);
     }
 
-    let payload_json = URL_SAFE_NO_PAD
-        .decode(parts[1])
-        .map_err(|_| VerifierError::InvalidBase64Encoding)?;
-    let payload: Value =
-        serde_json::from_slice(&payload_json).map_err(|_| VerifierError::InvalidJsonPayload)?;
+    let decode_part = |part: &str| -> Result<Value, VerifierError> {
+        let json = URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| VerifierError::InvalidBase64Encoding)?;
+        serde_json::from_slice(&json).map_err(|_| VerifierError::InvalidJsonPayload)
+    };
+    let header = decode_part(parts[0])?;
+    let payload = decode_part(parts[1])?;
 
-    if payload.get("_sd_alg").is_none() {
-        return Err(VerifierError::MissingSdAlgClaim);
+    let typ = header["typ"].as_str().unwrap_or_default();
+    if typ != sd_jwt::SD_JWT_VC_TYPE && typ != sd_jwt::LEGACY_SD_JWT_VC_TYPE {
+        error!("Unexpected SD-JWT typ: {}", header["typ"]);
+        return Err(VerifierError::InvalidSdJwtType(header["typ"].to_string()));
     }
+    if payload.get("vct").and_then(Value::as_str).is_none() {
+        error!("SD-JWT VC has no vct claim");
+        return Err(VerifierError::InvalidCredentialFormat);
+    }
+
+    let alg = payload
+        .get("_sd_alg")
+        .ok_or(VerifierError::MissingSdAlgClaim)
+        .and_then(|alg| {
+            SdAlg::from_name(alg.as_str().unwrap_or_default()).map_err(|_| {
+                error!("Unsupported _sd_alg: {}", alg);
+                VerifierError::UnsupportedSdAlg(alg.to_string())
+            })
+        })?;
 
-    crypto::verify_sd_jwt(sd_jwt).map_err(|e| {
+    let issuer = payload["iss"].as_str().ok_or_else(|| {
+        error!("SD-JWT VC has no iss claim");
+        VerifierError::InvalidCredentialFormat
+    })?;
+    let public_key = options
+        .issuer_keys
+        .resolve(issuer, header["kid"].as_str())
+        .inspect_err(|e| error!("Failed to resolve issuer key: {}", e))?;
+
+    crypto::verify_sd_jwt(jwt, &public_key, &options.allowed_algorithms).map_err(|e| {
         error!("SD-JWT verification failed: {}", e);
-        VerifierError::SignatureVerificationFailed(e.to_string())
-    })
+        signature_error(e, VerifierError::SignatureVerificationFailed)
+    })?;
+
+    verify_validity_period(&payload, options)?;
+    verify_key_binding(sd_jwt, &payload, alg, key_binding_jwt, options)?;
+
+    let claims = sd_jwt_processor::process_payload(&payload, alg, &disclosures)?;
+
+    if let Some(resolver) = &options.type_metadata {
+        type_metadata::validate_type(&claims, resolver.as_ref())?;
+    }
+
+    Ok(claims)
+}
+
+// 有効期間の開始と終了は存在する場合のみ、clock_skew の範囲のずれを許容して判定する
+fn verify_time_window(
+    not_before: Option<DateTime<Utc>>,
+    not_after: Option<DateTime<Utc>>,
+    options: &VerificationOptions,
+    not_yet_valid: VerifierError,
+    expired: VerifierError,
+) -> Result<(), VerifierError> {
+    let now = options.clock.now();
+    if not_before.is_some_and(|not_before| not_before > now + options.clock_skew) {
+        error!("Not yet valid: {:?} > {}", not_before, now);
+        return Err(not_yet_valid);
+    }
+    if not_after.is_some_and(|not_after| not_after <= now - options.clock_skew) {
+        error!("Expired: {:?} <= {}", not_after, now);
+        return Err(expired);
+    }
+    Ok(())
+}
+
+// iat / nbf / exp は存在する場合のみ、clock_skew の範囲のずれを許容して判定する
+fn verify_validity_period(
+    payload: &Value,
+    options: &VerificationOptions,
+) -> Result<(), VerifierError> {
+    let now = options.clock.now().timestamp();
+    let skew = options.clock_skew.num_seconds();
+    let timestamp = |claim: &str| -> Result<Option<i64>, VerifierError> {
+        match payload.get(claim) {
+            None => Ok(None),
+            Some(value) => value.as_i64().map(Some).ok_or_else(|| {
+                error!("Invalid {} claim: {}", claim, value);
+                VerifierError::InvalidCredentialFormat
+            }),
+        }
+    };
+
+    if let Some(issued_at) = timestamp("iat")? {
+        if issued_at > now + skew {
+            error!("SD-JWT is issued in the future: iat={}", issued_at);
+            return Err(VerifierError::InvalidIssuedAt);
+        }
+    }
+    if let Some(not_before) = timestamp("nbf")? {
+        if not_before > now + skew {
+            error!("SD-JWT is not yet valid: nbf={}", not_before);
+            return Err(VerifierError::CredentialNotYetValid);
+        }
+    }
+    if let Some(expires_at) = timestamp("exp")? {
+        if expires_at <= now - skew {
+            error!("SD-JWT has expired: exp={}", expires_at);
+            return Err(VerifierError::CredentialExpired);
+        }
+    }
+    Ok(())
+}
+
+// cnf を持つ SD-JWT は、Holder の鍵で署名された KB-JWT を伴う場合のみ受け入れる
+fn verify_key_binding(
+    sd_jwt: &str,
+    payload: &Value,
+    alg: SdAlg,
+    key_binding_jwt: Option<&str>,
+    options: &VerificationOptions,
+) -> Result<(), VerifierError> {
+    let Some(holder_jwk) = payload.get("cnf").and_then(|cnf| cnf.get("jwk")) else {
+        if key_binding_jwt.is_some() {
+            return Err(VerifierError::InvalidKeyBinding(
+                "SD-JWT has no cnf claim".to_string(),
+            ));
+        }
+        return Ok(());
+    };
+    let key_binding_jwt = key_binding_jwt.ok_or_else(|| {
+        error!("Key binding is required but KB-JWT is missing");
+        VerifierError::MissingKeyBinding
+    })?;
+    // 期待する aud / nonce がなければ、別の Verifier 宛ての KB-JWT も受け入れてしまう
+    let (Some(expected_audience), Some(expected_nonce)) =
+        (options.audience.as_deref(), options.nonce.as_deref())
+    else {
+        error!("Expected audience and nonce are required to verify KB-JWT");
+        return Err(VerifierError::MissingKeyBindingContext);
+    };
+
+    let holder_key =
+        jwk::from_jwk(holder_jwk).map_err(|e| VerifierError::InvalidKeyBinding(e.to_string()))?;
+    let (header, claims) = crypto::decode_jwt(key_binding_jwt)
+        .map_err(|e| VerifierError::InvalidKeyBinding(e.to_string()))?;
+
+    if header["typ"] != sd_jwt::KEY_BINDING_JWT_TYPE {
+        return Err(VerifierError::InvalidKeyBinding(format!(
+            "Unexpected typ: {}",
+            header["typ"]
+        )));
+    }
+
+    crypto::verify_jwt(key_binding_jwt, &holder_key, &options.allowed_algorithms).map_err(|e| {
+        error!("KB-JWT verification failed: {}", e);
+        signature_error(e, VerifierError::InvalidKeyBinding)
+    })?;
+
+    if claims["sd_hash"] != sd_jwt::sd_hash(alg, sd_jwt) {
+        return Err(VerifierError::InvalidKeyBinding(
+            "sd_hash does not match the presented SD-JWT".to_string(),
+        ));
+    }
+
+    let issued_at = claims["iat"]
+        .as_i64()
+        .ok_or_else(|| VerifierError::InvalidKeyBinding("Missing iat".to_string()))?;
+    let now = options.clock.now().timestamp();
+    if issued_at > now + options.clock_skew.num_seconds()
+        || issued_at < now - options.key_binding_max_age.num_seconds()
+    {
+        error!("KB-JWT is not fresh: iat={}", issued_at);
+        return Err(VerifierError::StaleKeyBinding);
+    }
+
+    let audience = claims["aud"]
+        .as_str()
+        .ok_or_else(|| VerifierError::InvalidKeyBinding("Missing aud".to_string()))?;
+    if audience != expected_audience {
+        error!("KB-JWT audience mismatch: {}", audience);
+        return Err(VerifierError::AudienceMismatch);
+    }
+
+    let nonce = claims["nonce"]
+        .as_str()
+        .ok_or_else(|| VerifierError::InvalidKeyBinding("Missing nonce".to_string()))?;
+    if nonce != expected_nonce {
+        error!("KB-JWT nonce mismatch: {}", nonce);
+        return Err(VerifierError::NonceMismatch);
+    }
+
+    Ok(())
 }
 
-pub fn verify_presentation(presentation: &VerifiablePresentation) -> Result<bool, VerifierError> {
+pub fn verify_presentation(
+    presentation: &VerifiablePresentation,
+    options: &VerificationOptions,
+) -> Result<Vec<Value>, VerifierError> {
     info!("Verifying presentation: {:?}", presentation);
 
+    // VP の domain / challenge は提示した側ではなく Verifier が指定する
+    let (Some(domain), Some(challenge)) = (&options.audience, &options.nonce) else {
+        error!("Expected domain and challenge are required for presentations");
+        return Err(VerifierError::MissingKeyBindingContext);
+    };
+
     let presentation_without_proof = {
         let mut pres = presentation.clone();
         pres.proof = None;
@@ -85,36 +347,152 @@ pub fn verify_presentation(presentation: &VerifiablePresentation) -> Result<bool
         .ok_or(VerifierError::MissingProof)?;
     info!("Presentation proof: {:?}", proof);
 
-    crypto::verify_vc(&presentation_without_proof, proof).map_err(|e| {
+    // VP の署名者は verificationMethod の DID で識別する
+    let verification_method = proof["verificationMethod"]
+        .as_str()
+        .ok_or(VerifierError::InvalidCredentialFormat)?;
+    let (controller, _) = verification_method
+        .split_once('#')
+        .unwrap_or((verification_method, ""));
+    if presentation
+        .holder
+        .as_deref()
+        .is_some_and(|holder| holder != controller)
+    {
+        error!("Presentation is not signed by its holder: {}", controller);
+        return Err(VerifierError::HolderMismatch);
+    }
+
+    // did:key と did:jwk は DID 自体から鍵を導出し、それ以外は信頼する鍵から解決する
+    let public_key = if did::key::is_did_key(controller) {
+        did::key::resolve_public_key(verification_method)
+            .map_err(|e| VerifierError::DidResolutionFailed(e.to_string()))
+    } else if did::jwk::is_did_jwk(controller) {
+        did::jwk::resolve_public_key(verification_method)
+            .map_err(|e| VerifierError::DidResolutionFailed(e.to_string()))
+    } else {
+        options
+            .issuer_keys
+            .resolve(controller, Some(verification_method))
+    }
+    .inspect_err(|e| error!("Failed to resolve presentation key: {}", e))?;
+
+    crypto::verify_vc(
+        &presentation_without_proof,
+        proof,
+        &public_key,
+        &options.allowed_algorithms,
+    )
+    .map_err(|e| {
         error!("Presentation signature verification failed: {}", e);
-        VerifierError::SignatureVerificationFailed(e.to_string())
+        signature_error(e, VerifierError::SignatureVerificationFailed)
     })?;
 
-    for credential in &presentation.verifiable_credential {
-        info!("Verifying credential in presentation: {:?}", credential);
-        verify_credential(credential)?;
+    // VP の有効期間は proof の created から expires（省略時は無期限）まで
+    verify_time_window(
+        proof_date_time(proof, "created")?,
+        proof_date_time(proof, "expires")?,
+        options,
+        VerifierError::PresentationNotYetValid,
+        VerifierError::PresentationExpired,
+    )?;
+
+    // VP と KB-JWT は Verifier が指定した domain / challenge に対して作成されていなければならない
+    if proof["domain"].as_str() != Some(domain.as_str()) {
+        return Err(VerifierError::AudienceMismatch);
+    }
+    if proof["challenge"].as_str() != Some(challenge.as_str()) {
+        return Err(VerifierError::NonceMismatch);
     }
 
-    Ok(true)
+    presentation
+        .verifiable_credential
+        .iter()
+        .map(|credential| {
+            info!("Verifying credential in presentation: {:?}", credential);
+            verify_credential(credential, options)
+        })
+        .collect()
+}
+
+fn proof_date_time(proof: &Value, name: &str) -> Result<Option<DateTime<Utc>>, VerifierError> {
+    match proof.get(name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|date_time| Some(date_time.with_timezone(&Utc)))
+            .ok_or_else(|| {
+                error!("Invalid proof {}: {}", name, value);
+                VerifierError::InvalidCredentialFormat
+            }),
+    }
 }
 
-fn is_trusted_issuer(issuer: &str) -> bool {
-    debug!("Checking if issuer is trusted: {}", issuer);
-    // TODO: Implement actual issuer trust verification
-    true
+// 許可されていない alg は署名の不一致と区別して返す
+fn signature_error(error: UtilsError, otherwise: fn(String) -> VerifierError) -> VerifierError {
+    match error {
+        UtilsError::AlgorithmNotAllowed(alg) | UtilsError::UnsupportedAlgorithm(alg) => {
+            VerifierError::AlgorithmNotAllowed(alg)
+        }
+        error => otherwise(error.to_string()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::issuer::issuer::create_sd_jwt_credential;
-    use crate::models::credential::{CredentialResponse, VerifiablePresentation};
+    use crate::did::resolver::test_resolver::StaticDidResolver;
+    use crate::did::resolver::DidResolverRegistry;
+    use crate::did::web::test_did_web::{did_web_document, mock_did_web};
+    use crate::did::web::DidWebResolver;
+    use crate::holder::holder::Holder;
+    use crate::holder::storage::test_storage::TestStorage;
+    use crate::issuer::api as issuer_api;
+    use crate::issuer::issuer::{create_credential, create_sd_jwt_credential, get_metadata};
+    use crate::issuer::type_metadata;
+    use crate::models::credential::{CredentialRequest, PresentationRequest};
+    use crate::models::credential::{
+        CredentialResponse, Issuer, IssuerMetadata, VerifiablePresentation,
+    };
     use crate::models::sd_jwt::SDJWTCredentialRequest;
     use crate::utils::crypto;
+    use crate::utils::data_integrity::{self, Cryptosuite, ProofOptions, DATA_INTEGRITY_CONTEXT};
+    use crate::utils::key_manager::test_key_manager::issuer_key_manager;
+    use crate::utils::key_manager::{FileKeyManager, IssuerConfig, KeyManager, KeyStatus};
+    use crate::utils::signer::{
+        SignatureAlgorithm, SignatureVerifier, Signer, SigningKey, VerifyingKey,
+    };
+    use crate::utils::{jcs, json_ld};
     use crate::verifier::api;
+    use crate::verifier::clock::test_clock::FixedClock;
+    use crate::verifier::key_resolver::FileIssuerKeyResolver;
+    use crate::verifier::type_metadata::{FileTypeMetadataResolver, InMemoryTypeMetadataResolver};
     use actix_web::{test, web, App};
     use chrono::Utc;
+    use ed25519_dalek::Keypair;
     use log::{debug, info};
+    use rand::rngs::OsRng;
+    use std::sync::Arc;
+    use std::time::Duration as StdDuration;
+
+    // テスト用の Issuer（keys/keys.json の鍵）を信頼する
+    fn local_issuer_keys() -> Arc<dyn IssuerKeyResolver> {
+        let mut resolver = InMemoryIssuerKeyResolver::new();
+        resolver
+            .insert_metadata(
+                &get_metadata(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap(),
+            )
+            .unwrap();
+        Arc::new(resolver)
+    }
+
+    fn default_options() -> VerificationOptions {
+        VerificationOptions {
+            issuer_keys: local_issuer_keys(),
+            ..VerificationOptions::default()
+        }
+    }
 
     fn create_test_credential() -> CredentialResponse {
         let mut credential = CredentialResponse {
@@ -124,8 +502,11 @@ mod tests {
                 "VerifiableCredential".to_string(),
                 "UniversityDegreeCredential".to_string(),
             ],
-            issuer: "did:example:123".to_string(),
-            issuance_date: Utc::now().to_rfc3339(),
+            issuer: Issuer::Id(
+                crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default())
+                    .unwrap(),
+            ),
+            issuance_date: Some(Utc::now().to_rfc3339()),
             credential_subject: serde_json::json!({
                 "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
                 "name": "Alice",
@@ -137,11 +518,17 @@ mod tests {
             proof: None,
             sd_jwt: None,
             disclosures: None,
+            ..Default::default()
         };
 
         let credential_json = serde_json::to_value(&credential).unwrap();
         debug!("Credential to sign: {:?}", credential_json);
-        let proof = crypto::sign_json(&credential_json).unwrap();
+        let proof = crypto::sign_json(
+            &credential_json,
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+        )
+        .unwrap();
         credential.proof = Some(proof);
         debug!("Signed credential: {:?}", credential);
         credential
@@ -155,9 +542,17 @@ mod tests {
                 "email": "alice@example.com",
                 "birthdate": "1990-01-01"
             }),
+            selective_disclosure: None,
+            holder_jwk: None,
+            validity_seconds: None,
         };
 
-        let sd_jwt_response = create_sd_jwt_credential(request).unwrap();
+        let sd_jwt_response = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
 
         // sd_jwt_response は既に CredentialResponse 型なので、そのまま返せます
         sd_jwt_response
@@ -168,26 +563,56 @@ mod tests {
         // 通常のVC形式のテスト
         let credential = create_test_credential();
         info!("Standard Credential to verify: {:?}", credential);
-        let result = verify_credential(&credential);
+        let result = verify_credential(&credential, &default_options());
         info!("Standard Credential Verification result: {:?}", result);
         assert!(
             result.is_ok(),
             "Standard Credential Verification failed: {:?}",
             result.err()
         );
-        assert!(result.unwrap());
+        assert_eq!(result.unwrap(), credential.credential_subject);
 
         // SD-JWT形式のテスト
         let sd_jwt_credential = create_test_sd_jwt_credential();
         info!("SD-JWT Credential to verify: {:?}", sd_jwt_credential);
-        let result = verify_credential(&sd_jwt_credential);
+        let result = verify_credential(&sd_jwt_credential, &default_options());
         info!("SD-JWT Credential Verification result: {:?}", result);
         assert!(
             result.is_ok(),
             "SD-JWT Credential Verification failed: {:?}",
             result.err()
         );
-        assert!(result.unwrap());
+        let claims = result.unwrap();
+        assert_eq!(claims["email"], "alice@example.com");
+        assert_eq!(claims["birthdate"], "1990-01-01");
+        assert!(claims.get("_sd").is_none());
+        assert!(claims.get("_sd_alg").is_none());
+    }
+
+    // テスト用の Issuer の鍵で、domain / challenge を含む proof を VP に付ける
+    fn sign_presentation(presentation: &mut VerifiablePresentation, domain: &str, challenge: &str) {
+        let presentation_json = serde_json::to_value(&*presentation).unwrap();
+        info!("Presentation to sign: {:?}", presentation_json);
+        let key_manager = issuer_key_manager();
+        let key = key_manager.get_current_key().unwrap();
+        let signer = key_manager.get_signer(&key.kid).unwrap();
+        let verification_method =
+            crypto::issuer_public_key_info(key_manager.as_ref(), &IssuerConfig::default())
+                .unwrap()
+                .id;
+        let options = ProofOptions {
+            domain: Some(domain.to_string()),
+            challenge: Some(challenge.to_string()),
+            ..ProofOptions::default()
+        };
+        let proof = crypto::create_proof(
+            &presentation_json,
+            signer.as_ref(),
+            &verification_method,
+            &options,
+        )
+        .unwrap();
+        presentation.proof = Some(proof);
     }
 
     #[actix_rt::test]
@@ -196,28 +621,42 @@ mod tests {
         let mut presentation = VerifiablePresentation {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             types: vec!["VerifiablePresentation".to_string()],
+            holder: None,
             verifiable_credential: vec![credential],
             proof: None,
         };
 
-        let presentation_json = serde_json::to_value(&presentation).unwrap();
-        info!("Presentation to sign: {:?}", presentation_json);
-        let proof = crypto::sign_json(&presentation_json).unwrap();
-        presentation.proof = Some(proof.clone());
-        info!("Generated proof: {:?}", proof);
+        sign_presentation(&mut presentation, "example.com", "nonce-1");
+        info!("Generated proof: {:?}", presentation.proof);
 
         info!("Presentation to verify: {:?}", presentation);
-        let result = verify_presentation(&presentation);
+        let result = verify_presentation(&presentation, &options("example.com", "nonce-1"));
         info!("Presentation verification result: {:?}", result);
         assert!(result.is_ok(), "Verification failed: {:?}", result.err());
-        assert!(result.unwrap());
+        assert_eq!(result.unwrap().len(), 1);
+
+        // domain / challenge は Verifier が指定し、proof の値と一致しなければならない
+        let result = verify_presentation(&presentation, &default_options());
+        assert!(matches!(
+            result,
+            Err(VerifierError::MissingKeyBindingContext)
+        ));
+        let result = verify_presentation(&presentation, &options("attacker.example", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::AudienceMismatch)));
+        let result = verify_presentation(&presentation, &options("example.com", "nonce-2"));
+        assert!(matches!(result, Err(VerifierError::NonceMismatch)));
     }
 
     #[actix_rt::test]
     async fn test_verify_credential_api() {
-        let app = test::init_service(App::new().service(
-            web::resource("/verify/credential").route(web::post().to(api::verify_credential)),
-        ))
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(local_issuer_keys()))
+                .service(
+                    web::resource("/verify/credential")
+                        .route(web::post().to(api::verify_credential)),
+                ),
+        )
         .await;
 
         // 通常のVC形式のテスト
@@ -270,33 +709,47 @@ mod tests {
             "SD-JWT Credential Verification failed: {:?}",
             body
         );
+        assert_eq!(body["claims"]["email"], "alice@example.com");
     }
 
     #[actix_rt::test]
     async fn test_verify_presentation_api() {
-        let app = test::init_service(App::new().service(
-            web::resource("/verify/presentation").route(web::post().to(api::verify_presentation)),
-        ))
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(local_issuer_keys()))
+                .service(
+                    web::resource("/verify/presentation")
+                        .route(web::post().to(api::verify_presentation)),
+                ),
+        )
         .await;
 
         let credential = create_test_credential();
         let mut presentation = VerifiablePresentation {
             context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
             types: vec!["VerifiablePresentation".to_string()],
+            holder: None,
             verifiable_credential: vec![credential],
             proof: None,
         };
 
-        let presentation_json = serde_json::to_value(&presentation).unwrap();
-        info!("Presentation to sign: {:?}", presentation_json);
-        let proof = crypto::sign_json(&presentation_json).unwrap();
-        presentation.proof = Some(proof);
+        sign_presentation(&mut presentation, "example.com", "nonce-1");
 
-        info!("Presentation to verify via API: {:?}", presentation);
+        // domain / challenge を指定しなければ検証しない
         let req = test::TestRequest::post()
             .uri("/verify/presentation")
             .set_json(&presentation)
             .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(!resp.status().is_success());
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "Expected aud and nonce are required for key binding");
+
+        info!("Presentation to verify via API: {:?}", presentation);
+        let req = test::TestRequest::post()
+            .uri("/verify/presentation?domain=example.com&challenge=nonce-1")
+            .set_json(&presentation)
+            .to_request();
 
         let resp = test::call_service(&app, req).await;
         info!("API response for presentation: {:?}", resp);
@@ -310,4 +763,1423 @@ mod tests {
         info!("API response body for presentation: {:?}", body);
         assert_eq!(body["verified"], true, "Verification failed: {:?}", body);
     }
+
+    fn create_test_bound_sd_jwt(holder_jwk: serde_json::Value) -> String {
+        let request = SDJWTCredentialRequest {
+            credential_subject: serde_json::json!({
+                "given_name": "Alice",
+                "family_name": "Smith",
+                "email": "alice@example.com"
+            }),
+            selective_disclosure: None,
+            holder_jwk: Some(holder_jwk),
+            validity_seconds: None,
+        };
+        create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap()
+        .sd_jwt
+        .unwrap()
+    }
+
+    fn present_with_key_binding(sd_jwt: &str, holder_key: &dyn Signer, issued_at: i64) -> String {
+        let key_binding_jwt = sd_jwt::create_key_binding_jwt(
+            sd_jwt,
+            SdAlg::Sha256,
+            "example.com",
+            "nonce-1",
+            issued_at,
+            holder_key,
+        )
+        .unwrap();
+        format!("{}{}", sd_jwt, key_binding_jwt)
+    }
+
+    fn options(audience: &str, nonce: &str) -> VerificationOptions {
+        VerificationOptions {
+            audience: Some(audience.to_string()),
+            nonce: Some(nonce.to_string()),
+            ..default_options()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_key_binding() {
+        let holder_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let sd_jwt = create_test_bound_sd_jwt(jwk::to_jwk(&holder_key.public_key()));
+        let presented = present_with_key_binding(&sd_jwt, &holder_key, Utc::now().timestamp());
+
+        let result = verify_sd_jwt_credential(&presented, &options("example.com", "nonce-1"));
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        // KB-JWT がなければ所持証明にならない
+        let result = verify_sd_jwt_credential(&sd_jwt, &default_options());
+        assert!(matches!(result, Err(VerifierError::MissingKeyBinding)));
+
+        let result = verify_sd_jwt_credential(&presented, &options("example.com", "nonce-2"));
+        assert!(matches!(result, Err(VerifierError::NonceMismatch)));
+
+        let result = verify_sd_jwt_credential(&presented, &options("other.example", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::AudienceMismatch)));
+
+        // 期待する aud / nonce を指定しなければ KB-JWT を受け入れない
+        let result = verify_sd_jwt_credential(&presented, &default_options());
+        assert!(matches!(
+            result,
+            Err(VerifierError::MissingKeyBindingContext)
+        ));
+        let without_nonce = VerificationOptions {
+            nonce: None,
+            ..options("example.com", "nonce-1")
+        };
+        let result = verify_sd_jwt_credential(&presented, &without_nonce);
+        assert!(matches!(
+            result,
+            Err(VerifierError::MissingKeyBindingContext)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_rejects_invalid_key_binding() {
+        let holder_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let sd_jwt = create_test_bound_sd_jwt(jwk::to_jwk(&holder_key.public_key()));
+
+        // 古すぎる KB-JWT
+        let stale = present_with_key_binding(&sd_jwt, &holder_key, Utc::now().timestamp() - 3600);
+        let result = verify_sd_jwt_credential(&stale, &options("example.com", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::StaleKeyBinding)));
+
+        // cnf と異なる鍵で署名された KB-JWT
+        let other_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let forged = present_with_key_binding(&sd_jwt, &other_key, Utc::now().timestamp());
+        let result = verify_sd_jwt_credential(&forged, &options("example.com", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::InvalidKeyBinding(_))));
+
+        // 別の SD-JWT に対して作成された KB-JWT（sd_hash 不一致）
+        let (_, _, key_binding_jwt) = sd_jwt::split(&forged);
+        let other_sd_jwt = create_test_bound_sd_jwt(jwk::to_jwk(&holder_key.public_key()));
+        let valid = present_with_key_binding(&other_sd_jwt, &holder_key, Utc::now().timestamp());
+        let (_, _, valid_key_binding_jwt) = sd_jwt::split(&valid);
+        assert!(key_binding_jwt.is_some());
+        let mismatched = format!("{}{}", sd_jwt, valid_key_binding_jwt.unwrap());
+        let result = verify_sd_jwt_credential(&mismatched, &options("example.com", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::InvalidKeyBinding(_))));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_presentation_with_key_binding() {
+        let holder = Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        );
+        let sd_jwt = create_test_bound_sd_jwt(holder.public_jwk());
+        let mut credential = create_test_sd_jwt_credential();
+        credential.sd_jwt = Some(sd_jwt);
+        let credential_id = holder.store_credential(credential).unwrap();
+
+        let presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![credential_id],
+                domain: "example.com".to_string(),
+                challenge: "nonce-1".to_string(),
+                disclose: None,
+            })
+            .unwrap();
+
+        let result = verify_presentation(&presentation, &options("example.com", "nonce-1"));
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        let result = verify_presentation(&presentation, &options("example.com", "nonce-2"));
+        assert!(matches!(result, Err(VerifierError::NonceMismatch)));
+
+        // 別の Holder を名乗る VP
+        let mut forged = presentation.clone();
+        forged.holder = Some(
+            Holder::new(
+                Arc::new(TestStorage::new()),
+                SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+            )
+            .did()
+            .unwrap(),
+        );
+        let result = verify_presentation(&forged, &options("example.com", "nonce-1"));
+        assert!(matches!(result, Err(VerifierError::HolderMismatch)));
+
+        // 盗んだ SD-JWT と KB-JWT を自分の鍵で署名した VP に入れ、proof に KB-JWT の aud / nonce を写しても
+        // Verifier が新しい challenge を指定していれば受け入れない
+        let attacker_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let attacker_did = did::key::did_key(&attacker_key.public_key()).unwrap();
+        let mut replayed = presentation.clone();
+        replayed.holder = Some(attacker_did.clone());
+        replayed.proof = None;
+        let proof_options = ProofOptions {
+            domain: Some("example.com".to_string()),
+            challenge: Some("nonce-1".to_string()),
+            ..ProofOptions::default()
+        };
+        replayed.proof = Some(
+            crypto::create_proof(
+                &serde_json::to_value(&replayed).unwrap(),
+                &attacker_key,
+                &did::key::key_id(&attacker_did).unwrap(),
+                &proof_options,
+            )
+            .unwrap(),
+        );
+        let result = verify_presentation(&replayed, &options("example.com", "nonce-2"));
+        assert!(matches!(result, Err(VerifierError::NonceMismatch)));
+        let result = verify_presentation(&replayed, &default_options());
+        assert!(matches!(
+            result,
+            Err(VerifierError::MissingKeyBindingContext)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_reconstructs_nested_claims() {
+        let credential_subject = serde_json::json!({
+            "given_name": "Alice",
+            "family_name": "Smith",
+            "address": {
+                "street_address": "123 Main St",
+                "locality": "Anytown",
+                "country": "JP"
+            },
+            "nationalities": ["JP", "US"]
+        });
+        let request = SDJWTCredentialRequest {
+            credential_subject: credential_subject.clone(),
+            selective_disclosure: Some(vec![
+                "address".to_string(),
+                "address.locality".to_string(),
+                "nationalities[]".to_string(),
+            ]),
+            holder_jwk: None,
+            validity_seconds: None,
+        };
+        let sd_jwt = create_sd_jwt_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap()
+        .sd_jwt
+        .unwrap();
+
+        let claims = verify_sd_jwt_credential(&sd_jwt, &default_options()).unwrap();
+        assert_eq!(
+            claims["iss"],
+            crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap()
+        );
+        for (name, value) in credential_subject.as_object().unwrap() {
+            assert_eq!(&claims[name], value, "Claim {} was not reconstructed", name);
+        }
+        assert!(claims["address"].get("_sd").is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_presentation_returns_disclosed_claims_only() {
+        let holder = Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        );
+        let credential_id = holder
+            .store_credential(create_test_sd_jwt_credential())
+            .unwrap();
+
+        let mut disclose = std::collections::HashMap::new();
+        disclose.insert(credential_id.clone(), vec!["email".to_string()]);
+        let presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![credential_id],
+                domain: "example.com".to_string(),
+                challenge: "nonce-1".to_string(),
+                disclose: Some(disclose),
+            })
+            .unwrap();
+
+        let claims =
+            verify_presentation(&presentation, &options("example.com", "nonce-1")).unwrap();
+        assert_eq!(claims[0]["email"], "alice@example.com");
+        assert!(claims[0].get("birthdate").is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_rejects_invalid_disclosures() {
+        let sd_jwt = create_test_sd_jwt_credential().sd_jwt.unwrap();
+        let (jwt, disclosures, _) = sd_jwt::split(&sd_jwt);
+
+        // 同じ Disclosure を二度提示する
+        let mut duplicated: Vec<String> = disclosures.iter().map(|d| d.to_string()).collect();
+        duplicated.push(disclosures[0].to_string());
+        let result =
+            verify_sd_jwt_credential(&sd_jwt::combine(jwt, &duplicated), &default_options());
+        assert!(matches!(result, Err(VerifierError::DuplicateDisclosure)));
+
+        // SD-JWT のどこからも参照されない Disclosure
+        let unreferenced = vec![sd_jwt::create_disclosure(
+            "salt",
+            "email",
+            &serde_json::json!("mallory@example.com"),
+        )];
+        let result =
+            verify_sd_jwt_credential(&sd_jwt::combine(jwt, &unreferenced), &default_options());
+        assert!(matches!(result, Err(VerifierError::UnreferencedDisclosure)));
+    }
+
+    #[actix_rt::test]
+    async fn test_process_payload_rejects_malformed_digests() {
+        let disclosure =
+            sd_jwt::create_disclosure("salt", "email", &serde_json::json!("alice@example.com"));
+        let digest = sd_jwt::hash_disclosure(SdAlg::Sha256, &disclosure);
+
+        // 既存のクレームと同名のクレームを開示する
+        let payload = serde_json::json!({ "email": "bob@example.com", "_sd": [digest] });
+        let result =
+            sd_jwt_processor::process_payload(&payload, SdAlg::Sha256, &[disclosure.as_str()]);
+        assert!(matches!(result, Err(VerifierError::ClaimNameCollision(_))));
+
+        // 同じダイジェストが複数回現れる
+        let payload = serde_json::json!({ "_sd": [digest], "nested": { "_sd": [digest] } });
+        let result =
+            sd_jwt_processor::process_payload(&payload, SdAlg::Sha256, &[disclosure.as_str()]);
+        assert!(matches!(result, Err(VerifierError::DuplicateDigest)));
+
+        // 配列要素のダイジェストにオブジェクト用の Disclosure を対応させる
+        let payload = serde_json::json!({ "items": [{ "...": digest }] });
+        let result =
+            sd_jwt_processor::process_payload(&payload, SdAlg::Sha256, &[disclosure.as_str()]);
+        assert!(matches!(result, Err(VerifierError::InvalidDisclosure(_))));
+
+        // 対応する Disclosure のないダイジェスト（デコイ）は無視する
+        let payload = serde_json::json!({ "_sd": [digest, "decoy"], "_sd_alg": "sha-256" });
+        let claims =
+            sd_jwt_processor::process_payload(&payload, SdAlg::Sha256, &[disclosure.as_str()])
+                .unwrap();
+        assert_eq!(claims, serde_json::json!({ "email": "alice@example.com" }));
+    }
+
+    fn create_test_sd_jwt_with_alg(sd_alg: &str, digest: String, disclosure: String) -> String {
+        let payload = serde_json::json!({
+            "iss": crypto::issuer_did(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap(),
+            "iat": Utc::now().timestamp(),
+            "vct": "SDJWTCredential",
+            "_sd_alg": sd_alg,
+            "_sd": [digest],
+        });
+        let jwt = crypto::sign_sd_jwt(
+            &payload,
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+        )
+        .unwrap();
+        sd_jwt::combine(&jwt, &[disclosure])
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_hash_algorithms() {
+        let disclosure =
+            sd_jwt::create_disclosure("salt", "email", &serde_json::json!("alice@example.com"));
+
+        for alg in [SdAlg::Sha256, SdAlg::Sha384, SdAlg::Sha512, SdAlg::Sha3_256] {
+            let digest = sd_jwt::hash_disclosure(alg, &disclosure);
+            let presented = create_test_sd_jwt_with_alg(alg.name(), digest, disclosure.clone());
+            let claims = verify_sd_jwt_credential(&presented, &default_options()).unwrap();
+            assert_eq!(claims["email"], "alice@example.com");
+        }
+
+        // _sd_alg と異なるアルゴリズムのダイジェストは Disclosure と対応しない
+        let digest = sd_jwt::hash_disclosure(SdAlg::Sha256, &disclosure);
+        let presented = create_test_sd_jwt_with_alg("sha-512", digest.clone(), disclosure.clone());
+        let result = verify_sd_jwt_credential(&presented, &default_options());
+        assert!(matches!(result, Err(VerifierError::UnreferencedDisclosure)));
+
+        let presented = create_test_sd_jwt_with_alg("md5", digest, disclosure);
+        let result = verify_sd_jwt_credential(&presented, &default_options());
+        assert!(matches!(result, Err(VerifierError::UnsupportedSdAlg(_))));
+    }
+
+    fn sign_test_sd_jwt(typ: &str, claims: serde_json::Value) -> String {
+        let key_manager = issuer_key_manager();
+        let key = key_manager.get_current_key().unwrap();
+        let signer = key_manager.get_signer(&key.kid).unwrap();
+        let mut payload = serde_json::json!({
+            "iss": crypto::issuer_did(key_manager.as_ref(), &IssuerConfig::default()).unwrap(),
+            "vct": "http://localhost:8080/issuer/vct/SDJWTCredential",
+            "_sd_alg": "sha-256",
+        });
+        for (key, value) in claims.as_object().unwrap() {
+            payload[key] = value.clone();
+        }
+        let header = serde_json::json!({ "typ": typ });
+        let jwt = crypto::sign_jwt(&header, &payload, signer.as_ref()).unwrap();
+        sd_jwt::combine(&jwt, &[])
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_typ() {
+        let now = Utc::now().timestamp();
+        let claims = serde_json::json!({ "iat": now });
+
+        for typ in ["dc+sd-jwt", "vc+sd-jwt"] {
+            let presented = sign_test_sd_jwt(typ, claims.clone());
+            let result = verify_sd_jwt_credential(&presented, &default_options());
+            assert!(result.is_ok(), "{} was rejected: {:?}", typ, result.err());
+        }
+
+        let presented = sign_test_sd_jwt("JWT", claims);
+        let result = verify_sd_jwt_credential(&presented, &default_options());
+        assert!(matches!(result, Err(VerifierError::InvalidSdJwtType(_))));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_validity_period() {
+        let now = Utc::now().timestamp();
+        let verify = |claims: serde_json::Value, options: &VerificationOptions| {
+            verify_sd_jwt_credential(&sign_test_sd_jwt("dc+sd-jwt", claims), options)
+        };
+        let default = default_options();
+
+        let result = verify(
+            serde_json::json!({ "iat": now - 120, "exp": now - 60 }),
+            &default,
+        );
+        assert!(matches!(result, Err(VerifierError::CredentialExpired)));
+
+        let result = verify(
+            serde_json::json!({ "iat": now, "nbf": now + 3600 }),
+            &default,
+        );
+        assert!(matches!(result, Err(VerifierError::CredentialNotYetValid)));
+
+        let result = verify(serde_json::json!({ "iat": now + 3600 }), &default);
+        assert!(matches!(result, Err(VerifierError::InvalidIssuedAt)));
+
+        // clock_skew の範囲内のずれは許容する
+        let result = verify(
+            serde_json::json!({ "iat": now + 30, "exp": now - 30 }),
+            &default,
+        );
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        let strict = VerificationOptions {
+            clock_skew: Duration::zero(),
+            ..default_options()
+        };
+        let result = verify(serde_json::json!({ "iat": now, "exp": now - 30 }), &strict);
+        assert!(matches!(result, Err(VerifierError::CredentialExpired)));
+    }
+
+    fn published_type_metadata() -> InMemoryTypeMetadataResolver {
+        let mut resolver = InMemoryTypeMetadataResolver::new();
+        for (vct, document) in type_metadata::published_type_metadata() {
+            resolver.insert(&vct, document);
+        }
+        resolver
+    }
+
+    fn with_type_metadata(resolver: impl TypeMetadataResolver + 'static) -> VerificationOptions {
+        VerificationOptions {
+            type_metadata: Some(Arc::new(resolver)),
+            ..default_options()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_type_metadata() {
+        let sd_jwt = create_test_sd_jwt_credential().sd_jwt.unwrap();
+
+        let options = with_type_metadata(published_type_metadata());
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        let options = with_type_metadata(InMemoryTypeMetadataResolver::new());
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::TypeMetadataNotFound(_))
+        ));
+
+        // 処理済みのペイロードが JSON Schema に適合しない
+        let presented = sign_test_sd_jwt(
+            "dc+sd-jwt",
+            serde_json::json!({ "iat": Utc::now().timestamp(), "given_name": 42 }),
+        );
+        let options = with_type_metadata(published_type_metadata());
+        let result = verify_sd_jwt_credential(&presented, &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::TypeSchemaValidationFailed(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_type_metadata_integrity() {
+        let sd_jwt = create_test_sd_jwt_credential().sd_jwt.unwrap();
+        let sd_jwt_vct = "http://localhost:8080/issuer/vct/SDJWTCredential";
+        let person_vct = "http://localhost:8080/issuer/vct/PersonCredential";
+
+        let tamper = |vct: &str| {
+            let mut resolver = published_type_metadata();
+            let mut metadata: serde_json::Value =
+                serde_json::from_slice(&resolver.resolve(vct).unwrap()).unwrap();
+            metadata["name"] = serde_json::json!("Tampered");
+            resolver.insert(vct, serde_json::to_vec(&metadata).unwrap());
+            resolver
+        };
+
+        // vct#integrity と一致しない Type Metadata
+        let options = with_type_metadata(tamper(sd_jwt_vct));
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::TypeMetadataIntegrityMismatch(vct)) if vct == sd_jwt_vct
+        ));
+
+        // extends#integrity と一致しない継承元の Type Metadata
+        let options = with_type_metadata(tamper(person_vct));
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::TypeMetadataIntegrityMismatch(vct)) if vct == person_vct
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_file_type_metadata_resolver() {
+        let directory =
+            std::env::temp_dir().join(format!("type-metadata-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (index, (_, document)) in type_metadata::published_type_metadata()
+            .into_iter()
+            .enumerate()
+        {
+            std::fs::write(directory.join(format!("{}.json", index)), document).unwrap();
+        }
+
+        let resolver = FileTypeMetadataResolver::new(&directory).unwrap();
+        let sd_jwt = create_test_sd_jwt_credential().sd_jwt.unwrap();
+        let result = verify_sd_jwt_credential(&sd_jwt, &with_type_metadata(resolver));
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+    }
+
+    fn test_signing_keys() -> Vec<SigningKey> {
+        vec![
+            SigningKey::Ed25519(Keypair::generate(&mut OsRng)),
+            SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng)),
+            SigningKey::P384(p384::ecdsa::SigningKey::random(&mut rand_core::OsRng)),
+            SigningKey::Rsa(rsa::RsaPrivateKey::new(&mut rand_core::OsRng, 2048).unwrap()),
+        ]
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_jwt_algorithms() {
+        let payload = serde_json::json!({ "iss": "did:example:123" });
+        for key in test_signing_keys() {
+            let jwt =
+                crypto::sign_jwt(&serde_json::json!({ "typ": "JWT" }), &payload, &key).unwrap();
+            let (header, _) = crypto::decode_jwt(&jwt).unwrap();
+            assert_eq!(header["alg"], key.algorithm().name());
+
+            // JWK を経由しても同じ公開鍵で検証できる
+            let public_key = jwk::from_jwk(&jwk::to_jwk(&key.public_key())).unwrap();
+            assert_eq!(public_key, key.public_key());
+            let result = crypto::verify_jwt(&jwt, &public_key, &SignatureAlgorithm::ALL);
+            assert!(result.is_ok(), "{:?}: {:?}", key.algorithm(), result.err());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_jwt_rejects_disallowed_algorithms() {
+        let payload = serde_json::json!({ "iss": "did:example:123" });
+        let key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let jwt = crypto::sign_jwt(&serde_json::json!({ "typ": "JWT" }), &payload, &key).unwrap();
+
+        let result = crypto::verify_jwt(&jwt, &key.public_key(), &[SignatureAlgorithm::EdDSA]);
+        assert!(matches!(result, Err(UtilsError::AlgorithmNotAllowed(alg)) if alg == "ES256"));
+
+        // alg と鍵の種類が一致しなければ署名を検証しない
+        let other_key = SigningKey::P384(p384::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let result = crypto::verify_jwt(&jwt, &other_key.public_key(), &SignatureAlgorithm::ALL);
+        assert!(matches!(result, Err(UtilsError::SignatureError(_))));
+
+        // alg を none に書き換えた JWT は受け入れない
+        let (_, payload_and_signature) = jwt.split_once('.').unwrap();
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let forged = format!("{}.{}", header, payload_and_signature);
+        let result = crypto::verify_jwt(&forged, &key.public_key(), &SignatureAlgorithm::ALL);
+        assert!(matches!(result, Err(UtilsError::UnsupportedAlgorithm(alg)) if alg == "none"));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_key_binding_with_es256_holder_key() {
+        let holder_key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let sd_jwt = create_test_bound_sd_jwt(jwk::to_jwk(&holder_key.public_key()));
+        let presented = present_with_key_binding(&sd_jwt, &holder_key, Utc::now().timestamp());
+
+        let result = verify_sd_jwt_credential(&presented, &options("example.com", "nonce-1"));
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        let options = VerificationOptions {
+            allowed_algorithms: vec![SignatureAlgorithm::EdDSA],
+            ..options("example.com", "nonce-1")
+        };
+        let result = verify_sd_jwt_credential(&presented, &options);
+        assert!(matches!(result, Err(VerifierError::AlgorithmNotAllowed(alg)) if alg == "ES256"));
+    }
+
+    fn sign_partner_sd_jwt(signer: &dyn Signer) -> String {
+        let header = serde_json::json!({
+            "typ": sd_jwt::SD_JWT_VC_TYPE,
+            "kid": "did:example:partner#key-1",
+        });
+        let payload = serde_json::json!({
+            "iss": "did:example:partner",
+            "vct": "http://localhost:8080/issuer/vct/SDJWTCredential",
+            "_sd_alg": "sha-256",
+        });
+        let jwt = crypto::sign_jwt(&header, &payload, signer).unwrap();
+        sd_jwt::combine(&jwt, &[])
+    }
+
+    fn create_test_v2_credential() -> CredentialResponse {
+        let request = CredentialRequest {
+            context: vec!["https://www.w3.org/ns/credentials/v2".to_string()],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "EmploymentCredential".to_string(),
+            ],
+            credential_subject: serde_json::json!({
+                "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+                "name": "Alice",
+                "employer": "Example Corp"
+            }),
+            ..Default::default()
+        };
+        create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap()
+    }
+
+    // 署名し直して、データモデルの検証だけで拒否されることを確認する
+    fn resign(mut credential: CredentialResponse) -> CredentialResponse {
+        credential.proof = None;
+        let credential_json = serde_json::to_value(&credential).unwrap();
+        credential.proof = Some(
+            crypto::sign_json(
+                &credential_json,
+                issuer_key_manager().as_ref(),
+                &IssuerConfig::default(),
+            )
+            .unwrap(),
+        );
+        credential
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_data_model_versions() {
+        for credential in [create_test_credential(), create_test_v2_credential()] {
+            let result = verify_credential(&credential, &default_options());
+            assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+        }
+
+        // JSON の issuer オブジェクトからも読み込める
+        let json = serde_json::to_value(create_test_v2_credential()).unwrap();
+        assert_eq!(json["issuer"]["name"], "Example University");
+        let credential: CredentialResponse = serde_json::from_value(json).unwrap();
+        let result = verify_credential(&credential, &default_options());
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        let mut invalid = Vec::new();
+        // 1.1 には issuanceDate が必須で、validFrom は定義されていない
+        let mut credential = create_test_credential();
+        credential.issuance_date = None;
+        invalid.push(credential);
+        let mut credential = create_test_credential();
+        credential.valid_from = Some(Utc::now().to_rfc3339());
+        invalid.push(credential);
+        // 2.0 には issuanceDate は定義されていない
+        let mut credential = create_test_v2_credential();
+        credential.issuance_date = Some(Utc::now().to_rfc3339());
+        invalid.push(credential);
+        let mut credential = create_test_v2_credential();
+        credential.valid_until = Some("2000-01-01T00:00:00Z".to_string());
+        invalid.push(credential);
+        let mut credential = create_test_v2_credential();
+        credential.valid_from = Some("yesterday".to_string());
+        invalid.push(credential);
+        let mut credential = create_test_v2_credential();
+        credential.credential_schema = Some(serde_json::json!({ "type": "JsonSchema" }));
+        invalid.push(credential);
+        let mut credential = create_test_v2_credential();
+        credential.context = vec!["https://example.com/credentials/v3".to_string()];
+        invalid.push(credential);
+        let mut credential = create_test_v2_credential();
+        credential.types = vec!["EmploymentCredential".to_string()];
+        invalid.push(credential);
+
+        for credential in invalid {
+            let result = verify_credential(&resign(credential), &default_options());
+            assert!(
+                matches!(result, Err(VerifierError::InvalidDataModel(_))),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    fn options_at(now: chrono::DateTime<Utc>) -> VerificationOptions {
+        VerificationOptions {
+            clock: Arc::new(FixedClock(now)),
+            ..options("example.com", "nonce-1")
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_validity_periods() {
+        let now = Utc::now();
+
+        // 1.1 は issuanceDate から expirationDate まで
+        let request = CredentialRequest {
+            context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "UniversityDegreeCredential".to_string(),
+            ],
+            credential_subject: serde_json::json!({
+                "name": "Alice",
+                "degree": { "type": "BachelorDegree" }
+            }),
+            validity_seconds: Some(3600),
+            ..Default::default()
+        };
+        let credential = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        assert!(credential.expiration_date.is_some());
+        assert!(verify_credential(&credential, &options_at(now)).is_ok());
+        let result = verify_credential(&credential, &options_at(now + Duration::hours(2)));
+        assert!(matches!(result, Err(VerifierError::CredentialExpired)));
+        let result = verify_credential(&credential, &options_at(now - Duration::hours(1)));
+        assert!(matches!(result, Err(VerifierError::CredentialNotYetValid)));
+
+        // 2.0 は validFrom から validUntil まで（EmploymentCredential の有効期間は 1 年）
+        let credential = create_test_v2_credential();
+        assert!(credential.valid_until.is_some());
+        assert!(verify_credential(&credential, &options_at(now + Duration::days(300))).is_ok());
+        let result = verify_credential(&credential, &options_at(now + Duration::days(400)));
+        assert!(matches!(result, Err(VerifierError::CredentialExpired)));
+        let result = verify_credential(&credential, &options_at(now - Duration::hours(1)));
+        assert!(matches!(result, Err(VerifierError::CredentialNotYetValid)));
+
+        // SD-JWT VC の iat / exp も同じ時計で判定する
+        let credential = create_test_sd_jwt_credential();
+        let result = verify_credential(&credential, &options_at(now + Duration::days(400)));
+        assert!(matches!(result, Err(VerifierError::CredentialExpired)));
+        let result = verify_credential(&credential, &options_at(now - Duration::hours(1)));
+        assert!(matches!(result, Err(VerifierError::InvalidIssuedAt)));
+
+        // VP は proof の created から expires まで
+        let holder = Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        );
+        let credential_id = holder.store_credential(create_test_credential()).unwrap();
+        let presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![credential_id],
+                domain: "example.com".to_string(),
+                challenge: "nonce-1".to_string(),
+                disclose: None,
+            })
+            .unwrap();
+        assert!(verify_presentation(&presentation, &options("example.com", "nonce-1")).is_ok());
+        let result = verify_presentation(&presentation, &options_at(now - Duration::hours(1)));
+        assert!(matches!(
+            result,
+            Err(VerifierError::PresentationNotYetValid)
+        ));
+
+        // expires も署名の対象なので、expires を指定して署名し直す
+        let holder_key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let holder_did = did::key::did_key(&holder_key.public_key()).unwrap();
+        let mut expired = presentation.clone();
+        expired.holder = Some(holder_did.clone());
+        expired.proof = None;
+        let options = ProofOptions {
+            expires: Some(now.to_rfc3339()),
+            ..ProofOptions::default()
+        };
+        expired.proof = Some(
+            crypto::create_proof(
+                &serde_json::to_value(&expired).unwrap(),
+                &holder_key,
+                &did::key::key_id(&holder_did).unwrap(),
+                &options,
+            )
+            .unwrap(),
+        );
+        let result = verify_presentation(&expired, &options_at(now + Duration::minutes(5)));
+        assert!(matches!(result, Err(VerifierError::PresentationExpired)));
+
+        // 署名した後に expires を延ばした VP は署名の検証で拒否する
+        let mut extended = expired.clone();
+        extended.proof.as_mut().unwrap()["expires"] = (now + Duration::days(1)).to_rfc3339().into();
+        let result = verify_presentation(&extended, &options_at(now + Duration::minutes(5)));
+        assert!(matches!(
+            result,
+            Err(VerifierError::SignatureVerificationFailed(_))
+        ));
+    }
+
+    // did:key の Ed25519 鍵で、指定した cryptosuite の Data Integrity proof を付ける
+    fn sign_with_cryptosuite(
+        mut credential: CredentialResponse,
+        cryptosuite: Cryptosuite,
+    ) -> (CredentialResponse, VerifyingKey) {
+        let key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let issuer = did::key::did_key(&key.public_key()).unwrap();
+        credential.issuer = Issuer::Id(issuer.clone());
+        credential.proof = None;
+        let options = ProofOptions {
+            cryptosuite,
+            ..ProofOptions::default()
+        };
+        credential.proof = Some(
+            crypto::create_proof(
+                &serde_json::to_value(&credential).unwrap(),
+                &key,
+                &did::key::key_id(&issuer).unwrap(),
+                &options,
+            )
+            .unwrap(),
+        );
+        (credential, key.public_key())
+    }
+
+    #[actix_rt::test]
+    async fn test_jws_proof_signs_jcs() {
+        let key = SigningKey::generate(SignatureAlgorithm::ES256).unwrap();
+        let issuer = did::key::did_key(&key.public_key()).unwrap();
+        let mut credential = create_test_credential();
+        credential.issuer = Issuer::Id(issuer.clone());
+        credential.credential_subject["gpa"] = serde_json::json!(3.50);
+        credential.credential_subject["credits"] = serde_json::json!(1e21);
+        credential.proof = None;
+
+        // detached JWS のペイロードは、JCS で正規化した proof の設定と文書それぞれのハッシュ
+        let document = serde_json::to_value(&credential).unwrap();
+        let canonical = jcs::canonicalize(&document).unwrap();
+        assert!(canonical.contains(r#""credits":1e+21"#) && canonical.contains(r#""gpa":3.5"#));
+        let options = ProofOptions {
+            expires: Some("2100-01-01T00:00:00Z".to_string()),
+            ..ProofOptions::default()
+        };
+        let proof = crypto::create_proof(
+            &document,
+            &key,
+            &did::key::key_id(&issuer).unwrap(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(proof["type"], "JsonWebSignature2020");
+        let jws = proof["jws"].as_str().unwrap();
+        let (header, signature) = jws.split_once("..").unwrap();
+        let mut proof_options = proof.clone();
+        proof_options.as_object_mut().unwrap().remove("jws");
+        let mut message = format!("{}.", header).into_bytes();
+        message.extend(data_integrity::jcs_hash_data(&document, &proof_options).unwrap());
+        let signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+        assert!(key.public_key().verify(&message, &signature).is_ok());
+
+        credential.proof = Some(proof);
+        let result = verify_credential_with_key(&credential, &key.public_key());
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        // proof の expires を取り除いたり延ばしたりすると検証できない
+        for expires in [None, Some("2200-01-01T00:00:00Z")] {
+            let mut tampered = credential.clone();
+            let proof = tampered.proof.as_mut().unwrap();
+            match expires {
+                Some(expires) => proof["expires"] = expires.into(),
+                None => {
+                    proof.as_object_mut().unwrap().remove("expires");
+                }
+            }
+            let result = verify_credential_with_key(&tampered, &key.public_key());
+            assert!(
+                matches!(result, Err(VerifierError::SignatureVerificationFailed(_))),
+                "{:?}",
+                result
+            );
+        }
+
+        // 署名する proof の設定を持たない、以前の Ed25519Signature2020 の proof は受け付けない
+        let key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let issuer = did::key::did_key(&key.public_key()).unwrap();
+        let mut credential = create_test_credential();
+        credential.issuer = Issuer::Id(issuer.clone());
+        credential.proof = None;
+        let message = serde_json::to_string(&serde_json::to_value(&credential).unwrap()).unwrap();
+        credential.proof = Some(serde_json::json!({
+            "type": "Ed25519Signature2020",
+            "created": Utc::now().to_rfc3339(),
+            "verificationMethod": did::key::key_id(&issuer).unwrap(),
+            "proofPurpose": "assertionMethod",
+            "proofValue": bs58::encode(key.sign(message.as_bytes()).unwrap()).into_string(),
+        }));
+        let result = verify_credential_with_key(&credential, &key.public_key());
+        assert!(
+            matches!(result, Err(VerifierError::SignatureVerificationFailed(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_data_integrity_proofs() {
+        // 発行した VC は既定で eddsa-jcs-2022 の DataIntegrityProof
+        let credential = create_test_credential();
+        let proof = credential.proof.as_ref().unwrap();
+        assert_eq!(proof["type"], "DataIntegrityProof");
+        assert_eq!(proof["cryptosuite"], "eddsa-jcs-2022");
+        assert!(proof["proofValue"].as_str().unwrap().starts_with('z'));
+        assert!(verify_credential(&credential, &default_options()).is_ok());
+
+        // eddsa-rdfc-2022 には、すべての用語を定義したコンテキストが必要
+        let mut v1 = create_test_credential();
+        v1.context = vec![
+            "https://www.w3.org/2018/credentials/v1".to_string(),
+            DATA_INTEGRITY_CONTEXT.to_string(),
+            "https://www.w3.org/2018/credentials/examples/v1".to_string(),
+        ];
+        let mut v2 = create_test_v2_credential();
+        v2.context
+            .push("https://www.w3.org/ns/credentials/examples/v2".to_string());
+
+        for credential in [v1, v2] {
+            for cryptosuite in Cryptosuite::ALL {
+                let (credential, key) = sign_with_cryptosuite(credential.clone(), cryptosuite);
+                assert_eq!(
+                    credential.proof.as_ref().unwrap()["cryptosuite"],
+                    cryptosuite.name()
+                );
+                let result = verify_credential_with_key(&credential, &key);
+                assert!(result.is_ok(), "{}: {:?}", cryptosuite.name(), result);
+
+                // credentialSubject と proof の設定はどちらも署名の対象
+                let mut tampered = credential.clone();
+                tampered.credential_subject["name"] = "Mallory".into();
+                let result = verify_credential_with_key(&tampered, &key);
+                assert!(matches!(
+                    result,
+                    Err(VerifierError::SignatureVerificationFailed(_))
+                ));
+                let mut tampered = credential.clone();
+                tampered.proof.as_mut().unwrap()["created"] = "2000-01-01T00:00:00Z".into();
+                let result = verify_credential_with_key(&tampered, &key);
+                assert!(matches!(
+                    result,
+                    Err(VerifierError::SignatureVerificationFailed(_))
+                ));
+            }
+        }
+
+        // 定義されていない用語（degree）は RDF に変換できないので署名しない
+        let mut credential = create_test_credential();
+        credential.context.push(DATA_INTEGRITY_CONTEXT.to_string());
+        credential.proof = None;
+        let options = ProofOptions {
+            cryptosuite: Cryptosuite::EddsaRdfc2022,
+            ..ProofOptions::default()
+        };
+        let key = SigningKey::Ed25519(Keypair::generate(&mut OsRng));
+        let result = crypto::create_proof(
+            &serde_json::to_value(&credential).unwrap(),
+            &key,
+            "did:example:issuer#key-1",
+            &options,
+        );
+        assert!(
+            matches!(&result, Err(UtilsError::JsonLdError(message)) if message.contains("degree")),
+            "{:?}",
+            result
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_issued_credentials_sign_with_rdfc() {
+        // Issuer のコンテキストがクレデンシャルタイプの用語を定義するので、
+        // 例のコンテキストなしで発行した VC も RDF に変換して署名できる
+        let request = CredentialRequest {
+            context: vec!["https://www.w3.org/2018/credentials/v1".to_string()],
+            types: vec![
+                "VerifiableCredential".to_string(),
+                "UniversityDegreeCredential".to_string(),
+            ],
+            credential_subject: serde_json::json!({
+                "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+                "name": "Alice",
+                "degree": {
+                    "type": "BachelorDegree",
+                    "name": "Bachelor of Science in Mechanical Engineering"
+                }
+            }),
+            ..Default::default()
+        };
+        let v1 = create_credential(
+            issuer_key_manager().as_ref(),
+            &IssuerConfig::default(),
+            request,
+        )
+        .unwrap();
+        for credential in [v1, create_test_v2_credential()] {
+            let (credential, key) = sign_with_cryptosuite(credential, Cryptosuite::EddsaRdfc2022);
+            let result = verify_credential_with_key(&credential, &key);
+            assert!(result.is_ok(), "{:?}", result);
+
+            let nquads =
+                json_ld::canonicalize(&serde_json::to_value(&credential).unwrap()).unwrap();
+            assert!(nquads.contains("<http://localhost:8080/issuer/vocab#"));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_presentation_proof_signs_domain_and_challenge() {
+        let holder = Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        );
+        let credential_id = holder.store_credential(create_test_credential()).unwrap();
+        let presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![credential_id],
+                domain: "example.com".to_string(),
+                challenge: "nonce-1".to_string(),
+                disclose: None,
+            })
+            .unwrap();
+        let proof = presentation.proof.as_ref().unwrap();
+        assert_eq!(proof["type"], "DataIntegrityProof");
+        assert_eq!(proof["domain"], "example.com");
+        assert_eq!(proof["challenge"], "nonce-1");
+        assert!(verify_presentation(&presentation, &options("example.com", "nonce-1")).is_ok());
+
+        for (name, value) in [("domain", "attacker.example"), ("challenge", "nonce-2")] {
+            let mut tampered = presentation.clone();
+            tampered.proof.as_mut().unwrap()[name] = value.into();
+            let result = verify_presentation(&tampered, &options("example.com", "nonce-1"));
+            assert!(
+                matches!(result, Err(VerifierError::SignatureVerificationFailed(_))),
+                "{}: {:?}",
+                name,
+                result
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_jws_presentation_proof_signs_domain_and_challenge() {
+        // Ed25519 以外の Holder の鍵で署名した VP でも domain と challenge は署名の対象
+        let holder = Holder::new(
+            Arc::new(TestStorage::new()),
+            SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap(),
+        );
+        let credential_id = holder.store_credential(create_test_credential()).unwrap();
+        let mut presentation = holder
+            .create_presentation(PresentationRequest {
+                verifiable_credential: vec![credential_id],
+                domain: "example.com".to_string(),
+                challenge: "nonce-1".to_string(),
+                disclose: None,
+            })
+            .unwrap();
+
+        let key = SigningKey::generate(SignatureAlgorithm::ES256).unwrap();
+        let holder_did = did::key::did_key(&key.public_key()).unwrap();
+        presentation.holder = Some(holder_did.clone());
+        presentation.proof = None;
+        let proof_options = ProofOptions {
+            domain: Some("example.com".to_string()),
+            challenge: Some("nonce-1".to_string()),
+            ..ProofOptions::default()
+        };
+        presentation.proof = Some(
+            crypto::create_proof(
+                &serde_json::to_value(&presentation).unwrap(),
+                &key,
+                &did::key::key_id(&holder_did).unwrap(),
+                &proof_options,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            presentation.proof.as_ref().unwrap()["type"],
+            "JsonWebSignature2020"
+        );
+        let result = verify_presentation(&presentation, &options("example.com", "nonce-1"));
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        for (name, value) in [("domain", "attacker.example"), ("challenge", "nonce-2")] {
+            let mut tampered = presentation.clone();
+            tampered.proof.as_mut().unwrap()[name] = value.into();
+            let result = verify_presentation(&tampered, &options("example.com", "nonce-1"));
+            assert!(
+                matches!(result, Err(VerifierError::SignatureVerificationFailed(_))),
+                "{}: {:?}",
+                name,
+                result
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_rejects_unknown_issuer() {
+        // 鍵のリゾルバを指定しなければ、どの Issuer も信頼しない
+        let credential = create_test_credential();
+        let result = verify_credential(&credential, &VerificationOptions::default());
+        assert!(
+            matches!(result, Err(VerifierError::UnknownIssuer(issuer)) if credential.issuer == issuer)
+        );
+
+        let sd_jwt = sign_test_sd_jwt(
+            sd_jwt::SD_JWT_VC_TYPE,
+            serde_json::json!({ "iss": "did:example:unknown" }),
+        );
+        let result = verify_sd_jwt_credential(&sd_jwt, &default_options());
+        assert!(
+            matches!(result, Err(VerifierError::UnknownIssuer(issuer)) if issuer == "did:example:unknown")
+        );
+
+        // Issuer の鍵として登録されていない verificationMethod
+        let mut credential = create_test_credential();
+        credential.proof.as_mut().unwrap()["verificationMethod"] =
+            format!("{}#key-2", credential.issuer).into();
+        let result = verify_credential(&credential, &default_options());
+        assert!(matches!(
+            result,
+            Err(VerifierError::UnknownVerificationMethod(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_resolves_issuer_key_by_kid() {
+        let partner_key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let mut resolver = InMemoryIssuerKeyResolver::new();
+        resolver.insert("did:example:partner", "#key-1", partner_key.public_key());
+        let options = VerificationOptions {
+            issuer_keys: Arc::new(resolver),
+            ..VerificationOptions::default()
+        };
+
+        let sd_jwt = sign_partner_sd_jwt(&partner_key);
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        // 自身の Issuer の鍵しか知らない Verifier は受け入れない
+        let result = verify_sd_jwt_credential(&sd_jwt, &default_options());
+        assert!(matches!(result, Err(VerifierError::UnknownIssuer(_))));
+
+        // kid を騙って別の鍵で署名したもの
+        let other_key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let forged = sign_partner_sd_jwt(&other_key);
+        let result = verify_sd_jwt_credential(&forged, &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::SignatureVerificationFailed(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_file_issuer_key_resolver() {
+        let directory = std::env::temp_dir().join(format!("issuers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let metadata = serde_json::to_vec(
+            &get_metadata(issuer_key_manager().as_ref(), &IssuerConfig::default()).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(directory.join("issuer.json"), metadata).unwrap();
+
+        let resolver =
+            FileIssuerKeyResolver::new(&directory, Arc::new(DidResolverRegistry::default()));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let options = VerificationOptions {
+            issuer_keys: Arc::new(resolver.unwrap()),
+            ..VerificationOptions::default()
+        };
+
+        let result = verify_credential(&create_test_credential(), &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+        let sd_jwt = create_test_sd_jwt_credential().sd_jwt.unwrap();
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_from_did_web_issuer() {
+        let mut server = mockito::Server::new_async().await;
+        let did = mock_did_web(&server);
+        let partner_key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        server
+            .mock("GET", "/.well-known/did.json")
+            .with_body(did_web_document(&did, &partner_key.public_key()).to_string())
+            .create_async()
+            .await;
+
+        let header = serde_json::json!({
+            "typ": sd_jwt::SD_JWT_VC_TYPE,
+            "kid": format!("{}#key-1", did),
+        });
+        let payload = serde_json::json!({
+            "iss": did,
+            "vct": "http://localhost:8080/issuer/vct/SDJWTCredential",
+            "_sd_alg": "sha-256",
+        });
+        let jwt = crypto::sign_jwt(&header, &payload, &partner_key).unwrap();
+        let sd_jwt = sd_jwt::combine(&jwt, &[]);
+
+        // DID だけを信頼し、鍵は DID ドキュメントから解決する
+        let did_web = Arc::new(
+            DidWebResolver::new(StdDuration::from_secs(60))
+                .with_scheme("http")
+                .allow_private_addresses(),
+        );
+        let did_resolver = DidResolverRegistry::default().with_method("web", did_web);
+        let mut resolver =
+            InMemoryIssuerKeyResolver::new().with_did_resolver(Arc::new(did_resolver));
+        resolver.insert_did(&did);
+        let options = VerificationOptions {
+            issuer_keys: Arc::new(resolver),
+            ..VerificationOptions::default()
+        };
+        let result = verify_sd_jwt_credential(&sd_jwt, &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+
+        // DID ドキュメントにない鍵 ID
+        let header = serde_json::json!({
+            "typ": sd_jwt::SD_JWT_VC_TYPE,
+            "kid": format!("{}#key-2", did),
+        });
+        let jwt = crypto::sign_jwt(&header, &payload, &partner_key).unwrap();
+        let result = verify_sd_jwt_credential(&sd_jwt::combine(&jwt, &[]), &options);
+        assert!(matches!(
+            result,
+            Err(VerifierError::UnknownVerificationMethod(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_sd_jwt_from_registered_did_method() {
+        let partner_key = SigningKey::P256(p256::ecdsa::SigningKey::random(&mut rand_core::OsRng));
+        let stub = StaticDidResolver::new();
+        stub.insert(
+            serde_json::from_value(serde_json::json!({
+                "@context": [did::document::DID_CONTEXT],
+                "id": "did:example:partner",
+                "verificationMethod": [{
+                    "id": "did:example:partner#key-1",
+                    "type": "JsonWebKey2020",
+                    "controller": "did:example:partner",
+                    "publicKeyJwk": jwk::to_jwk(&partner_key.public_key()),
+                }],
+                "assertionMethod": ["#key-1"],
+            }))
+            .unwrap(),
+        );
+        let did_resolver = DidResolverRegistry::default().with_method("example", Arc::new(stub));
+        let mut resolver =
+            InMemoryIssuerKeyResolver::new().with_did_resolver(Arc::new(did_resolver));
+        resolver.insert_did("did:example:partner");
+        let options = VerificationOptions {
+            issuer_keys: Arc::new(resolver),
+            ..VerificationOptions::default()
+        };
+
+        let result = verify_sd_jwt_credential(&sign_partner_sd_jwt(&partner_key), &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+    }
+
+    // 一時的な鍵ファイルの Issuer で、現在の署名鍵を使って VC に署名する
+    fn sign_credential_with(key_manager: &dyn KeyManager) -> CredentialResponse {
+        let key = key_manager.get_current_key().unwrap();
+        let issuer = did::key::did_key(&key.public_key).unwrap();
+        let mut credential = create_test_credential();
+        credential.issuer = Issuer::Id(issuer.clone());
+        credential.proof = None;
+
+        let credential_json = serde_json::to_value(&credential).unwrap();
+        let signer = key_manager.get_signer(&key.kid).unwrap();
+        let key_id = did::key::key_id(&issuer).unwrap();
+        credential.proof = Some(
+            crypto::create_proof(
+                &credential_json,
+                signer.as_ref(),
+                &key_id,
+                &ProofOptions::default(),
+            )
+            .unwrap(),
+        );
+        credential
+    }
+
+    // sign_credential_with で署名した VC を、その did:key の鍵だけを信頼して検証する
+    fn verify_credential_with_key(
+        credential: &CredentialResponse,
+        key: &VerifyingKey,
+    ) -> Result<Value, VerifierError> {
+        let mut resolver = InMemoryIssuerKeyResolver::new();
+        let key_id = did::key::key_id(credential.issuer.id()).unwrap();
+        resolver.insert(credential.issuer.id(), &key_id, key.clone());
+        let options = VerificationOptions {
+            issuer_keys: Arc::new(resolver),
+            ..VerificationOptions::default()
+        };
+        verify_credential(credential, &options)
+    }
+
+    fn metadata_resolver(metadata: &IssuerMetadata) -> VerificationOptions {
+        let mut resolver = InMemoryIssuerKeyResolver::new();
+        resolver.insert_metadata(metadata).unwrap();
+        VerificationOptions {
+            issuer_keys: Arc::new(resolver),
+            ..VerificationOptions::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_credentials_across_key_rotation() {
+        std::env::set_var("ISSUER_ADMIN_TOKEN", "test-admin-token");
+        let signing_key = SigningKey::generate(SignatureAlgorithm::EdDSA).unwrap();
+        let path = std::env::temp_dir().join(format!("keys-{}.json", uuid::Uuid::new_v4()));
+        let key_pair = serde_json::json!({
+            "public_key": bs58::encode(signing_key.public_key().to_bytes().unwrap()).into_string(),
+            "private_key": bs58::encode(signing_key.to_bytes().unwrap()).into_string(),
+        });
+        std::fs::write(&path, key_pair.to_string()).unwrap();
+        let key_manager: Arc<dyn KeyManager> =
+            Arc::new(FileKeyManager::new(path.to_string_lossy().to_string()));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(key_manager.clone()))
+                .app_data(web::Data::new(IssuerConfig::default()))
+                .route(
+                    "/issuer/admin/keys/rotate",
+                    web::post().to(issuer_api::rotate_key),
+                )
+                .route(
+                    "/issuer/admin/keys/reload",
+                    web::post().to(issuer_api::reload_keys),
+                ),
+        )
+        .await;
+        let rotate = |token: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/issuer/admin/keys/rotate")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(body)
+                .to_request()
+        };
+
+        // 管理用のトークンがなければローテーションできない
+        let resp = test::call_service(&app, rotate("wrong-token", serde_json::json!({}))).await;
+        assert_eq!(resp.status().as_u16(), 401);
+
+        let before_rotation = sign_credential_with(key_manager.as_ref());
+        let resp = test::call_service(
+            &app,
+            rotate(
+                "test-admin-token",
+                serde_json::json!({ "algorithm": "ES256" }),
+            ),
+        )
+        .await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+        let metadata: IssuerMetadata = test::read_body_json(resp).await;
+
+        // 新しい鍵で署名し、退役した鍵も検証のために公開し続ける
+        let current_key = key_manager.get_current_key().unwrap();
+        assert_eq!(current_key.kid, "key-2");
+        assert_eq!(
+            current_key.public_key.algorithm(),
+            SignatureAlgorithm::ES256
+        );
+        assert_eq!(
+            metadata.id,
+            did::key::did_key(&current_key.public_key).unwrap()
+        );
+        let statuses: Vec<_> = metadata
+            .public_keys
+            .iter()
+            .map(|info| info.status.unwrap())
+            .collect();
+        assert_eq!(statuses, vec![KeyStatus::Retired, KeyStatus::Active]);
+        assert_eq!(metadata.public_key.id, metadata.public_keys[1].id);
+
+        let after_rotation = sign_credential_with(key_manager.as_ref());
+        let options = metadata_resolver(&metadata);
+        for credential in [&before_rotation, &after_rotation] {
+            let result = verify_credential(credential, &options);
+            assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+        }
+
+        // did:web の DID ドキュメントにも退役した鍵を含める
+        let document = crypto::did_document("did:web:example.com", key_manager.as_ref()).unwrap();
+        assert_eq!(
+            document.assertion_method,
+            vec!["did:web:example.com#key-1", "did:web:example.com#key-2"]
+        );
+
+        // 失効させた鍵は公開せず、それで署名したクレデンシャルは検証できない
+        let resp = test::call_service(
+            &app,
+            rotate(
+                "test-admin-token",
+                serde_json::json!({ "revokeCurrent": true }),
+            ),
+        )
+        .await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+        let metadata: IssuerMetadata = test::read_body_json(resp).await;
+        let document = crypto::did_document("did:web:example.com", key_manager.as_ref()).unwrap();
+
+        // 他のプロセスが鍵ファイルを更新しても、読み直すまでは読み込んだ鍵を使う
+        FileKeyManager::new(path.to_string_lossy().to_string())
+            .rotate_key(None, false)
+            .unwrap();
+        assert_eq!(key_manager.get_current_key().unwrap().kid, "key-3");
+        let reload = |token: &str| {
+            test::TestRequest::post()
+                .uri("/issuer/admin/keys/reload")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+        let resp = test::call_service(&app, reload("wrong-token")).await;
+        assert_eq!(resp.status().as_u16(), 401);
+        let resp = test::call_service(&app, reload("test-admin-token")).await;
+        assert!(resp.status().is_success(), "{:?}", resp.status());
+        assert_eq!(key_manager.get_current_key().unwrap().kid, "key-4");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(metadata.public_keys.len(), 2);
+        assert_eq!(
+            document.assertion_method,
+            vec!["did:web:example.com#key-1", "did:web:example.com#key-3"]
+        );
+        let options = metadata_resolver(&metadata);
+        let result = verify_credential(&before_rotation, &options);
+        assert!(result.is_ok(), "Verification failed: {:?}", result.err());
+        let result = verify_credential(&after_rotation, &options);
+        assert!(matches!(result, Err(VerifierError::UnknownIssuer(_))));
+    }
 }